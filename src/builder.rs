@@ -5,10 +5,13 @@
 
 use std::collections::HashMap;
 use std::path::Path;
+use std::sync::Arc;
 
 use crate::cache::TemplateCache;
+use crate::constraints::{ConstraintAttributes, ConstraintEvaluator, ConstraintFunctionRegistry};
 use crate::error::{Error, Result};
 use crate::handlers::{ElementHandler, HandlerRegistry};
+use crate::program::MissingPath;
 use crate::types::*;
 
 /// Builder for constructing HtmlTemplate instances
@@ -35,6 +38,7 @@ pub struct HtmlTemplateBuilder {
     config: TemplateConfig,
     handlers: HashMap<String, Box<dyn ElementHandler>>,
     handler_registry: Option<HandlerRegistry>,
+    constraint_functions: Option<ConstraintFunctionRegistry>,
     custom_cache: Option<TemplateCache>,
 }
 
@@ -55,6 +59,7 @@ impl HtmlTemplateBuilder {
             config: TemplateConfig::default(),
             handlers: HashMap::new(),
             handler_registry: None,
+            constraint_functions: None,
             custom_cache: None,
         }
     }
@@ -108,6 +113,12 @@ impl HtmlTemplateBuilder {
         self
     }
 
+    /// Resolve relative URL attributes in rendered output against `base_url`
+    pub fn with_base_url<S: Into<String>>(mut self, base_url: S) -> Self {
+        self.config = self.config.with_base_url(base_url);
+        self
+    }
+
     /// Use a custom template configuration
     pub fn with_config(mut self, config: TemplateConfig) -> Self {
         self.config = config;
@@ -156,6 +167,50 @@ impl HtmlTemplateBuilder {
         self
     }
 
+    /// Use a registry of functions that `data-constraint` expressions can call
+    ///
+    /// Replaces the built-in functions unless the registry was created with
+    /// [`ConstraintFunctionRegistry::with_defaults`].
+    pub fn with_constraint_functions(mut self, functions: ConstraintFunctionRegistry) -> Self {
+        self.constraint_functions = Some(functions);
+        self
+    }
+
+    /// Add a function that `data-constraint` expressions can call, alongside
+    /// the built-in functions
+    pub fn register_constraint_function<S, F>(mut self, name: S, function: F) -> Self
+    where
+        S: Into<String>,
+        F: Fn(&[serde_json::Value]) -> Result<serde_json::Value> + Send + Sync + 'static,
+    {
+        self.constraint_functions
+            .get_or_insert_with(ConstraintFunctionRegistry::with_defaults)
+            .register(name, function);
+        self
+    }
+
+    /// Evaluate constraint expressions with `evaluator` instead of the
+    /// built-in grammar
+    ///
+    /// The evaluator receives each expression as written in the template, so
+    /// functions registered with
+    /// [`register_constraint_function`](Self::register_constraint_function)
+    /// only apply if it delegates to the built-in grammar.
+    pub fn with_constraint_evaluator<E>(mut self, evaluator: E) -> Self
+    where
+        E: ConstraintEvaluator + 'static,
+    {
+        self.config = self.config.with_constraint_evaluator(evaluator);
+        self
+    }
+
+    /// Read constraints from these attributes instead of `data-constraint`,
+    /// `data-else-if` and `data-else`
+    pub fn with_constraint_attributes(mut self, attributes: ConstraintAttributes) -> Self {
+        self.config = self.config.with_constraint_attributes(attributes);
+        self
+    }
+
     /// Use a custom cache instance instead of the global cache
     pub fn with_cache(mut self, cache: TemplateCache) -> Self {
         self.custom_cache = Some(cache);
@@ -163,14 +218,31 @@ impl HtmlTemplateBuilder {
     }
 
     /// Configure for aggressive caching with all optimizations enabled
-    pub fn aggressive_performance(mut self) -> Self {
-        self.config = TemplateConfig::aggressive_caching();
-        self
+    ///
+    /// Only the caching settings change; the base URL and constraint
+    /// settings are kept whichever order they are set in.
+    pub fn aggressive_performance(self) -> Self {
+        self.with_caching_of(TemplateConfig::aggressive_caching())
     }
 
     /// Configure for no caching (useful for development or one-time rendering)
-    pub fn no_caching(mut self) -> Self {
-        self.config = TemplateConfig::no_caching();
+    ///
+    /// Only the caching settings change; the base URL and constraint
+    /// settings are kept whichever order they are set in.
+    pub fn no_caching(self) -> Self {
+        self.with_caching_of(TemplateConfig::no_caching())
+    }
+
+    /// Take the caching settings of `preset`, keeping the rest of the
+    /// configuration
+    fn with_caching_of(mut self, preset: TemplateConfig) -> Self {
+        self.config = TemplateConfig {
+            cache_mode: preset.cache_mode,
+            zero_copy: preset.zero_copy,
+            cache_compiled_templates: preset.cache_compiled_templates,
+            cache_external_documents: preset.cache_external_documents,
+            ..self.config
+        };
         self
     }
 
@@ -204,19 +276,23 @@ impl HtmlTemplateBuilder {
         };
 
         // Add handlers if any were provided
-        if let Some(registry) = self.handler_registry {
-            // Use the new constructor with HandlerRegistry
-            let compiled = template.compiled.clone();
-            let config = template.config.clone();
-            Ok(HtmlTemplate::new_with_registry(compiled, config, registry))
+        let template = if let Some(registry) = self.handler_registry {
+            HtmlTemplate {
+                handler_registry: Some(Arc::new(registry)),
+                ..template
+            }
         } else if !self.handlers.is_empty() {
-            // Since we can't modify the template after creation, we need to create a new one
-            // with the handlers. This is a limitation of the current API design.
-            let compiled = template.compiled.clone();
-            let config = template.config.clone();
-            Ok(HtmlTemplate::new(compiled, config, self.handlers))
+            HtmlTemplate {
+                handlers: Arc::new(self.handlers),
+                ..template
+            }
         } else {
-            Ok(template)
+            template
+        };
+
+        match self.constraint_functions {
+            Some(functions) => Ok(template.with_constraint_functions(functions)),
+            None => Ok(template),
         }
     }
 
@@ -301,6 +377,7 @@ pub struct RenderBuilder<'a> {
     validate_data: bool,
     error_on_missing: bool,
     custom_handlers: HashMap<String, Box<dyn ElementHandler>>,
+    scopes: Option<Vec<String>>,
 }
 
 impl<'a> RenderBuilder<'a> {
@@ -311,22 +388,33 @@ impl<'a> RenderBuilder<'a> {
             validate_data: false,
             error_on_missing: false,
             custom_handlers: HashMap::new(),
+            scopes: None,
         }
     }
 
-    /// Enable data validation before rendering
+    /// Check the data for paths the template binds but the data lacks,
+    /// reporting them in [`RenderResult::missing_properties`]
     pub fn with_data_validation(mut self, enabled: bool) -> Self {
         self.validate_data = enabled;
         self
     }
 
     /// Configure whether to error on missing properties
+    ///
+    /// In this strict mode, every `itemprop`, `${}` path and constraint path
+    /// that doesn't resolve in the data is collected while rendering, and
+    /// the render fails with [`Error::MissingDataError`] listing each along
+    /// with the selector of the element binding it. Sections left out by
+    /// constraints or scopes aren't checked.
     pub fn error_on_missing_properties(mut self, enabled: bool) -> Self {
         self.error_on_missing = enabled;
         self
     }
 
     /// Add temporary handlers for this render operation
+    ///
+    /// They run instead of the template's own handler for the same tag name,
+    /// and alongside the template's handlers for other tags.
     pub fn with_handler<S: Into<String>>(
         mut self,
         tag_name: S,
@@ -336,11 +424,44 @@ impl<'a> RenderBuilder<'a> {
         self
     }
 
+    /// Keep only the `data-scope` sections whose scope is one of `scopes`,
+    /// such as the caller's roles or feature flags
+    ///
+    /// Without this, every `data-scope` section is rendered.
+    pub fn with_scopes<I, S>(mut self, scopes: I) -> Self
+    where
+        I: IntoIterator<Item = S>,
+        S: Into<String>,
+    {
+        self.scopes = Some(scopes.into_iter().map(Into::into).collect());
+        self
+    }
+
     /// Render the template with the configured options
     pub fn render(self, data: &dyn crate::value::RenderValue) -> Result<String> {
-        // For now, use the basic render method
-        // TODO: Implement validation and custom handler support
-        self.template.render(data)
+        self.render_checked(data, self.error_on_missing, &mut Vec::new())
+    }
+
+    /// Render, collecting the missing paths into `missing` if `check` is set
+    /// and failing on them in strict mode
+    fn render_checked(
+        &self,
+        data: &dyn crate::value::RenderValue,
+        check: bool,
+        missing: &mut Vec<MissingPath>,
+    ) -> Result<String> {
+        let renderer = self
+            .template
+            .renderer(self.scopes.as_deref())
+            .with_extra_handlers(&self.custom_handlers);
+        if !check {
+            return renderer.render(data);
+        }
+        let html = renderer.render_checked(data, missing)?;
+        if self.error_on_missing && !missing.is_empty() {
+            return Err(Error::MissingDataError(std::mem::take(missing)));
+        }
+        Ok(html)
     }
 
     /// Render and return additional metadata about the rendering process
@@ -349,14 +470,19 @@ impl<'a> RenderBuilder<'a> {
         data: &dyn crate::value::RenderValue,
     ) -> Result<RenderResult> {
         let start_time = std::time::Instant::now();
-        let rendered = self.render(data)?;
+        let mut missing = Vec::new();
+        let rendered = self.render_checked(
+            data,
+            self.validate_data || self.error_on_missing,
+            &mut missing,
+        )?;
         let duration = start_time.elapsed();
 
         Ok(RenderResult {
             html: rendered,
             duration,
-            properties_used: Vec::new(),    // TODO: Track property usage
-            missing_properties: Vec::new(), // TODO: Track missing properties
+            properties_used: Vec::new(), // TODO: Track property usage
+            missing_properties: missing.into_iter().map(|missing| missing.path).collect(),
         })
     }
 }
@@ -370,7 +496,8 @@ pub struct RenderResult {
     pub duration: std::time::Duration,
     /// Properties that were accessed during rendering
     pub properties_used: Vec<String>,
-    /// Properties that were requested but not found in data
+    /// Properties that were requested but not found in data, collected when
+    /// data validation or strict mode is enabled
     pub missing_properties: Vec<String>,
 }
 
@@ -675,6 +802,102 @@ mod tests {
         let _result = template.render(&data).unwrap();
     }
 
+    #[test]
+    fn test_builder_register_constraint_function() {
+        let html = r#"
+            <template>
+                <div>
+                    <p data-constraint="isEven(count)">Even</p>
+                    <p data-constraint="len(items) > 1">Several</p>
+                </div>
+            </template>
+        "#;
+        let template = HtmlTemplateBuilder::new()
+            .from_str(html)
+            .register_constraint_function("isEven", |args| {
+                Ok(json!(args[0].as_i64().is_some_and(|n| n % 2 == 0)))
+            })
+            .no_caching()
+            .build()
+            .unwrap();
+
+        let result = template
+            .render(&json!({"count": 4, "items": [1, 2]}))
+            .unwrap();
+        assert!(result.contains("Even"));
+        assert!(result.contains("Several"));
+
+        let result = template.render(&json!({"count": 3, "items": [1]})).unwrap();
+        assert!(!result.contains("Even"));
+        assert!(!result.contains("Several"));
+    }
+
+    #[test]
+    fn test_caching_presets_keep_other_settings() {
+        let html = r#"
+            <template>
+                <div>
+                    <a href="/about">About</a>
+                    <p data-if="count > 0">Some</p>
+                </div>
+            </template>
+        "#;
+        let attributes = || ConstraintAttributes::new("data-if", "data-elif", "data-otherwise");
+        let before = HtmlTemplateBuilder::new()
+            .from_str(html)
+            .with_base_url("https://example.com/")
+            .with_constraint_attributes(attributes())
+            .no_caching()
+            .build()
+            .unwrap();
+        let after = HtmlTemplateBuilder::new()
+            .from_str(html)
+            .aggressive_performance()
+            .with_base_url("https://example.com/")
+            .with_constraint_attributes(attributes())
+            .build()
+            .unwrap();
+
+        assert_eq!(before.config.cache_mode(), CacheMode::None);
+        assert_eq!(after.config.cache_mode(), CacheMode::Aggressive);
+        for template in [before, after] {
+            let result = template.render(&json!({"count": 0})).unwrap();
+            assert!(result.contains(r#"href="https://example.com/about""#));
+            assert!(!result.contains("Some"));
+        }
+    }
+
+    #[test]
+    fn test_builder_with_constraint_attributes() {
+        let html = r#"
+            <template>
+                <div>
+                    <p data-if="count > 10">Many</p>
+                    <p data-elif="count > 0">Some</p>
+                    <p data-otherwise>None</p>
+                </div>
+            </template>
+        "#;
+        let template = HtmlTemplateBuilder::new()
+            .from_str(html)
+            .no_caching()
+            .with_constraint_attributes(ConstraintAttributes::new(
+                "data-if",
+                "data-elif",
+                "data-otherwise",
+            ))
+            .build()
+            .unwrap();
+
+        let result = template.render(&json!({"count": 3})).unwrap();
+        assert!(!result.contains("Many"));
+        assert!(result.contains("Some"));
+        assert!(!result.contains("None"));
+
+        let result = template.render(&json!({"count": 0})).unwrap();
+        assert!(result.contains("None"));
+    }
+
     #[test]
     fn test_builder_with_custom_cache() {
         use crate::cache::{CacheConfig, TemplateCache};
@@ -721,13 +944,23 @@ mod tests {
 
         let data = json!({"content": "test"});
 
-        // Note: RenderBuilder's with_handler doesn't actually affect rendering yet
         let result = RenderBuilder::new(&template)
             .with_handler("div", Box::new(ClassHandler::new()))
             .render(&data)
             .unwrap();
-
         assert!(result.contains("test"));
+        assert!(result.contains(r#"class="has-content""#));
+
+        // The handler applies only to the render it was added to
+        assert!(!template.render(&data).unwrap().contains("has-content"));
+
+        // Strict mode runs it too
+        let result = RenderBuilder::new(&template)
+            .with_handler("div", Box::new(ClassHandler::new()))
+            .error_on_missing_properties(true)
+            .render(&json!({"content": ""}))
+            .unwrap();
+        assert!(result.contains(r#"class="empty""#));
     }
 
     #[test]
@@ -742,12 +975,148 @@ mod tests {
 
         let data = json!({});
 
-        // Note: error_on_missing_properties is not implemented yet
+        let error = RenderBuilder::new(&template)
+            .error_on_missing_properties(true)
+            .render(&data)
+            .unwrap_err();
+        assert!(error
+            .to_string()
+            .contains(r#"required (at [itemprop="required"])"#));
+
+        // Without strict mode the element renders empty
+        assert!(RenderBuilder::new(&template).render(&data).is_ok());
+        let data = json!({"required": "here"});
+        let result = RenderBuilder::new(&template)
+            .error_on_missing_properties(true)
+            .render(&data)
+            .unwrap();
+        assert!(result.contains("here"));
+    }
+
+    #[test]
+    fn test_render_builder_strict_mode_lists_every_path() {
+        let html = r#"
+            <template>
+                <article>
+                    <h1 itemprop="title"></h1>
+                    <div itemprop="author" itemscope>
+                        <span itemprop="name"></span>
+                    </div>
+                    <ul>
+                        <li itemprop="items[]" data-constraint="visible">${label}</li>
+                    </ul>
+                    <p class="note" data-constraint="showNote">
+                        <span itemprop="note"></span>
+                    </p>
+                </article>
+            </template>
+        "#;
+        let template = HtmlTemplateBuilder::new()
+            .from_str(html)
+            .with_selector("article")
+            .no_caching()
+            .build()
+            .unwrap();
+        let data = json!({
+            "author": {},
+            "items": [{"visible": true, "label": "a"}, {"visible": true}],
+            "showNote": false
+        });
+
+        let error = RenderBuilder::new(&template)
+            .error_on_missing_properties(true)
+            .render(&data)
+            .unwrap_err();
+        assert_eq!(
+            error.to_string(),
+            "Missing data: 3 bound path(s) don't resolve:\n  \
+             title (at [itemprop=\"title\"])\n  \
+             author.name (at [itemprop=\"name\"])\n  \
+             items[1].label (at [itemprop=\"items[]\"])"
+        );
+        let Error::MissingDataError(missing) = error else {
+            panic!("expected a missing data error");
+        };
+        assert_eq!(
+            missing[1],
+            MissingPath {
+                path: "author.name".to_string(),
+                selector: r#"[itemprop="name"]"#.to_string(),
+            }
+        );
+
+        // The note is hidden by its constraint, so it isn't checked
         let result = RenderBuilder::new(&template)
+            .with_data_validation(true)
+            .render_with_metadata(&data)
+            .unwrap();
+        assert!(result.html.contains("<li"));
+        assert_eq!(
+            result.missing_properties,
+            vec!["title", "author.name", "items[1].label"]
+        );
+    }
+
+    #[test]
+    fn test_render_builder_strict_mode_constraint_paths() {
+        let html = r#"
+            <template>
+                <section>
+                    <p data-constraint="user.isAdmin && count > 2">Admin</p>
+                    <p data-else>Guest</p>
+                </section>
+            </template>
+        "#;
+        let template = HtmlTemplateBuilder::new()
+            .from_str(html)
+            .with_selector("section")
+            .with_default_handlers()
+            .no_caching()
+            .build()
+            .unwrap();
+
+        let error = RenderBuilder::new(&template)
             .error_on_missing_properties(true)
-            .render(&data);
+            .render(&json!({"user": {}}))
+            .unwrap_err()
+            .to_string();
+        assert!(error.contains("2 bound path(s)"));
+        assert!(
+            error.contains(r#"user.isAdmin (at p[data-constraint="user.isAdmin && count > 2"])"#)
+        );
+        assert!(error.contains("\n  count (at p["));
+
+        let data = json!({"user": {"isAdmin": true}, "count": 3});
+        let result = RenderBuilder::new(&template)
+            .error_on_missing_properties(true)
+            .render(&data)
+            .unwrap();
+        assert!(result.contains("Admin"));
+    }
+
+    #[test]
+    fn test_render_builder_strict_mode_with_handlers() {
+        let html = r#"<template><form><input itemprop="q"><textarea itemprop="note"></textarea></form></template>"#;
+        let template = HtmlTemplateBuilder::new()
+            .from_str(html)
+            .with_selector("form")
+            .with_default_handlers()
+            .no_caching()
+            .build()
+            .unwrap();
 
-        // Currently this doesn't error, it just renders empty
-        assert!(result.is_ok());
+        // Paths bound inside handled elements are collected while rendering
+        let result = RenderBuilder::new(&template)
+            .with_data_validation(true)
+            .render_with_metadata(&json!({"q": "rust"}))
+            .unwrap();
+        assert!(result.html.contains(r#"<input itemprop="q" value="rust">"#));
+        assert_eq!(result.missing_properties, vec!["note"]);
+
+        let error = RenderBuilder::new(&template)
+            .error_on_missing_properties(true)
+            .render(&json!({"note": "hi"}))
+            .unwrap_err();
+        assert!(matches!(error, Error::MissingDataError(ref missing) if missing.len() == 1));
     }
 }
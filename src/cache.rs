@@ -4,10 +4,17 @@
 //! repeated parsing, compilation, and network requests.
 
 use std::collections::HashMap;
-use std::hash::Hash;
-use std::sync::{Arc, OnceLock, RwLock};
-use std::time::{Duration, Instant};
+use std::fs;
+use std::hash::{Hash, Hasher};
+use std::io::{self, Write};
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::{Arc, Mutex, OnceLock, PoisonError, RwLock};
+use std::time::{Duration, Instant, SystemTime, UNIX_EPOCH};
 
+use serde::{Deserialize, Serialize};
+
+use crate::constraints::{ConstraintAttributes, ConstraintEvaluator};
 use crate::error::Result;
 use crate::types::*;
 
@@ -53,6 +60,24 @@ impl<T> CacheEntry<T> {
     pub fn age(&self) -> Duration {
         Instant::now().duration_since(self.created_at)
     }
+
+    /// Restart this entry's lifetime with a new TTL
+    pub fn refresh(&mut self, ttl: Option<Duration>) {
+        let now = Instant::now();
+        self.created_at = now;
+        self.expires_at = ttl.map(|duration| now + duration);
+    }
+}
+
+/// Result of looking up a key with [`Cache::lookup`]
+#[derive(Debug, Clone, PartialEq)]
+pub enum CacheLookup<V> {
+    /// The entry exists and has not expired
+    Fresh(V),
+    /// The entry exists but has expired and should be revalidated
+    Stale(V),
+    /// No entry exists for the key
+    Missing,
 }
 
 /// Cache eviction strategy
@@ -74,8 +99,10 @@ pub struct Cache<K, V> {
     max_size: usize,
     default_ttl: Option<Duration>,
     eviction_strategy: EvictionStrategy,
+    keep_stale: bool,
     hits: usize,
     misses: usize,
+    revalidations: usize,
 }
 
 impl<K, V> Cache<K, V>
@@ -94,11 +121,22 @@ where
             max_size,
             default_ttl,
             eviction_strategy,
+            keep_stale: false,
             hits: 0,
             misses: 0,
+            revalidations: 0,
         }
     }
 
+    /// Keep expired entries until they are evicted so they can be revalidated
+    ///
+    /// Expired entries are never returned by [`get`](Self::get); use
+    /// [`lookup`](Self::lookup) to retrieve them for revalidation.
+    pub fn with_stale_entries(mut self) -> Self {
+        self.keep_stale = true;
+        self
+    }
+
     /// Insert a value into the cache
     pub fn insert(&mut self, key: K, value: V) -> Option<V> {
         self.insert_with_ttl(key, value, self.default_ttl)
@@ -127,7 +165,9 @@ where
 
         if let Some(entry) = self.entries.get_mut(key) {
             if entry.is_expired() {
-                self.entries.remove(key);
+                if !self.keep_stale {
+                    self.entries.remove(key);
+                }
                 self.misses += 1;
                 None
             } else {
@@ -141,6 +181,56 @@ where
         }
     }
 
+    /// Look up a value, distinguishing expired entries from missing ones
+    ///
+    /// Fresh entries count as hits and missing entries as misses. Stale entries
+    /// are not counted until the caller resolves them with
+    /// [`revalidate`](Self::revalidate) or [`record_miss`](Self::record_miss).
+    pub fn lookup(&mut self, key: &K) -> CacheLookup<V> {
+        self.cleanup_expired();
+
+        match self.entries.get_mut(key) {
+            Some(entry) if entry.is_expired() => CacheLookup::Stale(entry.value.clone()),
+            Some(entry) => {
+                entry.mark_accessed();
+                self.hits += 1;
+                CacheLookup::Fresh(entry.value.clone())
+            }
+            None => {
+                self.misses += 1;
+                CacheLookup::Missing
+            }
+        }
+    }
+
+    /// Mark an entry as still valid, restarting it with the default TTL
+    ///
+    /// Returns `false` if the key is not in the cache.
+    pub fn revalidate(&mut self, key: &K) -> bool {
+        let ttl = self.default_ttl;
+        match self.entries.get_mut(key) {
+            Some(entry) => {
+                entry.refresh(ttl);
+                entry.mark_accessed();
+                self.revalidations += 1;
+                true
+            }
+            None => false,
+        }
+    }
+
+    /// Count a miss for a lookup resolved outside the cache, such as a stale
+    /// entry whose value had changed
+    pub fn record_miss(&mut self) {
+        self.misses += 1;
+    }
+
+    /// Count a revalidation resolved outside the cache, such as a stale
+    /// value loaded from elsewhere and confirmed unchanged
+    pub fn record_revalidation(&mut self) {
+        self.revalidations += 1;
+    }
+
     /// Check if a key exists in the cache (without affecting access statistics)
     pub fn contains_key(&self, key: &K) -> bool {
         if let Some(entry) = self.entries.get(key) {
@@ -160,6 +250,7 @@ where
         self.entries.clear();
         self.hits = 0;
         self.misses = 0;
+        self.revalidations = 0;
     }
 
     /// Get cache statistics
@@ -167,6 +258,7 @@ where
         CacheStats {
             hits: self.hits,
             misses: self.misses,
+            revalidations: self.revalidations,
             hit_rate: if self.hits + self.misses > 0 {
                 self.hits as f64 / (self.hits + self.misses) as f64
             } else {
@@ -179,6 +271,10 @@ where
 
     /// Remove expired entries
     fn cleanup_expired(&mut self) {
+        if self.keep_stale {
+            return;
+        }
+
         let now = Instant::now();
         self.entries.retain(|_, entry| {
             if let Some(expires_at) = entry.expires_at {
@@ -234,6 +330,8 @@ where
 pub struct CacheStats {
     pub hits: usize,
     pub misses: usize,
+    /// Expired entries confirmed unchanged and kept; not counted in `hit_rate`
+    pub revalidations: usize,
     pub hit_rate: f64,
     pub entry_count: usize,
     pub max_size: usize,
@@ -244,6 +342,10 @@ pub struct CacheStats {
 pub struct TemplateCacheKey {
     pub html: String,
     pub root_selector: Option<String>,
+    /// Attribute names the template's constraints were read from
+    constraint_attributes: ConstraintAttributes,
+    /// The custom evaluator that validated the template's constraints, if any
+    constraint_evaluator: Option<EvaluatorIdentity>,
 }
 
 impl TemplateCacheKey {
@@ -251,10 +353,58 @@ impl TemplateCacheKey {
         Self {
             html: html.to_string(),
             root_selector: root_selector.map(|s| s.to_string()),
+            constraint_attributes: ConstraintAttributes::default(),
+            constraint_evaluator: None,
+        }
+    }
+
+    /// Key a template compiled with `config`, which decides how its
+    /// constraints are compiled
+    ///
+    /// Templates compiled with different evaluator instances never share an
+    /// entry, since each evaluator validates expressions its own way.
+    pub fn for_config(html: &str, root_selector: Option<&str>, config: &TemplateConfig) -> Self {
+        Self {
+            constraint_attributes: config.constraint_attributes().clone(),
+            constraint_evaluator: config.constraint_evaluator.clone().map(EvaluatorIdentity),
+            ..Self::new(html, root_selector)
         }
     }
 }
 
+/// A constraint evaluator compared by address
+///
+/// The key holds on to the evaluator, so its address can't be reused by
+/// another evaluator while the entry is cached.
+#[derive(Clone)]
+struct EvaluatorIdentity(Arc<dyn ConstraintEvaluator>);
+
+impl EvaluatorIdentity {
+    fn address(&self) -> *const () {
+        Arc::as_ptr(&self.0) as *const ()
+    }
+}
+
+impl PartialEq for EvaluatorIdentity {
+    fn eq(&self, other: &Self) -> bool {
+        self.address() == other.address()
+    }
+}
+
+impl Eq for EvaluatorIdentity {}
+
+impl Hash for EvaluatorIdentity {
+    fn hash<H: Hasher>(&self, state: &mut H) {
+        self.address().hash(state);
+    }
+}
+
+impl std::fmt::Debug for EvaluatorIdentity {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "ConstraintEvaluator({:p})", self.address())
+    }
+}
+
 /// External document cache key
 #[derive(Debug, Clone, PartialEq, Eq, Hash)]
 pub struct DocumentCacheKey {
@@ -289,11 +439,298 @@ pub struct CachedDocument {
     pub last_modified: Option<String>,
 }
 
+impl CachedDocument {
+    /// Create a document holding HTML content with no validators
+    pub fn html(content: impl Into<String>) -> Self {
+        Self {
+            content: content.into(),
+            content_type: Some("text/html".to_string()),
+            etag: None,
+            last_modified: None,
+        }
+    }
+    /// Check if the document has an ETag or Last-Modified validator
+    pub fn has_validators(&self) -> bool {
+        self.etag.is_some() || self.last_modified.is_some()
+    }
+}
+
+/// Disk-backed store for external documents
+///
+/// Each document is written to its own JSON file in the store's directory,
+/// named by a stable hash of its [`DocumentCacheKey`], so the store can be
+/// shared across processes and survives restarts. Once the files exceed the
+/// size cap, the least recently written ones are removed.
+///
+/// Clones share a running total of the stored size, so the directory is only
+/// scanned on the first write and when documents need evicting.
+#[derive(Debug, Clone)]
+pub struct DiskDocumentStore {
+    dir: PathBuf,
+    ttl: Option<Duration>,
+    max_bytes: u64,
+    /// Bytes stored, once the directory has been scanned
+    stored_bytes: Arc<Mutex<Option<u64>>>,
+}
+
+/// A document as persisted by [`DiskDocumentStore`]
+#[derive(Serialize, Deserialize)]
+struct DiskEntry {
+    url: String,
+    headers: Vec<(String, String)>,
+    content: String,
+    content_type: Option<String>,
+    etag: Option<String>,
+    last_modified: Option<String>,
+    /// When the document was stored or last revalidated, in milliseconds
+    /// since the Unix epoch
+    stored_at: u64,
+}
+
+impl DiskDocumentStore {
+    /// Create a store in `dir`, which is created on first write
+    ///
+    /// Entries older than `ttl` are returned as stale, and the total size of
+    /// stored files is kept below `max_bytes`.
+    pub fn new(dir: impl Into<PathBuf>, ttl: Option<Duration>, max_bytes: u64) -> Self {
+        Self {
+            dir: dir.into(),
+            ttl,
+            max_bytes,
+            stored_bytes: Arc::new(Mutex::new(None)),
+        }
+    }
+
+    /// Get the directory documents are stored in
+    pub fn dir(&self) -> &Path {
+        &self.dir
+    }
+
+    /// Look up a stored document
+    ///
+    /// Unreadable or corrupt files are treated as missing.
+    pub fn lookup(&self, key: &DocumentCacheKey) -> CacheLookup<CachedDocument> {
+        let entry = match self.read_entry(key) {
+            Some(entry) => entry,
+            None => return CacheLookup::Missing,
+        };
+
+        let age = Duration::from_millis(now_millis().saturating_sub(entry.stored_at));
+        let expired = self.ttl.is_some_and(|ttl| age > ttl);
+        let document = CachedDocument {
+            content: entry.content,
+            content_type: entry.content_type,
+            etag: entry.etag,
+            last_modified: entry.last_modified,
+        };
+
+        if expired {
+            CacheLookup::Stale(document)
+        } else {
+            CacheLookup::Fresh(document)
+        }
+    }
+
+    /// Get how long a stored document has left before it expires
+    pub fn remaining_ttl(&self, key: &DocumentCacheKey) -> Option<Duration> {
+        let entry = self.read_entry(key)?;
+        let age = Duration::from_millis(now_millis().saturating_sub(entry.stored_at));
+        self.ttl.map(|ttl| ttl.saturating_sub(age))
+    }
+
+    /// Store a document, then evict old documents to stay within the size cap
+    pub fn store(&self, key: &DocumentCacheKey, document: &CachedDocument) -> Result<()> {
+        let entry = DiskEntry {
+            url: key.url.clone(),
+            headers: key.headers.clone(),
+            content: document.content.clone(),
+            content_type: document.content_type.clone(),
+            etag: document.etag.clone(),
+            last_modified: document.last_modified.clone(),
+            stored_at: now_millis(),
+        };
+        self.write_entry(key, &entry)
+    }
+
+    /// Restart the lifetime of a stored document confirmed to be unchanged
+    ///
+    /// Returns `false` if the document is not stored.
+    pub fn revalidate(&self, key: &DocumentCacheKey) -> Result<bool> {
+        match self.read_entry(key) {
+            Some(mut entry) => {
+                entry.stored_at = now_millis();
+                self.write_entry(key, &entry)?;
+                Ok(true)
+            }
+            None => Ok(false),
+        }
+    }
+
+    /// Remove a stored document
+    pub fn remove(&self, key: &DocumentCacheKey) -> Result<()> {
+        let path = self.path(key);
+        let mut stored_bytes = self.lock_stored_bytes();
+        let size = file_size(&path);
+        match fs::remove_file(path) {
+            Err(e) if e.kind() != io::ErrorKind::NotFound => Err(e.into()),
+            _ => self.track_size(&mut stored_bytes, size, 0),
+        }
+    }
+
+    /// Remove every stored document
+    pub fn clear(&self) -> Result<()> {
+        for (path, _, _) in self.stored_files()? {
+            fs::remove_file(path)?;
+        }
+        *self.lock_stored_bytes() = Some(0);
+        Ok(())
+    }
+
+    /// Get the total size in bytes of the stored documents
+    pub fn size(&self) -> Result<u64> {
+        Ok(self.stored_files()?.iter().map(|(_, size, _)| size).sum())
+    }
+
+    /// Path of the file holding the document for `key`
+    fn path(&self, key: &DocumentCacheKey) -> PathBuf {
+        // FNV-1a, so file names stay the same across processes and builds
+        let mut hash: u64 = 0xcbf2_9ce4_8422_2325;
+        let mut write = |bytes: &[u8]| {
+            for byte in bytes {
+                hash ^= u64::from(*byte);
+                hash = hash.wrapping_mul(0x0100_0000_01b3);
+            }
+        };
+        write(key.url.as_bytes());
+        for (name, value) in &key.headers {
+            write(b"\n");
+            write(name.as_bytes());
+            write(b": ");
+            write(value.as_bytes());
+        }
+        self.dir.join(format!("{:016x}.json", hash))
+    }
+
+    /// Read the entry for `key`, ignoring files that belong to another key
+    fn read_entry(&self, key: &DocumentCacheKey) -> Option<DiskEntry> {
+        let bytes = fs::read(self.path(key)).ok()?;
+        let entry: DiskEntry = serde_json::from_slice(&bytes).ok()?;
+        (entry.url == key.url && entry.headers == key.headers).then_some(entry)
+    }
+
+    /// Write an entry atomically, so concurrent readers never see partial files
+    fn write_entry(&self, key: &DocumentCacheKey, entry: &DiskEntry) -> Result<()> {
+        // Each write gets its own temporary file, even between threads
+        static WRITES: AtomicU64 = AtomicU64::new(0);
+
+        fs::create_dir_all(&self.dir)?;
+        let path = self.path(key);
+        let temp = path.with_extension(format!(
+            "tmp{}-{}",
+            std::process::id(),
+            WRITES.fetch_add(1, Ordering::Relaxed)
+        ));
+        let bytes = serde_json::to_vec(entry)?;
+        let mut file = fs::File::create(&temp)?;
+        file.write_all(&bytes)?;
+        file.sync_all()?;
+
+        // Only the rename is serialized, so the replaced size stays accurate
+        let mut stored_bytes = self.lock_stored_bytes();
+        let replaced = file_size(&path);
+        fs::rename(&temp, &path)?;
+        self.track_size(&mut stored_bytes, replaced, bytes.len() as u64)
+    }
+
+    /// Update the running size after a file of `removed` bytes is replaced by
+    /// one of `added` bytes, evicting documents if the store exceeds its cap
+    fn track_size(&self, stored_bytes: &mut Option<u64>, removed: u64, added: u64) -> Result<()> {
+        let total = match *stored_bytes {
+            Some(total) => total.saturating_sub(removed) + added,
+            None => self.stored_files()?.iter().map(|(_, size, _)| size).sum(),
+        };
+        *stored_bytes = Some(if total > self.max_bytes {
+            self.enforce_size_cap()?
+        } else {
+            total
+        });
+        Ok(())
+    }
+
+    fn lock_stored_bytes(&self) -> std::sync::MutexGuard<'_, Option<u64>> {
+        self.stored_bytes
+            .lock()
+            .unwrap_or_else(PoisonError::into_inner)
+    }
+
+    /// Remove the least recently written documents until the store fits its
+    /// cap, returning the size left
+    fn enforce_size_cap(&self) -> Result<u64> {
+        let mut files = self.stored_files()?;
+        let mut total: u64 = files.iter().map(|(_, size, _)| size).sum();
+        files.sort_by_key(|(_, _, modified)| *modified);
+
+        for (path, size, _) in files {
+            if total <= self.max_bytes {
+                break;
+            }
+            fs::remove_file(path)?;
+            total -= size;
+        }
+        Ok(total)
+    }
+
+    /// List the stored document files with their size and modification time
+    fn stored_files(&self) -> Result<Vec<StoredFile>> {
+        let entries = match fs::read_dir(&self.dir) {
+            Ok(entries) => entries,
+            Err(e) if e.kind() == io::ErrorKind::NotFound => return Ok(Vec::new()),
+            Err(e) => return Err(e.into()),
+        };
+
+        let mut files = Vec::new();
+        for entry in entries {
+            let entry = entry?;
+            let path = entry.path();
+            if path
+                .extension()
+                .is_some_and(|extension| extension == "json")
+            {
+                let metadata = entry.metadata()?;
+                files.push((path, metadata.len(), metadata.modified()?));
+            }
+        }
+        Ok(files)
+    }
+}
+
+/// A document file's path, size and modification time
+type StoredFile = (PathBuf, u64, SystemTime);
+
+/// Size of a file in bytes, or zero if it does not exist
+fn file_size(path: &Path) -> u64 {
+    fs::metadata(path).map_or(0, |metadata| metadata.len())
+}
+
+/// Current time in milliseconds since the Unix epoch
+fn now_millis() -> u64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map_or(0, |elapsed| elapsed.as_millis() as u64)
+}
+
 /// Template cache manager
+///
+/// Clones share the same underlying caches.
+#[derive(Clone)]
 pub struct TemplateCache {
     parsed_templates: Arc<RwLock<Cache<TemplateCacheKey, HtmlTemplate>>>,
     compiled_templates: Arc<RwLock<Cache<TemplateCacheKey, Arc<CompiledTemplate>>>>,
     external_documents: Arc<RwLock<Cache<DocumentCacheKey, CachedDocument>>>,
+    /// Persistent store behind `external_documents`, if configured
+    document_store: Option<DiskDocumentStore>,
+    /// Serve expired documents without revalidating them
+    offline: bool,
     // Note: CSS selector caching disabled due to lifetime issues with dom_query::Selection
     // css_selectors: Arc<RwLock<Cache<String, dom_query::Selection>>>,
 }
@@ -317,15 +754,27 @@ impl TemplateCache {
                 config.compiled_ttl,
                 config.eviction_strategy,
             ))),
-            external_documents: Arc::new(RwLock::new(Cache::new(
-                config.document_cache_size,
-                config.document_ttl,
-                config.eviction_strategy,
-            ))),
+            external_documents: Arc::new(RwLock::new(
+                Cache::new(
+                    config.document_cache_size,
+                    config.document_ttl,
+                    config.eviction_strategy,
+                )
+                .with_stale_entries(),
+            )),
+            document_store: config.document_dir.map(|dir| {
+                DiskDocumentStore::new(dir, config.document_ttl, config.document_dir_max_bytes)
+            }),
+            offline: config.offline,
             // css_selectors disabled
         }
     }
 
+    /// Get the persistent document store, if one is configured
+    pub fn document_store(&self) -> Option<&DiskDocumentStore> {
+        self.document_store.as_ref()
+    }
+
     /// Get or create a parsed template
     pub fn get_or_parse_template<F>(
         &self,
@@ -390,23 +839,139 @@ impl TemplateCache {
         F: FnOnce() -> Result<CachedDocument>,
     {
         // Try to get from cache first
-        if let Ok(mut cache) = self.external_documents.write() {
-            if let Some(document) = cache.get(key) {
-                return Ok(document);
-            }
-        }
+        let replaced_stale = match self.lookup_document(key) {
+            CacheLookup::Fresh(document) => return Ok(document),
+            CacheLookup::Stale(_) => true,
+            CacheLookup::Missing => false,
+        };
 
         // Not in cache, fetch it
         let document = fetcher()?;
 
         // Store in cache
-        if let Ok(mut cache) = self.external_documents.write() {
-            cache.insert(key.clone(), document.clone());
-        }
+        self.store_document(key, document.clone(), replaced_stale);
 
         Ok(document)
     }
 
+    /// Get an external document, revalidating it once it has expired
+    ///
+    /// Fresh documents are returned from the cache. Expired documents are passed
+    /// to `revalidate`, which returns `None` if the document is unchanged (the
+    /// entry's TTL is then refreshed) or the new document. Documents not in the
+    /// cache are loaded with `fetch`.
+    pub fn get_or_revalidate_document<F, R>(
+        &self,
+        key: &DocumentCacheKey,
+        fetch: F,
+        revalidate: R,
+    ) -> Result<CachedDocument>
+    where
+        F: FnOnce() -> Result<CachedDocument>,
+        R: FnOnce(&CachedDocument) -> Result<Option<CachedDocument>>,
+    {
+        let document = match self.lookup_document(key) {
+            CacheLookup::Fresh(document) => return Ok(document),
+            CacheLookup::Stale(stale) => match revalidate(&stale)? {
+                None => {
+                    self.mark_document_revalidated(key);
+                    return Ok(stale);
+                }
+                Some(document) => {
+                    self.store_document(key, document.clone(), true);
+                    return Ok(document);
+                }
+            },
+            CacheLookup::Missing => fetch()?,
+        };
+
+        self.store_document(key, document.clone(), false);
+        Ok(document)
+    }
+
+    /// Look up an external document without fetching it
+    ///
+    /// Documents missing from memory are loaded from the persistent store, if
+    /// configured; they still count as in-memory misses. Offline, expired
+    /// documents are returned as fresh.
+    pub(crate) fn lookup_document(&self, key: &DocumentCacheKey) -> CacheLookup<CachedDocument> {
+        let lookup = match self.external_documents.write() {
+            Ok(mut cache) => cache.lookup(key),
+            Err(_) => CacheLookup::Missing,
+        };
+
+        let lookup = match (lookup, &self.document_store) {
+            (CacheLookup::Missing, Some(store)) => {
+                let lookup = store.lookup(key);
+                if let CacheLookup::Fresh(document) = &lookup {
+                    if let Ok(mut cache) = self.external_documents.write() {
+                        cache.insert_with_ttl(
+                            key.clone(),
+                            document.clone(),
+                            store.remaining_ttl(key),
+                        );
+                    }
+                }
+                lookup
+            }
+            (lookup, _) => lookup,
+        };
+
+        match lookup {
+            CacheLookup::Stale(document) if self.offline => CacheLookup::Fresh(document),
+            lookup => lookup,
+        }
+    }
+
+    /// Refresh the TTL of an expired document confirmed to be unchanged
+    pub(crate) fn mark_document_revalidated(&self, key: &DocumentCacheKey) {
+        let revalidated = self
+            .external_documents
+            .write()
+            .is_ok_and(|mut cache| cache.revalidate(key));
+        if !revalidated {
+            // The stale document came from the persistent store
+            if let Some(CacheLookup::Stale(document)) =
+                self.document_store.as_ref().map(|store| store.lookup(key))
+            {
+                if let Ok(mut cache) = self.external_documents.write() {
+                    cache.insert(key.clone(), document);
+                    cache.record_revalidation();
+                }
+            }
+        }
+        if let Some(store) = &self.document_store {
+            // The persistent store is best effort; memory still has the document
+            let _ = store.revalidate(key);
+        }
+    }
+
+    /// Store a fetched document, counting a miss if it replaces a stale
+    /// in-memory entry
+    ///
+    /// A stale document loaded from the persistent store was already counted
+    /// as a miss when memory didn't have it.
+    ///
+    /// The document is written to the persistent store before the in-memory
+    /// cache is locked, so lookups never wait on disk writes.
+    pub(crate) fn store_document(
+        &self,
+        key: &DocumentCacheKey,
+        document: CachedDocument,
+        replaced_stale: bool,
+    ) {
+        if let Some(store) = &self.document_store {
+            // The persistent store is best effort; memory still has the document
+            let _ = store.store(key, &document);
+        }
+        if let Ok(mut cache) = self.external_documents.write() {
+            let replaced = cache.insert(key.clone(), document);
+            if replaced_stale && replaced.is_some() {
+                cache.record_miss();
+            }
+        }
+    }
+
     // CSS selector caching methods disabled due to lifetime issues
     // TODO: Implement CSS selector caching with proper lifetime management
 
@@ -421,6 +986,9 @@ impl TemplateCache {
         if let Ok(mut cache) = self.external_documents.write() {
             cache.clear();
         }
+        if let Some(store) = &self.document_store {
+            let _ = store.clear();
+        }
         // css_selectors disabled
     }
 
@@ -470,6 +1038,13 @@ pub struct CacheConfig {
     pub document_ttl: Option<Duration>,
     pub selector_ttl: Option<Duration>,
     pub eviction_strategy: EvictionStrategy,
+    /// Directory to persist external documents in, so they survive restarts
+    pub document_dir: Option<PathBuf>,
+    /// Maximum total size in bytes of the documents in `document_dir`
+    pub document_dir_max_bytes: u64,
+    /// Serve cached documents after `document_ttl` expires without
+    /// revalidating them, for rendering without network access
+    pub offline: bool,
 }
 
 impl Default for CacheConfig {
@@ -484,6 +1059,9 @@ impl Default for CacheConfig {
             document_ttl: Some(Duration::from_secs(1800)), // 30 minutes
             selector_ttl: Some(Duration::from_secs(3600)), // 1 hour
             eviction_strategy: EvictionStrategy::LRU,
+            document_dir: None,
+            document_dir_max_bytes: 100 * 1024 * 1024, // 100 MiB
+            offline: false,
         }
     }
 }
@@ -493,6 +1071,7 @@ impl Default for CacheStats {
         Self {
             hits: 0,
             misses: 0,
+            revalidations: 0,
             hit_rate: 0.0,
             entry_count: 0,
             max_size: 0,
@@ -614,6 +1193,253 @@ mod tests {
         assert_eq!(cache.get(&"key1"), None);
     }
 
+    #[test]
+    fn test_cache_stale_lookup_and_revalidate() {
+        let mut cache = Cache::new(10, Some(Duration::from_millis(1)), EvictionStrategy::LRU)
+            .with_stale_entries();
+        cache.insert("key1", "value1");
+        assert_eq!(cache.lookup(&"key1"), CacheLookup::Fresh("value1"));
+
+        thread::sleep(Duration::from_millis(5));
+
+        // Expired entries are kept for revalidation but never served by get
+        assert_eq!(cache.get(&"key1"), None);
+        assert_eq!(cache.lookup(&"key1"), CacheLookup::Stale("value1"));
+        assert_eq!(cache.lookup(&"key2"), CacheLookup::<&str>::Missing);
+
+        assert!(cache.revalidate(&"key1"));
+        assert!(!cache.revalidate(&"key2"));
+
+        let stats = cache.stats();
+        assert_eq!(stats.hits, 1);
+        assert_eq!(stats.misses, 2);
+        assert_eq!(stats.revalidations, 1);
+    }
+
+    #[test]
+    fn test_document_revalidation() {
+        let cache = TemplateCache::with_config(CacheConfig {
+            document_ttl: Some(Duration::from_millis(1)),
+            ..Default::default()
+        });
+        let key = DocumentCacheKey::new("https://example.com/doc");
+        let document = |content: &str| CachedDocument {
+            etag: Some("\"v1\"".to_string()),
+            ..CachedDocument::html(content)
+        };
+
+        let first = cache
+            .get_or_revalidate_document(&key, || Ok(document("v1")), |_| unreachable!())
+            .unwrap();
+        assert_eq!(first.content, "v1");
+
+        // Unchanged: the stale entry is served and its TTL refreshed
+        thread::sleep(Duration::from_millis(5));
+        let revalidated = cache
+            .get_or_revalidate_document(
+                &key,
+                || unreachable!(),
+                |stale| {
+                    assert_eq!(stale.etag.as_deref(), Some("\"v1\""));
+                    Ok(None)
+                },
+            )
+            .unwrap();
+        assert_eq!(revalidated.content, "v1");
+
+        // Changed: the new document replaces the entry
+        thread::sleep(Duration::from_millis(5));
+        let changed = cache
+            .get_or_revalidate_document(&key, || unreachable!(), |_| Ok(Some(document("v2"))))
+            .unwrap();
+        assert_eq!(changed.content, "v2");
+
+        let stats = cache.get_stats().external_documents;
+        assert_eq!(stats.misses, 2);
+        assert_eq!(stats.revalidations, 1);
+        assert_eq!(stats.entry_count, 1);
+    }
+
+    fn store_dir(name: &str) -> PathBuf {
+        let dir =
+            std::env::temp_dir().join(format!("html-template-{}-{}", name, std::process::id()));
+        let _ = fs::remove_dir_all(&dir);
+        dir
+    }
+
+    fn disk_cache(dir: &Path, ttl: Duration, offline: bool) -> TemplateCache {
+        TemplateCache::with_config(CacheConfig {
+            document_ttl: Some(ttl),
+            document_dir: Some(dir.to_path_buf()),
+            offline,
+            ..Default::default()
+        })
+    }
+
+    #[test]
+    fn test_documents_persist_across_caches() {
+        let dir = store_dir("persist");
+        let key = DocumentCacheKey::new("https://example.com/doc");
+        let document = CachedDocument {
+            etag: Some("\"v1\"".to_string()),
+            last_modified: Some("Mon, 01 Jan 2024 00:00:00 GMT".to_string()),
+            ..CachedDocument::html("<p>v1</p>")
+        };
+
+        let first = disk_cache(&dir, Duration::from_secs(60), false);
+        first
+            .get_or_fetch_document(&key, || Ok(document.clone()))
+            .unwrap();
+
+        // A new cache, as in a new process, loads it from disk
+        let second = disk_cache(&dir, Duration::from_secs(60), false);
+        let loaded = second
+            .get_or_fetch_document(&key, || unreachable!())
+            .unwrap();
+        assert_eq!(loaded.content, "<p>v1</p>");
+        assert_eq!(loaded.etag.as_deref(), Some("\"v1\""));
+        assert_eq!(
+            loaded.last_modified.as_deref(),
+            Some("Mon, 01 Jan 2024 00:00:00 GMT")
+        );
+
+        // Keys with different headers are stored separately
+        let with_headers = DocumentCacheKey::with_headers(
+            "https://example.com/doc",
+            vec![("Accept-Language".to_string(), "fr".to_string())],
+        );
+        assert!(matches!(
+            second.document_store().unwrap().lookup(&with_headers),
+            CacheLookup::Missing
+        ));
+
+        second.clear_all();
+        assert_eq!(second.document_store().unwrap().size().unwrap(), 0);
+        fs::remove_dir_all(&dir).ok();
+    }
+
+    #[test]
+    fn test_disk_documents_expire_unless_offline() {
+        let dir = store_dir("offline");
+        let key = DocumentCacheKey::new("https://example.com/doc");
+        let store = DiskDocumentStore::new(&dir, Some(Duration::from_millis(10)), u64::MAX);
+        store.store(&key, &CachedDocument::html("cached")).unwrap();
+        thread::sleep(Duration::from_millis(20));
+
+        // Expired documents are revalidated with the persisted validators
+        let online = disk_cache(&dir, Duration::from_millis(10), false);
+        assert!(matches!(
+            online.lookup_document(&key),
+            CacheLookup::Stale(document) if document.content == "cached"
+        ));
+        online.mark_document_revalidated(&key);
+        assert!(matches!(store.lookup(&key), CacheLookup::Fresh(_)));
+        thread::sleep(Duration::from_millis(20));
+
+        // Offline, they are served without revalidating
+        let offline = disk_cache(&dir, Duration::from_millis(10), true);
+        let document = offline
+            .get_or_revalidate_document(&key, || unreachable!(), |_| unreachable!())
+            .unwrap();
+        assert_eq!(document.content, "cached");
+        fs::remove_dir_all(&dir).ok();
+    }
+
+    #[test]
+    fn test_disk_stale_document_stats() {
+        let dir = store_dir("stale-stats");
+        let key = DocumentCacheKey::new("https://example.com/doc");
+        let store = DiskDocumentStore::new(&dir, Some(Duration::from_millis(10)), u64::MAX);
+        let stale = || {
+            store.store(&key, &CachedDocument::html("v1")).unwrap();
+            thread::sleep(Duration::from_millis(20));
+        };
+
+        // Unchanged: the miss in memory is followed by a revalidation
+        stale();
+        let unchanged = disk_cache(&dir, Duration::from_millis(10), false);
+        unchanged
+            .get_or_revalidate_document(&key, || unreachable!(), |_| Ok(None))
+            .unwrap();
+        let stats = unchanged.get_stats().external_documents;
+        assert_eq!((stats.misses, stats.revalidations), (1, 1));
+
+        // Changed: the new document is a single miss
+        stale();
+        let changed = disk_cache(&dir, Duration::from_millis(10), false);
+        changed
+            .get_or_revalidate_document(
+                &key,
+                || unreachable!(),
+                |_| Ok(Some(CachedDocument::html("v2"))),
+            )
+            .unwrap();
+        let stats = changed.get_stats().external_documents;
+        assert_eq!((stats.misses, stats.revalidations), (1, 0));
+
+        // Refetched without revalidation: also a single miss
+        stale();
+        let refetched = disk_cache(&dir, Duration::from_millis(10), false);
+        refetched
+            .get_or_fetch_document(&key, || Ok(CachedDocument::html("v2")))
+            .unwrap();
+        let stats = refetched.get_stats().external_documents;
+        assert_eq!((stats.misses, stats.revalidations), (1, 0));
+        fs::remove_dir_all(&dir).ok();
+    }
+
+    #[test]
+    fn test_disk_store_size_cap() {
+        let dir = store_dir("size-cap");
+        let document = CachedDocument::html("x".repeat(1000));
+        let keys: Vec<_> = (0..3)
+            .map(|i| DocumentCacheKey::new(&format!("https://example.com/{}", i)))
+            .collect();
+
+        let unbounded = DiskDocumentStore::new(&dir, None, u64::MAX);
+        unbounded.store(&keys[0], &document).unwrap();
+        let entry_size = unbounded.size().unwrap();
+        fs::remove_dir_all(&dir).ok();
+
+        let store = DiskDocumentStore::new(&dir, None, entry_size * 2);
+        for key in &keys {
+            store.store(key, &document).unwrap();
+            thread::sleep(Duration::from_millis(10));
+        }
+
+        // The oldest document is evicted to make room
+        assert!(matches!(store.lookup(&keys[0]), CacheLookup::Missing));
+        assert!(matches!(store.lookup(&keys[1]), CacheLookup::Fresh(_)));
+        assert!(matches!(store.lookup(&keys[2]), CacheLookup::Fresh(_)));
+        assert!(store.size().unwrap() <= entry_size * 2);
+        fs::remove_dir_all(&dir).ok();
+    }
+
+    #[test]
+    fn test_disk_store_concurrent_writes() {
+        let dir = store_dir("concurrent");
+        let store = DiskDocumentStore::new(&dir, None, u64::MAX);
+        let key = DocumentCacheKey::new("https://example.com/shared");
+
+        let writers: Vec<_> = (0..8)
+            .map(|i| {
+                let store = store.clone();
+                let key = key.clone();
+                thread::spawn(move || store.store(&key, &CachedDocument::html(format!("v{}", i))))
+            })
+            .collect();
+        for writer in writers {
+            writer.join().unwrap().unwrap();
+        }
+
+        // Every write used its own temporary file and one of them won
+        assert!(matches!(store.lookup(&key), CacheLookup::Fresh(_)));
+        let files: Vec<_> = fs::read_dir(&dir).unwrap().collect();
+        assert_eq!(files.len(), 1);
+        assert_eq!(*store.lock_stored_bytes(), Some(store.size().unwrap()));
+        fs::remove_dir_all(&dir).ok();
+    }
+
     #[test]
     fn test_cache_entry_lifecycle() {
         let entry = CacheEntry::new("test_value", Some(Duration::from_secs(60)));
@@ -633,6 +1459,47 @@ mod tests {
         assert_ne!(key1, key3);
     }
 
+    #[test]
+    fn test_template_cache_key_evaluator_identity() {
+        use crate::constraints::{ConstraintContext, DefaultConstraintEvaluator};
+
+        /// Accepts only expressions without spaces
+        struct Compact;
+
+        impl ConstraintEvaluator for Compact {
+            fn evaluate(
+                &self,
+                _expression: &str,
+                _context: &ConstraintContext<'_>,
+            ) -> Result<bool> {
+                Ok(true)
+            }
+
+            fn validate(&self, expression: &str) -> Result<()> {
+                match expression.contains(' ') {
+                    true => Err(crate::Error::constraint_static("spaces are not allowed")),
+                    false => Ok(()),
+                }
+            }
+        }
+
+        let html = r#"<template><div><p data-constraint="a == b">A</p></div></template>"#;
+        let lenient = TemplateConfig::new().with_constraint_evaluator(DefaultConstraintEvaluator);
+        let strict = TemplateConfig::new().with_constraint_evaluator(Compact);
+
+        let key = TemplateCacheKey::for_config(html, None, &lenient);
+        assert_eq!(
+            key,
+            TemplateCacheKey::for_config(html, None, &lenient.clone())
+        );
+        assert_ne!(key, TemplateCacheKey::for_config(html, None, &strict));
+
+        // A template compiled with one evaluator isn't reused for another
+        let cache = TemplateCache::new();
+        assert!(HtmlTemplate::from_str_with_cache(html, None, lenient, &cache).is_ok());
+        assert!(HtmlTemplate::from_str_with_cache(html, None, strict, &cache).is_err());
+    }
+
     #[test]
     fn test_document_cache_key() {
         let key1 = DocumentCacheKey::new("https://example.com/test");
@@ -682,6 +1549,7 @@ mod tests {
             parsed_templates: CacheStats {
                 hits: 10,
                 misses: 5,
+                revalidations: 0,
                 hit_rate: 0.67,
                 entry_count: 8,
                 max_size: 100,
@@ -689,6 +1557,7 @@ mod tests {
             compiled_templates: CacheStats {
                 hits: 20,
                 misses: 10,
+                revalidations: 0,
                 hit_rate: 0.67,
                 entry_count: 15,
                 max_size: 200,
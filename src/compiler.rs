@@ -5,6 +5,7 @@ use std::sync::Arc;
 
 use crate::error::Result;
 use crate::parser::Parser;
+use crate::program::RenderProgram;
 use crate::types::*;
 
 static ARRAY_INDEX_REGEX: Lazy<Regex> =
@@ -14,19 +15,29 @@ pub struct Compiler;
 
 impl Compiler {
     pub fn compile(html: &str, root_selector: Option<&str>) -> Result<Arc<CompiledTemplate>> {
-        let parser = Parser::new(html)?;
+        Self::compile_with_config(html, root_selector, &TemplateConfig::default())
+    }
+
+    /// Compile a template using the constraint attributes and evaluator in `config`
+    pub fn compile_with_config(
+        html: &str,
+        root_selector: Option<&str>,
+        config: &TemplateConfig,
+    ) -> Result<Arc<CompiledTemplate>> {
+        let parser = Parser::new(html)?.with_config(config);
         let mut template = parser.parse_template(root_selector)?;
 
         // Optimize the template structure
         Self::optimize_template(&mut template)?;
 
-        // Build lookup tables for faster rendering
-        Self::build_lookup_tables(&mut template)?;
+        // Lower the template into the program it renders with
+        Self::build_program(&mut template);
 
         Ok(Arc::new(template))
     }
 
-    pub fn compile_from_template(template: CompiledTemplate) -> Arc<CompiledTemplate> {
+    pub fn compile_from_template(mut template: CompiledTemplate) -> Arc<CompiledTemplate> {
+        Self::build_program(&mut template);
         Arc::new(template)
     }
 
@@ -48,13 +59,8 @@ impl Compiler {
         Ok(())
     }
 
-    fn build_lookup_tables(_template: &mut CompiledTemplate) -> Result<()> {
-        // In a full implementation, we would build various lookup tables:
-        // - Property name to element index mapping
-        // - Selector to element mapping
-        // - Constraint dependency graph
-
-        Ok(())
+    fn build_program(template: &mut CompiledTemplate) {
+        template.program = RenderProgram::compile(template);
     }
 
     fn selector_specificity(selector: &str) -> u32 {
@@ -217,12 +223,7 @@ mod tests {
         // Should have parsed all elements with itemprop
         assert_eq!(compiled.elements.len(), 4); // header, title, date, content
 
-        // Check that header is marked as scope
-        let header = compiled
-            .elements
-            .iter()
-            .find(|e| e.properties.iter().any(|p| p.name == "header"))
-            .unwrap();
-        assert!(header.is_scope);
+        // Check that header binds its children to its own scope
+        assert!(compiled.program.to_string().contains("enter-scope header"));
     }
 }
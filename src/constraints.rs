@@ -3,7 +3,14 @@
 //! This module implements the constraint evaluation system that handles
 //! data-scope and data-constraint attributes for conditional rendering.
 
-use std::collections::HashMap;
+use std::borrow::Cow;
+use std::cmp::Ordering;
+use std::collections::{BTreeSet, HashMap};
+use std::sync::Arc;
+
+use once_cell::sync::Lazy;
+use regex::Regex;
+use serde_json::Value as JsonValue;
 
 use crate::error::{Error, Result};
 use crate::types::{Constraint, ConstraintType};
@@ -15,8 +22,12 @@ pub struct ConstraintContext<'a> {
     data: &'a dyn RenderValue,
     /// Map of element IDs to their data values
     id_map: HashMap<String, &'a dyn RenderValue>,
-    /// Current scope name if any
-    current_scope: Option<&'a str>,
+    /// Items collected from the data being rendered, consulted after `id_map`
+    ids: Option<&'a ItemIds<'a>>,
+    /// Active scopes, such as the caller's roles or feature flags
+    scopes: Vec<&'a str>,
+    /// Functions that expressions can call
+    functions: &'a ConstraintFunctionRegistry,
 }
 
 impl<'a> ConstraintContext<'a> {
@@ -25,248 +36,1427 @@ impl<'a> ConstraintContext<'a> {
         Self {
             data,
             id_map: HashMap::new(),
-            current_scope: None,
+            ids: None,
+            scopes: Vec::new(),
+            functions: &DEFAULT_FUNCTIONS,
         }
     }
 
-    /// Set the current scope
+    /// Set the functions that expressions can call, replacing the built-ins
+    pub fn with_functions(mut self, functions: &'a ConstraintFunctionRegistry) -> Self {
+        self.functions = functions;
+        self
+    }
+
+    /// Add an active scope
     pub fn with_scope(mut self, scope: &'a str) -> Self {
-        self.current_scope = Some(scope);
+        self.scopes.push(scope);
+        self
+    }
+
+    /// Add several active scopes
+    pub fn with_scopes<I, S>(mut self, scopes: I) -> Self
+    where
+        I: IntoIterator<Item = &'a S>,
+        S: AsRef<str> + ?Sized + 'a,
+    {
+        self.scopes
+            .extend(scopes.into_iter().map(|scope| scope.as_ref()));
         self
     }
 
+    /// Check if `scope` is active
+    pub fn in_scope(&self, scope: &str) -> bool {
+        self.scopes.contains(&scope)
+    }
+
     /// Register an element with an @id
     pub fn register_id(&mut self, id: &str, value: &'a dyn RenderValue) {
         self.id_map.insert(id.to_string(), value);
     }
 
+    /// Resolve `@id` references against items collected from the render data
+    pub fn with_ids(mut self, ids: &'a ItemIds<'a>) -> Self {
+        self.ids = Some(ids);
+        self
+    }
+
+    /// Get the data the constraint is evaluated against
+    pub fn data(&self) -> &'a dyn RenderValue {
+        self.data
+    }
+
+    /// Get the item registered under `id`, for `@id` references
+    pub fn item(&self, id: &str) -> Option<&'a dyn RenderValue> {
+        self.lookup_id(id)
+    }
+
+    /// Find the item registered under `id`
+    fn lookup_id(&self, id: &str) -> Option<&'a dyn RenderValue> {
+        self.id_map
+            .get(id)
+            .copied()
+            .or_else(|| self.ids.and_then(|ids| ids.get(id)))
+    }
+
     /// Evaluate a constraint
     pub fn evaluate(&self, constraint: &Constraint) -> Result<bool> {
         match &constraint.constraint_type {
-            ConstraintType::Scope(scope_name) => {
-                // Check if we're in the specified scope
-                Ok(self.current_scope == Some(scope_name))
-            }
-            ConstraintType::Expression(expr) => self.evaluate_expression(expr),
+            ConstraintType::Scope(scope_name) => Ok(self.in_scope(scope_name)),
+            ConstraintType::Expression(expr) => self.evaluate_parsed(expr.ast()),
         }
     }
 
     /// Evaluate a constraint expression
     pub fn evaluate_expression(&self, expr: &str) -> Result<bool> {
-        // Simple expression parser for constraints
-        // Supports: property comparisons, existence checks, etc.
+        self.evaluate_parsed(&Expression::parse(expr)?)
+    }
 
-        let expr = expr.trim();
+    /// Evaluate a parsed constraint expression
+    pub fn evaluate_parsed(&self, expr: &Expression) -> Result<bool> {
+        Ok(is_truthy(self.resolve(expr)?.as_deref()))
+    }
+
+    /// Resolve an expression to a typed value, or `None` if a property is missing
+    pub fn resolve(&self, expr: &Expression) -> Result<Option<Cow<'a, JsonValue>>> {
+        let value = match expr {
+            Expression::Literal(literal) => literal.to_json(),
+            Expression::Array(items) => {
+                let mut values = Vec::with_capacity(items.len());
+                for item in items {
+                    values.push(self.resolve(item)?.map_or(JsonValue::Null, Cow::into_owned));
+                }
+                JsonValue::Array(values)
+            }
+            Expression::Path(path) => return Ok(resolve_path(self.data, path)),
+            Expression::IdRef { id, path } => {
+                let item = self
+                    .lookup_id(id)
+                    .ok_or_else(|| Error::constraint_owned(format!("Unknown item id '@{}'", id)))?;
+                return Ok(resolve_path(item, path));
+            }
+            Expression::Call { name, args } => {
+                let function = self.functions.get(name).ok_or_else(|| {
+                    Error::constraint_owned(format!("Unknown constraint function '{}'", name))
+                })?;
+                let mut values = Vec::with_capacity(args.len());
+                for arg in args {
+                    values.push(self.resolve(arg)?.map_or(JsonValue::Null, Cow::into_owned));
+                }
+                function(&values)?
+            }
+            Expression::Not(operand) => JsonValue::Bool(!self.evaluate_parsed(operand)?),
+            Expression::Binary { op, left, right } => JsonValue::Bool(match op {
+                BinaryOp::Or => self.evaluate_parsed(left)? || self.evaluate_parsed(right)?,
+                BinaryOp::And => self.evaluate_parsed(left)? && self.evaluate_parsed(right)?,
+                op => {
+                    let left = self.resolve(left)?;
+                    let right = self.resolve(right)?;
+                    compare(*op, left.as_deref(), right.as_deref())
+                }
+            }),
+        };
+        Ok(Some(Cow::Owned(value)))
+    }
 
-        // Check for negation operator
-        if expr.starts_with('!') {
-            let property = expr[1..].trim();
-            return self.check_property_exists(property).map(|exists| !exists);
+    /// Collect the paths and `@id` references in `expr` that don't resolve
+    pub(crate) fn unresolved_paths(&self, expr: &Expression, out: &mut Vec<String>) {
+        match expr {
+            Expression::Literal(_) => {}
+            Expression::Path(path) => {
+                if resolve_path(self.data, path).is_none() {
+                    out.push(expr.to_string());
+                }
+            }
+            Expression::IdRef { id, path } => {
+                let item = self.lookup_id(id);
+                if item.and_then(|item| resolve_path(item, path)).is_none() {
+                    out.push(expr.to_string());
+                }
+            }
+            Expression::Array(items) | Expression::Call { args: items, .. } => {
+                for item in items {
+                    self.unresolved_paths(item, out);
+                }
+            }
+            Expression::Not(operand) => self.unresolved_paths(operand, out),
+            Expression::Binary { left, right, .. } => {
+                self.unresolved_paths(left, out);
+                self.unresolved_paths(right, out);
+            }
         }
+    }
+}
 
-        // Check for logical OR first (lowest precedence)
-        if let Some((left, right)) = expr.split_once("||") {
-            return self.evaluate_logical_or(left.trim(), right.trim());
+/// Items that constraints can reference by id, as in `@user-42.role`
+///
+/// Collected from the data of one render and borrowed from it, so an index
+/// lives no longer than that data. An item whose id has a fragment, such as
+/// `https://example.com/team#user-42`, is also registered under the fragment.
+/// When ids repeat, the first item collected wins.
+#[derive(Default)]
+pub struct ItemIds<'a> {
+    items: HashMap<String, &'a dyn RenderValue>,
+}
+
+impl<'a> ItemIds<'a> {
+    /// Create an empty index
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Collect `data` and every item nested in it that has an id (`@id` in
+    /// JSON, `itemid` in microdata)
+    pub fn collect(data: &'a dyn RenderValue) -> Self {
+        let mut ids = Self::new();
+        match data.as_json() {
+            Some(json) => ids.collect_json(json),
+            None => ids.collect_value(data),
         }
+        ids
+    }
 
-        // Check for logical AND (higher precedence than ||)
-        if let Some((left, right)) = expr.split_once("&&") {
-            return self.evaluate_logical_and(left.trim(), right.trim());
+    fn collect_json(&mut self, value: &'a JsonValue) {
+        if let Some(id) = value.get_id() {
+            self.register(id, value);
         }
+        match value {
+            JsonValue::Object(map) => map.values().for_each(|value| self.collect_json(value)),
+            JsonValue::Array(items) => items.iter().for_each(|item| self.collect_json(item)),
+            _ => {}
+        }
+    }
 
-        // Check for existence operator (just a property name)
-        if !expr.contains(['=', '!', '<', '>', ' ']) {
-            return self.check_property_exists(expr);
+    /// Values not backed by JSON only expose their own id and array items
+    fn collect_value(&mut self, value: &'a dyn RenderValue) {
+        if let Some(id) = value.get_id() {
+            self.register(id, value);
+        }
+        for item in value.as_array().into_iter().flatten() {
+            self.collect_value(item);
         }
+    }
 
-        // Check for equality
-        if let Some((left, right)) = expr.split_once("==") {
-            return self.evaluate_equality(left.trim(), right.trim());
+    /// Register an item under `id`, unless another item already has it
+    pub fn register(&mut self, id: &str, value: &'a dyn RenderValue) {
+        let id = id.trim();
+        if let Some((_, fragment)) = id.split_once('#') {
+            if !fragment.is_empty() {
+                self.items.entry(fragment.to_string()).or_insert(value);
+            }
         }
+        self.items.entry(id.to_string()).or_insert(value);
+    }
 
-        // Check for inequality
-        if let Some((left, right)) = expr.split_once("!=") {
-            return self.evaluate_inequality(left.trim(), right.trim());
+    /// Get the item registered under `id`
+    pub fn get(&self, id: &str) -> Option<&'a dyn RenderValue> {
+        self.items.get(id).copied()
+    }
+
+    /// Number of registered ids
+    pub fn len(&self) -> usize {
+        self.items.len()
+    }
+
+    /// Check if no ids are registered
+    pub fn is_empty(&self) -> bool {
+        self.items.is_empty()
+    }
+}
+
+/// Apply a comparison operator to two resolved operands
+///
+/// A missing operand makes every comparison false except `!=`.
+fn compare(op: BinaryOp, left: Option<&JsonValue>, right: Option<&JsonValue>) -> bool {
+    let (left, right) = match (left, right) {
+        (Some(left), Some(right)) => (left, right),
+        _ => return op == BinaryOp::Ne,
+    };
+
+    match op {
+        BinaryOp::Eq => values_equal(left, right),
+        BinaryOp::Ne => !values_equal(left, right),
+        BinaryOp::Lt => compare_values(left, right).is_some_and(Ordering::is_lt),
+        BinaryOp::Le => compare_values(left, right).is_some_and(Ordering::is_le),
+        BinaryOp::Gt => compare_values(left, right).is_some_and(Ordering::is_gt),
+        BinaryOp::Ge => compare_values(left, right).is_some_and(Ordering::is_ge),
+        BinaryOp::In => contains_value(right, left),
+        BinaryOp::Or | BinaryOp::And => unreachable!("logical operators are not comparisons"),
+    }
+}
+
+/// Resolve a property path against data
+///
+/// JSON-backed data is read without copying. `length` gives the number of
+/// items in an array, characters in a string or keys in an object, unless
+/// the object has a `length` property of its own.
+fn resolve_path<'a>(data: &'a dyn RenderValue, path: &[PathSegment]) -> Option<Cow<'a, JsonValue>> {
+    let mut current = match data.as_json() {
+        Some(json) => json,
+        None => return resolve_path_as_string(data, path),
+    };
+
+    for (i, segment) in path.iter().enumerate() {
+        current = match (current, segment) {
+            (JsonValue::Object(map), PathSegment::Property(name)) if map.contains_key(name) => {
+                &map[name]
+            }
+            (JsonValue::Array(items), PathSegment::Index(index)) => items.get(*index)?,
+            (value, PathSegment::Property(name)) if name == "length" && i == path.len() - 1 => {
+                let length = match value {
+                    JsonValue::Array(items) => items.len(),
+                    JsonValue::String(text) => text.chars().count(),
+                    JsonValue::Object(map) => map.len(),
+                    _ => return None,
+                };
+                return Some(Cow::Owned(JsonValue::from(length)));
+            }
+            _ => return None,
+        };
+    }
+
+    Some(Cow::Borrowed(current))
+}
+
+/// Resolve a property path against data that is not backed by JSON, whose
+/// values are only available as strings
+fn resolve_path_as_string<'a>(
+    data: &'a dyn RenderValue,
+    path: &[PathSegment],
+) -> Option<Cow<'a, JsonValue>> {
+    if let Some(value) = data.get_property(&property_path(path)) {
+        return Some(Cow::Owned(JsonValue::String(value.into_owned())));
+    }
+
+    // `length` of an array or string
+    let (last, parent) = path.split_last()?;
+    if last != &PathSegment::Property("length".to_string()) {
+        return None;
+    }
+    let parent_path = property_path(parent);
+    let length = match data
+        .get_value(&parent_path)
+        .and_then(|value| value.as_array())
+    {
+        Some(items) => items.len(),
+        None => data.get_property(&parent_path)?.chars().count(),
+    };
+    Some(Cow::Owned(JsonValue::from(length)))
+}
+
+/// Convert parsed path segments to a `RenderValue` property path
+///
+/// An index is attached to the preceding segment, as in `items[0]`.
+fn property_path(path: &[PathSegment]) -> Vec<String> {
+    let mut segments: Vec<String> = Vec::with_capacity(path.len());
+    for segment in path {
+        match segment {
+            PathSegment::Property(name) => segments.push(name.clone()),
+            PathSegment::Index(index) => match segments.last_mut() {
+                Some(last) => last.push_str(&format!("[{}]", index)),
+                None => segments.push(format!("[{}]", index)),
+            },
         }
+    }
+    segments
+}
+
+/// Check whether a value counts as true when used as a condition
+///
+/// Missing values, `null`, `false`, `0`, empty strings, arrays and objects
+/// are false. So are the strings `"false"` and `"0"`, as microdata values
+/// are always strings.
+pub fn is_truthy(value: Option<&JsonValue>) -> bool {
+    match value {
+        None | Some(JsonValue::Null) => false,
+        Some(JsonValue::Bool(value)) => *value,
+        Some(JsonValue::Number(number)) => number.as_f64().is_some_and(|n| n != 0.0),
+        Some(JsonValue::String(text)) => !matches!(text.as_str(), "" | "false" | "0"),
+        Some(JsonValue::Array(items)) => !items.is_empty(),
+        Some(JsonValue::Object(map)) => !map.is_empty(),
+    }
+}
 
-        // Check for greater than or equal (must come before >)
-        if let Some((left, right)) = expr.split_once(">=") {
-            return self.evaluate_greater_equal(left.trim(), right.trim());
+/// Check two values for equality
+///
+/// Values of the same type compare by value, with arrays and objects compared
+/// element by element. A number equals a string holding the same number, and a
+/// boolean equals the string `"true"` or `"false"`. Other mixed types, and
+/// `null` with anything but `null`, are unequal.
+pub fn values_equal(left: &JsonValue, right: &JsonValue) -> bool {
+    match (left, right) {
+        (JsonValue::Number(_), JsonValue::Number(_))
+        | (JsonValue::Number(_), JsonValue::String(_))
+        | (JsonValue::String(_), JsonValue::Number(_)) => {
+            match (as_number(left), as_number(right)) {
+                (Some(left), Some(right)) => left == right,
+                _ => false,
+            }
+        }
+        (JsonValue::Bool(value), JsonValue::String(text))
+        | (JsonValue::String(text), JsonValue::Bool(value)) => {
+            text == if *value { "true" } else { "false" }
         }
+        (JsonValue::Array(left), JsonValue::Array(right)) => {
+            left.len() == right.len()
+                && left
+                    .iter()
+                    .zip(right)
+                    .all(|(left, right)| values_equal(left, right))
+        }
+        (JsonValue::Object(left), JsonValue::Object(right)) => {
+            left.len() == right.len()
+                && left.iter().all(|(key, left)| {
+                    right
+                        .get(key)
+                        .is_some_and(|right| values_equal(left, right))
+                })
+        }
+        _ => left == right,
+    }
+}
+
+/// Order two values for `<`, `<=`, `>` and `>=`
+///
+/// Numbers, and strings holding numbers, compare numerically. Strings that are
+/// both ISO-8601 dates or date-times compare chronologically, and other strings
+/// lexicographically. Any other pair of values is unordered.
+pub fn compare_values(left: &JsonValue, right: &JsonValue) -> Option<Ordering> {
+    if let (Some(left), Some(right)) = (as_number(left), as_number(right)) {
+        return left.partial_cmp(&right);
+    }
 
-        // Check for less than or equal (must come before <)
-        if let Some((left, right)) = expr.split_once("<=") {
-            return self.evaluate_less_equal(left.trim(), right.trim());
+    match (left, right) {
+        (JsonValue::String(left), JsonValue::String(right)) => {
+            match (parse_iso8601(left), parse_iso8601(right)) {
+                (Some(left), Some(right)) => left.partial_cmp(&right),
+                _ => Some(left.cmp(right)),
+            }
         }
+        _ => None,
+    }
+}
 
-        // Check for greater than
-        if let Some((left, right)) = expr.split_once('>') {
-            return self.evaluate_greater_than(left.trim(), right.trim());
+/// Check whether `haystack` contains `needle`, for `in`
+///
+/// Arrays contain equal items, strings contain substrings and objects
+/// contain keys.
+pub fn contains_value(haystack: &JsonValue, needle: &JsonValue) -> bool {
+    match (haystack, needle) {
+        (JsonValue::Array(items), needle) => items.iter().any(|item| values_equal(item, needle)),
+        (JsonValue::String(text), JsonValue::String(needle)) => text.contains(needle.as_str()),
+        (JsonValue::Object(map), JsonValue::String(key)) => map.contains_key(key),
+        _ => false,
+    }
+}
+
+/// Get a number, or a string holding a number, as `f64`
+fn as_number(value: &JsonValue) -> Option<f64> {
+    match value {
+        JsonValue::Number(number) => number.as_f64(),
+        JsonValue::String(text) => text.trim().parse().ok().filter(|n: &f64| n.is_finite()),
+        _ => None,
+    }
+}
+
+/// Parse an ISO-8601 date (`2024-05-01`) or date-time (`2024-05-01T09:30:00Z`)
+/// to seconds since the Unix epoch
+///
+/// Date-times without an offset, and plain dates, are taken as UTC.
+fn parse_iso8601(text: &str) -> Option<f64> {
+    let bytes = text.as_bytes();
+    let number = |range: std::ops::Range<usize>| -> Option<i64> {
+        let digits = text.get(range)?;
+        digits
+            .bytes()
+            .all(|b| b.is_ascii_digit())
+            .then(|| digits.parse().ok())?
+    };
+
+    if bytes.len() < 10 || bytes[4] != b'-' || bytes[7] != b'-' {
+        return None;
+    }
+    let (year, month, day) = (number(0..4)?, number(5..7)?, number(8..10)?);
+    if !(1..=12).contains(&month) || !(1..=31).contains(&day) {
+        return None;
+    }
+    let mut seconds = days_from_civil(year, month, day) as f64 * 86_400.0;
+
+    let rest = &text[10..];
+    if rest.is_empty() {
+        return Some(seconds);
+    }
+    let rest = rest.strip_prefix(['T', 't', ' '])?;
+    let time_end = rest
+        .find(|c: char| !(c.is_ascii_digit() || c == ':' || c == '.'))
+        .unwrap_or(rest.len());
+    let (time, zone) = rest.split_at(time_end);
+
+    let mut parts = time.splitn(3, ':');
+    let hours: f64 = parts.next()?.parse().ok()?;
+    let minutes: f64 = parts.next()?.parse().ok()?;
+    let secs: f64 = parts.next().map_or(Some(0.0), |secs| secs.parse().ok())?;
+    if hours >= 24.0 || minutes >= 60.0 || secs >= 61.0 {
+        return None;
+    }
+    seconds += hours * 3600.0 + minutes * 60.0 + secs;
+
+    match zone {
+        "" | "Z" | "z" => Some(seconds),
+        zone => {
+            let sign = match zone.as_bytes()[0] {
+                b'+' => 1.0,
+                b'-' => -1.0,
+                _ => return None,
+            };
+            let offset = zone[1..].replace(':', "");
+            if offset.len() != 4 || !offset.bytes().all(|b| b.is_ascii_digit()) {
+                return None;
+            }
+            let offset_hours: f64 = offset[..2].parse().ok()?;
+            let offset_minutes: f64 = offset[2..].parse().ok()?;
+            Some(seconds - sign * (offset_hours * 3600.0 + offset_minutes * 60.0))
         }
+    }
+}
+
+/// Days from 1970-01-01 to a date in the proleptic Gregorian calendar
+fn days_from_civil(year: i64, month: i64, day: i64) -> i64 {
+    let year = if month <= 2 { year - 1 } else { year };
+    let era = year.div_euclid(400);
+    let year_of_era = year - era * 400;
+    let month_index = (month + 9) % 12;
+    let day_of_year = (153 * month_index + 2) / 5 + day - 1;
+    let day_of_era = year_of_era * 365 + year_of_era / 4 - year_of_era / 100 + day_of_year;
+    era * 146_097 + day_of_era - 719_468
+}
 
-        // Check for less than
-        if let Some((left, right)) = expr.split_once('<') {
-            return self.evaluate_less_than(left.trim(), right.trim());
+/// A function that constraint expressions can call
+///
+/// Arguments are resolved before the call; a missing property is passed as
+/// `null`. Errors hide the constrained element, like any other evaluation error.
+pub type ConstraintFunction = dyn Fn(&[JsonValue]) -> Result<JsonValue> + Send + Sync;
+
+/// Built-in functions used when no registry is attached
+static DEFAULT_FUNCTIONS: Lazy<ConstraintFunctionRegistry> =
+    Lazy::new(ConstraintFunctionRegistry::with_defaults);
+
+/// Registry of functions that constraint expressions can call by name
+///
+/// # Examples
+///
+/// ```rust,ignore
+/// use html_template::{ConstraintFunctionRegistry, HtmlTemplateBuilder};
+/// use serde_json::Value;
+///
+/// let mut functions = ConstraintFunctionRegistry::with_defaults();
+/// functions.register("isEven", |args| {
+///     Ok(Value::Bool(args[0].as_i64().is_some_and(|n| n % 2 == 0)))
+/// });
+///
+/// let template = HtmlTemplateBuilder::new()
+///     .from_str(r#"<template><p data-constraint="isEven(count)">Even</p></template>"#)
+///     .with_constraint_functions(functions)
+///     .build()?;
+/// ```
+#[derive(Clone)]
+pub struct ConstraintFunctionRegistry {
+    functions: HashMap<String, Arc<ConstraintFunction>>,
+}
+
+impl ConstraintFunctionRegistry {
+    /// Create a registry with no functions
+    pub fn new() -> Self {
+        Self {
+            functions: HashMap::new(),
         }
+    }
+
+    /// Create a registry with the built-in functions
+    ///
+    /// - `len(value)` - items in an array, characters in a string or keys in an object
+    /// - `empty(value)` - whether a value is `null`, `""`, `[]` or `{}`
+    /// - `contains(haystack, needle)` - array membership, substring or object key
+    /// - `startsWith(text, prefix)` and `endsWith(text, suffix)`
+    /// - `matches(text, pattern)` - whether a regular expression matches
+    /// - `now()` - the current UTC time as an ISO-8601 date-time
+    pub fn with_defaults() -> Self {
+        let mut registry = Self::new();
+        registry.register("len", |args| {
+            let [value] = expect_args("len", args)?;
+            let length = match value {
+                JsonValue::Null => 0,
+                JsonValue::Array(items) => items.len(),
+                JsonValue::String(text) => text.chars().count(),
+                JsonValue::Object(map) => map.len(),
+                _ => {
+                    return Err(Error::constraint_static(
+                        "len() expects an array, string or object",
+                    ))
+                }
+            };
+            Ok(JsonValue::from(length))
+        });
+        registry.register("empty", |args| {
+            let [value] = expect_args("empty", args)?;
+            Ok(JsonValue::Bool(match value {
+                JsonValue::Null => true,
+                JsonValue::String(text) => text.is_empty(),
+                JsonValue::Array(items) => items.is_empty(),
+                JsonValue::Object(map) => map.is_empty(),
+                _ => false,
+            }))
+        });
+        registry.register("contains", |args| {
+            let [haystack, needle] = expect_args("contains", args)?;
+            Ok(JsonValue::Bool(contains_value(haystack, needle)))
+        });
+        registry.register("startsWith", |args| {
+            let [text, prefix] = expect_args("startsWith", args)?;
+            Ok(JsonValue::Bool(
+                as_text(text).starts_with(as_text(prefix).as_ref()),
+            ))
+        });
+        registry.register("endsWith", |args| {
+            let [text, suffix] = expect_args("endsWith", args)?;
+            Ok(JsonValue::Bool(
+                as_text(text).ends_with(as_text(suffix).as_ref()),
+            ))
+        });
+        registry.register("matches", |args| {
+            let [text, pattern] = expect_args("matches", args)?;
+            let regex = Regex::new(&as_text(pattern)).map_err(|e| {
+                Error::constraint_owned(format!("matches() got an invalid pattern: {}", e))
+            })?;
+            Ok(JsonValue::Bool(regex.is_match(&as_text(text))))
+        });
+        registry.register("now", |args| {
+            let [] = expect_args("now", args)?;
+            Ok(JsonValue::String(format_iso8601(
+                std::time::SystemTime::now(),
+            )))
+        });
+        registry
+    }
 
-        Err(Error::parse_owned(format!(
-            "Invalid constraint expression: {}",
-            expr
-        )))
+    /// Register a function, replacing any function with the same name
+    pub fn register<S, F>(&mut self, name: S, function: F)
+    where
+        S: Into<String>,
+        F: Fn(&[JsonValue]) -> Result<JsonValue> + Send + Sync + 'static,
+    {
+        self.functions.insert(name.into(), Arc::new(function));
     }
 
-    /// Check if a property exists and is truthy
-    fn check_property_exists(&self, prop: &str) -> Result<bool> {
-        let value = self.resolve_value(prop)?;
-        match value {
-            Some(v) => {
-                // Check for falsy values
-                match v.as_str() {
-                    "false" | "0" | "" => Ok(false),
-                    _ => Ok(true),
+    /// Get a function by name
+    pub fn get(&self, name: &str) -> Option<&ConstraintFunction> {
+        self.functions.get(name).map(|function| function.as_ref())
+    }
+
+    /// Check if a function is registered under `name`
+    pub fn contains(&self, name: &str) -> bool {
+        self.functions.contains_key(name)
+    }
+
+    /// Names of all registered functions
+    pub fn names(&self) -> Vec<&str> {
+        let mut names: Vec<&str> = self.functions.keys().map(String::as_str).collect();
+        names.sort_unstable();
+        names
+    }
+}
+
+impl Default for ConstraintFunctionRegistry {
+    fn default() -> Self {
+        Self::with_defaults()
+    }
+}
+
+impl std::fmt::Debug for ConstraintFunctionRegistry {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("ConstraintFunctionRegistry")
+            .field("functions", &self.names())
+            .finish()
+    }
+}
+
+/// Check a built-in function's argument count
+fn expect_args<'a, const N: usize>(
+    name: &str,
+    args: &'a [JsonValue],
+) -> Result<&'a [JsonValue; N]> {
+    args.try_into().map_err(|_| {
+        Error::constraint_owned(format!(
+            "{}() expects {} argument{}, got {}",
+            name,
+            N,
+            if N == 1 { "" } else { "s" },
+            args.len()
+        ))
+    })
+}
+
+/// Get a value as text for the string built-ins; `null` is empty
+fn as_text(value: &JsonValue) -> Cow<'_, str> {
+    match value {
+        JsonValue::String(text) => Cow::Borrowed(text),
+        JsonValue::Null => Cow::Borrowed(""),
+        value => Cow::Owned(value.to_string()),
+    }
+}
+
+/// Format a time as an ISO-8601 UTC date-time, such as `2024-05-01T09:30:00Z`
+fn format_iso8601(time: std::time::SystemTime) -> String {
+    let seconds = match time.duration_since(std::time::UNIX_EPOCH) {
+        Ok(elapsed) => elapsed.as_secs() as i64,
+        Err(before) => -(before.duration().as_secs() as i64),
+    };
+    let (year, month, day) = civil_from_days(seconds.div_euclid(86_400));
+    let time_of_day = seconds.rem_euclid(86_400);
+    format!(
+        "{:04}-{:02}-{:02}T{:02}:{:02}:{:02}Z",
+        year,
+        month,
+        day,
+        time_of_day / 3600,
+        time_of_day % 3600 / 60,
+        time_of_day % 60
+    )
+}
+
+/// Date in the proleptic Gregorian calendar a number of days after 1970-01-01
+fn civil_from_days(days: i64) -> (i64, i64, i64) {
+    let days = days + 719_468;
+    let era = days.div_euclid(146_097);
+    let day_of_era = days - era * 146_097;
+    let year_of_era =
+        (day_of_era - day_of_era / 1460 + day_of_era / 36_524 - day_of_era / 146_096) / 365;
+    let day_of_year = day_of_era - (365 * year_of_era + year_of_era / 4 - year_of_era / 100);
+    let month_index = (5 * day_of_year + 2) / 153;
+    let day = day_of_year - (153 * month_index + 2) / 5 + 1;
+    let month = if month_index < 10 {
+        month_index + 3
+    } else {
+        month_index - 9
+    };
+    let year = year_of_era + era * 400 + i64::from(month <= 2);
+    (year, month, day)
+}
+
+/// A parsed constraint expression
+#[derive(Debug, Clone, PartialEq)]
+pub enum Expression {
+    /// A string, number, boolean or null literal
+    Literal(Literal),
+    /// An array literal such as `['draft', 'review']`
+    Array(Vec<Expression>),
+    /// A property path such as `user.roles[0]`
+    Path(Vec<PathSegment>),
+    /// A reference to an item by id, such as `@limit` or `@user-42.role`
+    IdRef { id: String, path: Vec<PathSegment> },
+    /// A call to a registered function, such as `contains(tags, 'sale')`
+    Call { name: String, args: Vec<Expression> },
+    /// Logical negation, `!expr`
+    Not(Box<Expression>),
+    /// A logical or comparison operator
+    Binary {
+        op: BinaryOp,
+        left: Box<Expression>,
+        right: Box<Expression>,
+    },
+}
+
+impl Expression {
+    /// Parse a constraint expression
+    ///
+    /// Errors report the column (counted in characters from 1) of the problem.
+    pub fn parse(source: &str) -> Result<Self> {
+        let tokens = tokenize(source)?;
+        let mut parser = ExpressionParser {
+            source,
+            tokens,
+            position: 0,
+        };
+
+        let expr = parser.parse_or()?;
+        match parser.peek() {
+            Token {
+                kind: TokenKind::End,
+                ..
+            } => Ok(expr),
+            token => Err(syntax_error(
+                source,
+                token.column,
+                format!("unexpected {}", token.kind),
+            )),
+        }
+    }
+}
+
+/// Writes the expression back as constraint syntax, adding parentheses only
+/// where precedence requires them
+impl std::fmt::Display for Expression {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Expression::Literal(literal) => write!(f, "{}", literal),
+            Expression::Array(items) => {
+                write!(f, "[")?;
+                write_list(f, items)?;
+                write!(f, "]")
+            }
+            Expression::Path(path) => write_path(f, path),
+            Expression::IdRef { id, path } => {
+                write!(f, "@{}", id)?;
+                if !path.is_empty() {
+                    write!(f, ".")?;
+                    write_path(f, path)?;
+                }
+                Ok(())
+            }
+            Expression::Call { name, args } => {
+                write!(f, "{}(", name)?;
+                write_list(f, args)?;
+                write!(f, ")")
+            }
+            Expression::Not(operand) => match operand.as_ref() {
+                Expression::Binary { .. } => write!(f, "!({})", operand),
+                _ => write!(f, "!{}", operand),
+            },
+            Expression::Binary { op, left, right } => {
+                // Operators of equal precedence associate to the left
+                let precedence = op.precedence();
+                match left.as_ref() {
+                    Expression::Binary { op: inner, .. } if inner.precedence() < precedence => {
+                        write!(f, "({})", left)?
+                    }
+                    _ => write!(f, "{}", left)?,
+                }
+                write!(f, " {} ", op)?;
+                match right.as_ref() {
+                    Expression::Binary { op: inner, .. } if inner.precedence() <= precedence => {
+                        write!(f, "({})", right)
+                    }
+                    _ => write!(f, "{}", right),
                 }
             }
-            None => Ok(false),
         }
     }
+}
 
-    /// Evaluate equality comparison
-    fn evaluate_equality(&self, left: &str, right: &str) -> Result<bool> {
-        let left_val = self.resolve_value(left)?;
-        let right_val = self.resolve_value(right)?;
+fn write_list(f: &mut std::fmt::Formatter<'_>, items: &[Expression]) -> std::fmt::Result {
+    for (i, item) in items.iter().enumerate() {
+        if i > 0 {
+            write!(f, ", ")?;
+        }
+        write!(f, "{}", item)?;
+    }
+    Ok(())
+}
+
+fn write_path(f: &mut std::fmt::Formatter<'_>, path: &[PathSegment]) -> std::fmt::Result {
+    for (i, segment) in path.iter().enumerate() {
+        match segment {
+            PathSegment::Property(name) if i > 0 => write!(f, ".{}", name)?,
+            PathSegment::Property(name) => write!(f, "{}", name)?,
+            PathSegment::Index(index) => write!(f, "[{}]", index)?,
+        }
+    }
+    Ok(())
+}
+
+/// A literal value in a constraint expression
+#[derive(Debug, Clone, PartialEq)]
+pub enum Literal {
+    String(String),
+    Number(f64),
+    Bool(bool),
+    Null,
+}
 
-        match (left_val, right_val) {
-            (Some(l), Some(r)) => Ok(l == r),
-            (None, None) => {
-                // Only return true if both are actually missing properties, not literals
-                // Check if both are property references (not literals)
-                let left_is_literal = left.starts_with('"')
-                    || left.starts_with('\'')
-                    || left.parse::<f64>().is_ok()
-                    || left == "true"
-                    || left == "false";
-                let right_is_literal = right.starts_with('"')
-                    || right.starts_with('\'')
-                    || right.parse::<f64>().is_ok()
-                    || right == "true"
-                    || right == "false";
+impl Literal {
+    /// Get the literal as a JSON value
+    pub fn to_json(&self) -> JsonValue {
+        match self {
+            Literal::String(value) => JsonValue::String(value.clone()),
+            Literal::Number(value) => {
+                serde_json::Number::from_f64(*value).map_or(JsonValue::Null, JsonValue::Number)
+            }
+            Literal::Bool(value) => JsonValue::Bool(*value),
+            Literal::Null => JsonValue::Null,
+        }
+    }
+}
 
-                // If both are non-existent properties, they're equal
-                // If one is a literal and one is a missing property, they're not equal
-                Ok(!left_is_literal && !right_is_literal)
+impl std::fmt::Display for Literal {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Literal::String(value) => {
+                write!(f, "'{}'", value.replace('\\', "\\\\").replace('\'', "\\'"))
             }
-            _ => Ok(false),
+            Literal::Number(value) => write!(f, "{}", value),
+            Literal::Bool(value) => write!(f, "{}", value),
+            Literal::Null => write!(f, "null"),
+        }
+    }
+}
+
+/// A segment of a property path
+#[derive(Debug, Clone, PartialEq)]
+pub enum PathSegment {
+    /// A named property, as in `user.name`
+    Property(String),
+    /// An array index, as in `items[0]`
+    Index(usize),
+}
+
+/// Binary operators, from lowest to highest precedence: `||`, `&&`,
+/// `==`/`!=`, then `<`/`<=`/`>`/`>=`/`in`
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum BinaryOp {
+    Or,
+    And,
+    Eq,
+    Ne,
+    Lt,
+    Le,
+    Gt,
+    Ge,
+    /// Membership in an array, substring of a string or key of an object
+    In,
+}
+
+impl BinaryOp {
+    /// The operator as written in an expression
+    pub fn symbol(self) -> &'static str {
+        match self {
+            BinaryOp::Or => "||",
+            BinaryOp::And => "&&",
+            BinaryOp::Eq => "==",
+            BinaryOp::Ne => "!=",
+            BinaryOp::Lt => "<",
+            BinaryOp::Le => "<=",
+            BinaryOp::Gt => ">",
+            BinaryOp::Ge => ">=",
+            BinaryOp::In => "in",
+        }
+    }
+
+    /// Binding strength, higher binds tighter
+    fn precedence(self) -> u8 {
+        match self {
+            BinaryOp::Or => 1,
+            BinaryOp::And => 2,
+            BinaryOp::Eq | BinaryOp::Ne => 3,
+            BinaryOp::Lt | BinaryOp::Le | BinaryOp::Gt | BinaryOp::Ge | BinaryOp::In => 4,
         }
     }
 
-    /// Evaluate inequality comparison
-    fn evaluate_inequality(&self, left: &str, right: &str) -> Result<bool> {
-        self.evaluate_equality(left, right).map(|result| !result)
+    /// Check if this operator orders its operands
+    fn is_ordering(self) -> bool {
+        matches!(
+            self,
+            BinaryOp::Lt | BinaryOp::Le | BinaryOp::Gt | BinaryOp::Ge
+        )
+    }
+}
+
+impl std::fmt::Display for BinaryOp {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str(self.symbol())
+    }
+}
+
+/// A token of a constraint expression
+#[derive(Debug, Clone, PartialEq)]
+struct Token {
+    kind: TokenKind,
+    /// Column of the token's first character, counted from 1
+    column: usize,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+enum TokenKind {
+    Identifier(String),
+    /// `@` followed by an id, which may contain `-` and `:`
+    Id(String),
+    Number(f64),
+    String(String),
+    Dot,
+    LeftBracket,
+    RightBracket,
+    LeftParen,
+    RightParen,
+    Comma,
+    Not,
+    Op(BinaryOp),
+    End,
+}
+
+impl std::fmt::Display for TokenKind {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            TokenKind::Identifier(name) => write!(f, "'{}'", name),
+            TokenKind::Id(id) => write!(f, "'@{}'", id),
+            TokenKind::Number(value) => write!(f, "number {}", value),
+            TokenKind::String(_) => write!(f, "string"),
+            TokenKind::Dot => write!(f, "'.'"),
+            TokenKind::LeftBracket => write!(f, "'['"),
+            TokenKind::RightBracket => write!(f, "']'"),
+            TokenKind::LeftParen => write!(f, "'('"),
+            TokenKind::RightParen => write!(f, "')'"),
+            TokenKind::Comma => write!(f, "','"),
+            TokenKind::Not => write!(f, "'!'"),
+            TokenKind::Op(op) => write!(f, "'{}'", op.symbol()),
+            TokenKind::End => write!(f, "end of expression"),
+        }
     }
+}
+
+/// Build a constraint syntax error pointing at `column`
+fn syntax_error(source: &str, column: usize, message: String) -> Error {
+    Error::constraint_owned(format!(
+        "Invalid constraint '{}': {} at column {}",
+        source, message, column
+    ))
+}
 
-    /// Evaluate greater than comparison
-    fn evaluate_greater_than(&self, left: &str, right: &str) -> Result<bool> {
-        let left_val = self.resolve_value(left)?;
-        let right_val = self.resolve_value(right)?;
+/// Split a constraint expression into tokens
+fn tokenize(source: &str) -> Result<Vec<Token>> {
+    let chars: Vec<char> = source.chars().collect();
+    let mut tokens = Vec::new();
+    let mut i = 0;
+
+    while i < chars.len() {
+        let c = chars[i];
+        let column = i + 1;
+        if c.is_whitespace() {
+            i += 1;
+            continue;
+        }
 
-        match (left_val, right_val) {
-            (Some(l), Some(r)) => {
-                // Try to parse as numbers
-                if let (Ok(l_num), Ok(r_num)) = (l.parse::<f64>(), r.parse::<f64>()) {
-                    Ok(l_num > r_num)
-                } else {
-                    // String comparison
-                    Ok(l > r)
+        let next = chars.get(i + 1).copied();
+        let (kind, len) = match (c, next) {
+            ('|', Some('|')) => (TokenKind::Op(BinaryOp::Or), 2),
+            ('&', Some('&')) => (TokenKind::Op(BinaryOp::And), 2),
+            ('=', Some('=')) => (TokenKind::Op(BinaryOp::Eq), 2),
+            ('!', Some('=')) => (TokenKind::Op(BinaryOp::Ne), 2),
+            ('<', Some('=')) => (TokenKind::Op(BinaryOp::Le), 2),
+            ('>', Some('=')) => (TokenKind::Op(BinaryOp::Ge), 2),
+            ('<', _) => (TokenKind::Op(BinaryOp::Lt), 1),
+            ('>', _) => (TokenKind::Op(BinaryOp::Gt), 1),
+            ('!', _) => (TokenKind::Not, 1),
+            ('.', _) => (TokenKind::Dot, 1),
+            ('[', _) => (TokenKind::LeftBracket, 1),
+            (']', _) => (TokenKind::RightBracket, 1),
+            ('(', _) => (TokenKind::LeftParen, 1),
+            (')', _) => (TokenKind::RightParen, 1),
+            (',', _) => (TokenKind::Comma, 1),
+            ('"' | '\'', _) => lex_string(source, &chars, i)?,
+            ('@', _) => {
+                let len = chars[i + 1..]
+                    .iter()
+                    .take_while(|c| is_identifier_char(**c) || **c == ':')
+                    .count();
+                if len == 0 {
+                    return Err(syntax_error(
+                        source,
+                        column,
+                        "expected an id after '@'".into(),
+                    ));
                 }
+                let id = chars[i + 1..i + 1 + len].iter().collect();
+                (TokenKind::Id(id), len + 1)
+            }
+            (c, next)
+                if c.is_ascii_digit()
+                    || (c == '-' && next.is_some_and(|next| next.is_ascii_digit())) =>
+            {
+                // After a dot only an integer can follow, as in `items.0`
+                let after_dot = matches!(
+                    tokens.last(),
+                    Some(Token {
+                        kind: TokenKind::Dot,
+                        ..
+                    })
+                );
+                lex_number(source, &chars, i, after_dot)?
+            }
+            (c, _) if is_identifier_char(c) && c != '-' => {
+                let len = chars[i..]
+                    .iter()
+                    .take_while(|c| is_identifier_char(**c))
+                    .count();
+                let name: String = chars[i..i + len].iter().collect();
+                let kind = match name.as_str() {
+                    "in" => TokenKind::Op(BinaryOp::In),
+                    _ => TokenKind::Identifier(name),
+                };
+                (kind, len)
+            }
+            (c, _) => {
+                return Err(syntax_error(
+                    source,
+                    column,
+                    format!("unexpected character '{}'", c),
+                ))
+            }
+        };
+
+        tokens.push(Token { kind, column });
+        i += len;
+    }
+
+    tokens.push(Token {
+        kind: TokenKind::End,
+        column: chars.len() + 1,
+    });
+    Ok(tokens)
+}
+
+/// Characters allowed in property names after the first
+fn is_identifier_char(c: char) -> bool {
+    c.is_alphanumeric() || matches!(c, '_' | '$' | '-')
+}
+
+/// Lex a quoted string starting at `start`, returning the token and its length
+fn lex_string(source: &str, chars: &[char], start: usize) -> Result<(TokenKind, usize)> {
+    let quote = chars[start];
+    let mut value = String::new();
+    let mut i = start + 1;
+
+    while i < chars.len() {
+        match chars[i] {
+            c if c == quote => return Ok((TokenKind::String(value), i + 1 - start)),
+            '\\' => {
+                let escaped = match chars.get(i + 1) {
+                    Some('n') => '\n',
+                    Some('t') => '\t',
+                    Some('r') => '\r',
+                    Some('0') => '\0',
+                    Some('u') => {
+                        let hex: String = chars.iter().skip(i + 2).take(4).collect();
+                        let code = (hex.len() == 4)
+                            .then(|| u32::from_str_radix(&hex, 16).ok())
+                            .flatten()
+                            .and_then(char::from_u32)
+                            .ok_or_else(|| {
+                                syntax_error(source, i + 1, "invalid unicode escape".into())
+                            })?;
+                        value.push(code);
+                        i += 6;
+                        continue;
+                    }
+                    Some(c @ ('\\' | '\'' | '"')) => *c,
+                    Some(c) => {
+                        return Err(syntax_error(
+                            source,
+                            i + 1,
+                            format!("unknown escape '\\{}'", c),
+                        ))
+                    }
+                    None => break,
+                };
+                value.push(escaped);
+                i += 2;
+            }
+            c => {
+                value.push(c);
+                i += 1;
             }
-            _ => Ok(false),
         }
     }
 
-    /// Evaluate less than comparison
-    fn evaluate_less_than(&self, left: &str, right: &str) -> Result<bool> {
-        let left_val = self.resolve_value(left)?;
-        let right_val = self.resolve_value(right)?;
+    Err(syntax_error(
+        source,
+        start + 1,
+        "unterminated string".into(),
+    ))
+}
 
-        match (left_val, right_val) {
-            (Some(l), Some(r)) => {
-                // Try to parse as numbers
-                if let (Ok(l_num), Ok(r_num)) = (l.parse::<f64>(), r.parse::<f64>()) {
-                    Ok(l_num < r_num)
-                } else {
-                    // String comparison
-                    Ok(l < r)
-                }
+/// Lex a number starting at `start`, returning the token and its length
+fn lex_number(
+    source: &str,
+    chars: &[char],
+    start: usize,
+    integer_only: bool,
+) -> Result<(TokenKind, usize)> {
+    let mut end = start + 1;
+    let digits = |end: &mut usize| {
+        while chars.get(*end).is_some_and(char::is_ascii_digit) {
+            *end += 1;
+        }
+    };
+    digits(&mut end);
+
+    if !integer_only {
+        if chars.get(end) == Some(&'.') && chars.get(end + 1).is_some_and(char::is_ascii_digit) {
+            end += 1;
+            digits(&mut end);
+        }
+        if matches!(chars.get(end), Some('e' | 'E')) {
+            let sign = usize::from(matches!(chars.get(end + 1), Some('+' | '-')));
+            if chars.get(end + 1 + sign).is_some_and(char::is_ascii_digit) {
+                end += 1 + sign;
+                digits(&mut end);
+            }
+        }
+    }
+
+    let text: String = chars[start..end].iter().collect();
+    let value = text
+        .parse()
+        .map_err(|_| syntax_error(source, start + 1, format!("invalid number '{}'", text)))?;
+    Ok((TokenKind::Number(value), end - start))
+}
+
+/// Recursive-descent parser over the tokens of a constraint expression
+struct ExpressionParser<'a> {
+    source: &'a str,
+    tokens: Vec<Token>,
+    position: usize,
+}
+
+/// Parses the operands of one precedence level
+type OperandParser<'a> = fn(&mut ExpressionParser<'a>) -> Result<Expression>;
+
+impl<'a> ExpressionParser<'a> {
+    fn peek(&self) -> &Token {
+        &self.tokens[self.position]
+    }
+
+    fn advance(&mut self) -> Token {
+        let token = self.tokens[self.position].clone();
+        if token.kind != TokenKind::End {
+            self.position += 1;
+        }
+        token
+    }
+
+    /// Consume the next token if it is `kind`
+    fn eat(&mut self, kind: &TokenKind) -> bool {
+        if &self.peek().kind == kind {
+            self.advance();
+            true
+        } else {
+            false
+        }
+    }
+
+    /// Consume the next token, which must be `kind`
+    fn expect(&mut self, kind: TokenKind) -> Result<()> {
+        if self.eat(&kind) {
+            Ok(())
+        } else {
+            Err(self.unexpected(&format!("{}", kind)))
+        }
+    }
+
+    /// Error for an unexpected next token, when `expected` was wanted
+    fn unexpected(&self, expected: &str) -> Error {
+        let token = self.peek();
+        syntax_error(
+            self.source,
+            token.column,
+            format!("expected {}, found {}", expected, token.kind),
+        )
+    }
+
+    /// Parse binary operators of one precedence level
+    fn parse_binary(&mut self, ops: &[BinaryOp], operand: OperandParser<'a>) -> Result<Expression> {
+        let mut left = operand(self)?;
+        while let TokenKind::Op(op) = self.peek().kind {
+            if !ops.contains(&op) {
+                break;
             }
-            _ => Ok(false),
+            self.advance();
+            let right = operand(self)?;
+            left = Expression::Binary {
+                op,
+                left: Box::new(left),
+                right: Box::new(right),
+            };
         }
+        Ok(left)
     }
 
-    /// Evaluate greater than or equal comparison
-    fn evaluate_greater_equal(&self, left: &str, right: &str) -> Result<bool> {
-        let less = self.evaluate_less_than(left, right)?;
-        Ok(!less)
+    fn parse_or(&mut self) -> Result<Expression> {
+        self.parse_binary(&[BinaryOp::Or], Self::parse_and)
     }
 
-    /// Evaluate less than or equal comparison
-    fn evaluate_less_equal(&self, left: &str, right: &str) -> Result<bool> {
-        let greater = self.evaluate_greater_than(left, right)?;
-        Ok(!greater)
+    fn parse_and(&mut self) -> Result<Expression> {
+        self.parse_binary(&[BinaryOp::And], Self::parse_equality)
     }
 
-    /// Evaluate logical AND
-    fn evaluate_logical_and(&self, left: &str, right: &str) -> Result<bool> {
-        let left_result = self.evaluate_expression(left)?;
-        let right_result = self.evaluate_expression(right)?;
-        Ok(left_result && right_result)
+    fn parse_equality(&mut self) -> Result<Expression> {
+        self.parse_binary(&[BinaryOp::Eq, BinaryOp::Ne], Self::parse_comparison)
     }
 
-    /// Evaluate logical OR
-    fn evaluate_logical_or(&self, left: &str, right: &str) -> Result<bool> {
-        let left_result = self.evaluate_expression(left)?;
-        let right_result = self.evaluate_expression(right)?;
-        Ok(left_result || right_result)
+    fn parse_comparison(&mut self) -> Result<Expression> {
+        self.parse_binary(
+            &[
+                BinaryOp::Lt,
+                BinaryOp::Le,
+                BinaryOp::Gt,
+                BinaryOp::Ge,
+                BinaryOp::In,
+            ],
+            Self::parse_unary,
+        )
     }
 
-    /// Resolve a value from a reference
-    fn resolve_value(&self, reference: &str) -> Result<Option<String>> {
-        // Check if it's a literal string (both single and double quotes)
-        if (reference.starts_with('"') && reference.ends_with('"'))
-            || (reference.starts_with('\'') && reference.ends_with('\''))
-        {
-            return Ok(Some(reference[1..reference.len() - 1].to_string()));
+    fn parse_unary(&mut self) -> Result<Expression> {
+        if self.eat(&TokenKind::Not) {
+            return Ok(Expression::Not(Box::new(self.parse_unary()?)));
         }
+        self.parse_primary()
+    }
 
-        // Check if it's a literal number
-        if reference.parse::<f64>().is_ok() {
-            return Ok(Some(reference.to_string()));
+    fn parse_primary(&mut self) -> Result<Expression> {
+        let token = self.advance();
+        match token.kind {
+            TokenKind::LeftParen => {
+                let expr = self.parse_or()?;
+                self.expect(TokenKind::RightParen)?;
+                Ok(expr)
+            }
+            TokenKind::LeftBracket => {
+                Ok(Expression::Array(self.parse_list(TokenKind::RightBracket)?))
+            }
+            TokenKind::String(value) => Ok(Expression::Literal(Literal::String(value))),
+            TokenKind::Number(value) => Ok(Expression::Literal(Literal::Number(value))),
+            TokenKind::Identifier(name) => match name.as_str() {
+                "true" => Ok(Expression::Literal(Literal::Bool(true))),
+                "false" => Ok(Expression::Literal(Literal::Bool(false))),
+                "null" => Ok(Expression::Literal(Literal::Null)),
+                _ if self.eat(&TokenKind::LeftParen) => Ok(Expression::Call {
+                    name,
+                    args: self.parse_list(TokenKind::RightParen)?,
+                }),
+                _ => {
+                    let mut path = vec![PathSegment::Property(name)];
+                    self.parse_path(&mut path)?;
+                    Ok(Expression::Path(path))
+                }
+            },
+            TokenKind::Id(id) => {
+                let mut path = Vec::new();
+                self.parse_path(&mut path)?;
+                Ok(Expression::IdRef { id, path })
+            }
+            kind => Err(syntax_error(
+                self.source,
+                token.column,
+                format!("expected a value, found {}", kind),
+            )),
         }
+    }
 
-        // Check if it's a boolean literal
-        if reference == "true" || reference == "false" {
-            return Ok(Some(reference.to_string()));
+    /// Parse comma-separated expressions up to and including `close`
+    fn parse_list(&mut self, close: TokenKind) -> Result<Vec<Expression>> {
+        let mut items = Vec::new();
+        if self.eat(&close) {
+            return Ok(items);
+        }
+        loop {
+            items.push(self.parse_or()?);
+            if self.eat(&close) {
+                return Ok(items);
+            }
+            self.expect(TokenKind::Comma)?;
         }
+    }
 
-        // Check if it's an @id reference
-        if reference.starts_with('@') {
-            let id = &reference[1..];
-            if let Some(value) = self.id_map.get(id) {
-                // For now, convert to string representation
-                return Ok(value.get_property(&[]).map(|cow| cow.to_string()));
+    /// Parse the `.name` and `[index]` segments following the start of a path
+    fn parse_path(&mut self, path: &mut Vec<PathSegment>) -> Result<()> {
+        loop {
+            if self.eat(&TokenKind::Dot) {
+                let token = self.advance();
+                match token.kind {
+                    TokenKind::Identifier(name) => path.push(PathSegment::Property(name)),
+                    TokenKind::Number(index) if index >= 0.0 && index.fract() == 0.0 => {
+                        path.push(PathSegment::Index(index as usize))
+                    }
+                    kind => {
+                        return Err(syntax_error(
+                            self.source,
+                            token.column,
+                            format!("expected a property name, found {}", kind),
+                        ))
+                    }
+                }
+            } else if self.eat(&TokenKind::LeftBracket) {
+                let token = self.advance();
+                match token.kind {
+                    TokenKind::Number(index) if index >= 0.0 && index.fract() == 0.0 => {
+                        path.push(PathSegment::Index(index as usize))
+                    }
+                    TokenKind::String(name) => path.push(PathSegment::Property(name)),
+                    kind => {
+                        return Err(syntax_error(
+                            self.source,
+                            token.column,
+                            format!("expected an array index, found {}", kind),
+                        ))
+                    }
+                }
+                self.expect(TokenKind::RightBracket)?;
+            } else {
+                return Ok(());
             }
-            return Ok(None);
         }
+    }
+}
 
-        // Otherwise, treat as property path
-        let path: Vec<String> = reference.split('.').map(String::from).collect();
-        Ok(self.data.get_property(&path).map(|cow| cow.to_string()))
+/// Evaluates constraint expressions during rendering
+///
+/// Implement this to use another expression language, such as a CEL-like or
+/// JSONLogic-style evaluator or a call to a policy engine, in place of the
+/// built-in grammar. The evaluator receives each expression exactly as written
+/// in the template, and attach it with
+/// [`HtmlTemplateBuilder::with_constraint_evaluator`].
+///
+/// [`HtmlTemplateBuilder::with_constraint_evaluator`]: crate::HtmlTemplateBuilder::with_constraint_evaluator
+///
+/// # Examples
+///
+/// ```rust,ignore
+/// use html_template::{ConstraintContext, ConstraintEvaluator, Result};
+///
+/// /// Shows an element when every comma-separated flag is set
+/// struct FlagEvaluator;
+///
+/// impl ConstraintEvaluator for FlagEvaluator {
+///     fn evaluate(&self, expression: &str, context: &ConstraintContext<'_>) -> Result<bool> {
+///         Ok(expression.split(',').all(|flag| {
+///             context.data().get_property(&[flag.trim().to_string()]).as_deref() == Some("true")
+///         }))
+///     }
+/// }
+/// ```
+pub trait ConstraintEvaluator: Send + Sync {
+    /// Decide whether an element whose constraint is `expression` is rendered
+    ///
+    /// An error fails the render.
+    fn evaluate(&self, expression: &str, context: &ConstraintContext<'_>) -> Result<bool>;
+
+    /// Check an expression when the template is compiled
+    ///
+    /// Errors fail compilation. The default accepts every expression.
+    fn validate(&self, _expression: &str) -> Result<()> {
+        Ok(())
     }
 }
 
-/// Constraint evaluator that can be used during rendering
-pub struct ConstraintEvaluator;
+/// The built-in constraint expression grammar
+///
+/// Templates use this grammar unless another evaluator is attached. They parse
+/// their expressions once, when the template is compiled, whereas
+/// [`evaluate`](ConstraintEvaluator::evaluate) here parses the expression on
+/// every call. Custom evaluators can delegate to it, at that cost.
+#[derive(Debug, Clone, Copy, Default)]
+pub struct DefaultConstraintEvaluator;
 
-impl ConstraintEvaluator {
+impl DefaultConstraintEvaluator {
     /// Create a new constraint evaluator
     pub fn new() -> Self {
         Self
@@ -282,17 +1472,367 @@ impl ConstraintEvaluator {
     }
 }
 
-impl Default for ConstraintEvaluator {
+impl ConstraintEvaluator for DefaultConstraintEvaluator {
+    fn evaluate(&self, expression: &str, context: &ConstraintContext<'_>) -> Result<bool> {
+        context.evaluate_expression(expression)
+    }
+
+    fn validate(&self, expression: &str) -> Result<()> {
+        Expression::parse(expression).map(|_| ())
+    }
+}
+
+/// Names of the attributes that hold constraints
+///
+/// # Examples
+///
+/// ```rust,ignore
+/// // <p data-if="admin">...</p><p data-elif="editor">...</p><p data-otherwise>...</p>
+/// let attributes = ConstraintAttributes::new("data-if", "data-elif", "data-otherwise");
+/// ```
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct ConstraintAttributes {
+    /// Attribute holding an element's constraint, `data-constraint` by default
+    pub constraint: String,
+    /// Attribute continuing a chain with another constraint, `data-else-if` by default
+    pub else_if: String,
+    /// Attribute ending a chain, `data-else` by default
+    pub else_branch: String,
+}
+
+impl ConstraintAttributes {
+    /// Use the given attribute names
+    pub fn new<C, I, E>(constraint: C, else_if: I, else_branch: E) -> Self
+    where
+        C: Into<String>,
+        I: Into<String>,
+        E: Into<String>,
+    {
+        Self {
+            constraint: constraint.into(),
+            else_if: else_if.into(),
+            else_branch: else_branch.into(),
+        }
+    }
+}
+
+impl Default for ConstraintAttributes {
     fn default() -> Self {
-        Self::new()
+        Self::new("data-constraint", "data-else-if", "data-else")
+    }
+}
+
+/// A likely mistake in a constraint expression, found by
+/// [`HtmlTemplate::check_constraints`](crate::HtmlTemplate::check_constraints)
+#[derive(Debug, Clone, PartialEq)]
+pub struct ConstraintWarning {
+    /// The expression as written in the template
+    pub expression: String,
+    /// What looks wrong with it
+    pub kind: ConstraintWarningKind,
+}
+
+/// The problems reported by constraint checks
+#[derive(Debug, Clone, PartialEq)]
+pub enum ConstraintWarningKind {
+    /// A path that no `itemprop` or `${}` binding in the template uses, with
+    /// the closest bound name when the path looks like a typo of it
+    UnboundPath {
+        path: String,
+        suggestion: Option<String>,
+    },
+    /// A path found nowhere in the sample data
+    MissingPath { path: String },
+    /// An `@id` reference to an item that isn't in the sample data
+    UnknownId { id: String },
+    /// A call to a function that isn't registered
+    UnknownFunction { name: String },
+    /// A comparison that can never hold, such as `count > true` or `'abc' < 5`
+    IncompatibleComparison { comparison: String },
+    /// A condition that doesn't depend on the data, such as `1 == 1` or `show || true`
+    Constant { condition: String, value: bool },
+}
+
+impl std::fmt::Display for ConstraintWarning {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "constraint '{}': ", self.expression)?;
+        match &self.kind {
+            ConstraintWarningKind::UnboundPath { path, suggestion } => {
+                write!(f, "`{}` is not bound by any itemprop or ${{}} path", path)?;
+                match suggestion {
+                    Some(suggestion) => write!(f, "; did you mean `{}`?", suggestion),
+                    None => Ok(()),
+                }
+            }
+            ConstraintWarningKind::MissingPath { path } => {
+                write!(f, "`{}` is not in the sample data", path)
+            }
+            ConstraintWarningKind::UnknownId { id } => {
+                write!(f, "no item in the sample data has id '@{}'", id)
+            }
+            ConstraintWarningKind::UnknownFunction { name } => {
+                write!(f, "unknown function '{}'", name)
+            }
+            ConstraintWarningKind::IncompatibleComparison { comparison } => {
+                write!(
+                    f,
+                    "`{}` compares incompatible values and is always false",
+                    comparison
+                )
+            }
+            ConstraintWarningKind::Constant { condition, value } => {
+                write!(f, "`{}` is always {}", condition, value)
+            }
+        }
+    }
+}
+
+/// Checks parsed constraint expressions for likely mistakes without rendering
+pub(crate) struct ConstraintChecker<'a> {
+    /// Names bound by the template, which paths are checked against
+    bindings: &'a BTreeSet<String>,
+    functions: &'a ConstraintFunctionRegistry,
+    /// Sample data that paths and ids are looked up in instead of `bindings`
+    sample: Option<(&'a JsonValue, ItemIds<'a>)>,
+}
+
+impl<'a> ConstraintChecker<'a> {
+    /// Check against the names a template binds, with `functions` or the
+    /// built-ins callable
+    pub(crate) fn new(
+        bindings: &'a BTreeSet<String>,
+        functions: Option<&'a ConstraintFunctionRegistry>,
+    ) -> Self {
+        Self {
+            bindings,
+            functions: functions.unwrap_or(&DEFAULT_FUNCTIONS),
+            sample: None,
+        }
+    }
+
+    /// Look up paths and `@id` references in `sample` instead
+    pub(crate) fn with_sample(mut self, sample: &'a JsonValue) -> Self {
+        self.sample = Some((sample, ItemIds::collect(sample)));
+        self
+    }
+
+    /// Check the expression parsed from `source`
+    pub(crate) fn check(&self, source: &str, expr: &Expression) -> Vec<ConstraintWarning> {
+        let mut kinds = Vec::new();
+        self.visit(expr, true, &mut kinds);
+        kinds
+            .into_iter()
+            .map(|kind| ConstraintWarning {
+                expression: source.to_string(),
+                kind,
+            })
+            .collect()
+    }
+
+    /// Visit `expr`, which is a condition when its truthiness decides the
+    /// result rather than its value, as with operands of `&&`, `||` and `!`
+    fn visit(&self, expr: &Expression, condition: bool, out: &mut Vec<ConstraintWarningKind>) {
+        if let Expression::Binary { op, left, right } = expr {
+            if is_incompatible_comparison(*op, left, right) {
+                out.push(ConstraintWarningKind::IncompatibleComparison {
+                    comparison: expr.to_string(),
+                });
+                return;
+            }
+        }
+        if condition {
+            if let Some(value) = constant_value(expr) {
+                out.push(ConstraintWarningKind::Constant {
+                    condition: expr.to_string(),
+                    value,
+                });
+                return;
+            }
+        }
+
+        match expr {
+            Expression::Literal(_) => {}
+            Expression::Array(items) => items.iter().for_each(|item| self.visit(item, false, out)),
+            Expression::Path(path) => self.check_path(expr, path, out),
+            Expression::IdRef { id, .. } => {
+                if let Some((_, ids)) = &self.sample {
+                    if ids.get(id).is_none() {
+                        out.push(ConstraintWarningKind::UnknownId { id: id.clone() });
+                    }
+                }
+            }
+            Expression::Call { name, args } => {
+                if !self.functions.contains(name) {
+                    out.push(ConstraintWarningKind::UnknownFunction { name: name.clone() });
+                }
+                args.iter().for_each(|arg| self.visit(arg, false, out));
+            }
+            Expression::Not(operand) => self.visit(operand, true, out),
+            Expression::Binary { op, left, right } => {
+                let logical = matches!(op, BinaryOp::Or | BinaryOp::And);
+                self.visit(left, logical, out);
+                self.visit(right, logical, out);
+            }
+        }
+    }
+
+    fn check_path(
+        &self,
+        expr: &Expression,
+        path: &[PathSegment],
+        out: &mut Vec<ConstraintWarningKind>,
+    ) {
+        if let Some((sample, _)) = &self.sample {
+            if !path_in_sample(sample, path) {
+                out.push(ConstraintWarningKind::MissingPath {
+                    path: expr.to_string(),
+                });
+            }
+            return;
+        }
+
+        if let Some(PathSegment::Property(name)) = path.first() {
+            if !self.bindings.contains(name) {
+                out.push(ConstraintWarningKind::UnboundPath {
+                    path: expr.to_string(),
+                    suggestion: self.closest_binding(name),
+                });
+            }
+        }
     }
+
+    /// Find the bound name a misspelt `name` most likely meant
+    fn closest_binding(&self, name: &str) -> Option<String> {
+        self.bindings
+            .iter()
+            .map(|binding| (edit_distance(name, binding), binding))
+            .filter(|(distance, _)| *distance <= 2 && *distance < name.chars().count())
+            .min_by_key(|(distance, _)| *distance)
+            .map(|(_, binding)| binding.clone())
+    }
+}
+
+/// Get the truthiness of a condition that doesn't depend on the data
+fn constant_value(expr: &Expression) -> Option<bool> {
+    match expr {
+        Expression::Not(operand) => constant_value(operand).map(|value| !value),
+        Expression::Binary {
+            op: op @ (BinaryOp::Or | BinaryOp::And),
+            left,
+            right,
+        } => {
+            // `x || true` and `x && false` hold whatever `x` is
+            let absorbing = *op == BinaryOp::Or;
+            match (constant_value(left), constant_value(right)) {
+                (Some(value), _) | (_, Some(value)) if value == absorbing => Some(absorbing),
+                (Some(_), Some(_)) => Some(!absorbing),
+                _ => None,
+            }
+        }
+        _ => literal_value(expr)
+            .map(|value| is_truthy(Some(&value)))
+            .or_else(|| match expr {
+                Expression::Binary { .. } if is_data_free(expr) => {
+                    ConstraintContext::new(&JsonValue::Null)
+                        .evaluate_parsed(expr)
+                        .ok()
+                }
+                _ => None,
+            }),
+    }
+}
+
+/// Check if an expression only involves literals
+fn is_data_free(expr: &Expression) -> bool {
+    match expr {
+        Expression::Literal(_) => true,
+        Expression::Array(items) => items.iter().all(is_data_free),
+        Expression::Not(operand) => is_data_free(operand),
+        Expression::Binary { left, right, .. } => is_data_free(left) && is_data_free(right),
+        Expression::Path(_) | Expression::IdRef { .. } | Expression::Call { .. } => false,
+    }
+}
+
+/// Get the value of a literal or an array of literals
+fn literal_value(expr: &Expression) -> Option<JsonValue> {
+    match expr {
+        Expression::Literal(literal) => Some(literal.to_json()),
+        Expression::Array(items) => items
+            .iter()
+            .map(literal_value)
+            .collect::<Option<_>>()
+            .map(JsonValue::Array),
+        _ => None,
+    }
+}
+
+/// Check if a comparison can never hold because of the literals it compares
+fn is_incompatible_comparison(op: BinaryOp, left: &Expression, right: &Expression) -> bool {
+    let (left, right) = (literal_value(left), literal_value(right));
+    let unordered = |value: &Option<JsonValue>| {
+        matches!(
+            value,
+            Some(JsonValue::Bool(_) | JsonValue::Null | JsonValue::Array(_) | JsonValue::Object(_))
+        )
+    };
+
+    match op {
+        op if op.is_ordering() => {
+            unordered(&left)
+                || unordered(&right)
+                || matches!((&left, &right), (Some(left), Some(right)) if compare_values(left, right).is_none())
+        }
+        BinaryOp::In => matches!(
+            right,
+            Some(JsonValue::Number(_) | JsonValue::Bool(_) | JsonValue::Null)
+        ),
+        BinaryOp::Eq | BinaryOp::Ne => match (&left, &right) {
+            (Some(left), Some(right)) => {
+                std::mem::discriminant(left) != std::mem::discriminant(right)
+                    && !values_equal(left, right)
+            }
+            _ => false,
+        },
+        _ => false,
+    }
+}
+
+/// Check if `path` resolves in `value` or in any object or array nested in it,
+/// since constraints inside scopes and array items see only part of the data
+fn path_in_sample(value: &JsonValue, path: &[PathSegment]) -> bool {
+    resolve_path(value, path).is_some()
+        || match value {
+            JsonValue::Object(map) => map.values().any(|value| path_in_sample(value, path)),
+            JsonValue::Array(items) => items.iter().any(|item| path_in_sample(item, path)),
+            _ => false,
+        }
+}
+
+/// Levenshtein distance between two strings, counted in characters
+fn edit_distance(a: &str, b: &str) -> usize {
+    let b: Vec<char> = b.chars().collect();
+    let mut previous: Vec<usize> = (0..=b.len()).collect();
+    for (i, a) in a.chars().enumerate() {
+        let mut current = vec![i + 1; b.len() + 1];
+        for (j, b) in b.iter().enumerate() {
+            let substitution = previous[j] + usize::from(a != *b);
+            current[j + 1] = substitution.min(previous[j + 1] + 1).min(current[j] + 1);
+        }
+        previous = current;
+    }
+    previous[b.len()]
 }
 
 #[cfg(test)]
 mod tests {
     use super::*;
+    use crate::types::CompiledExpression;
     use serde_json::json;
 
+    fn compiled(source: &str) -> CompiledExpression {
+        CompiledExpression::parse(source).unwrap()
+    }
+
     #[test]
     fn test_property_exists() {
         let data = json!({
@@ -302,16 +1842,14 @@ mod tests {
 
         let context = ConstraintContext::new(&data);
         let constraint = Constraint {
-            element_selector: "div".to_string(),
-            constraint_type: ConstraintType::Expression("name".to_string()),
+            constraint_type: ConstraintType::Expression(compiled("name")),
             scope: None,
         };
 
         assert!(context.evaluate(&constraint).unwrap());
 
         let constraint = Constraint {
-            element_selector: "div".to_string(),
-            constraint_type: ConstraintType::Expression("missing".to_string()),
+            constraint_type: ConstraintType::Expression(compiled("missing")),
             scope: None,
         };
 
@@ -329,24 +1867,21 @@ mod tests {
 
         // String equality
         let constraint = Constraint {
-            element_selector: "div".to_string(),
-            constraint_type: ConstraintType::Expression("status == \"active\"".to_string()),
+            constraint_type: ConstraintType::Expression(compiled("status == \"active\"")),
             scope: None,
         };
         assert!(context.evaluate(&constraint).unwrap());
 
         // Number equality
         let constraint = Constraint {
-            element_selector: "div".to_string(),
-            constraint_type: ConstraintType::Expression("count == 5".to_string()),
+            constraint_type: ConstraintType::Expression(compiled("count == 5")),
             scope: None,
         };
         assert!(context.evaluate(&constraint).unwrap());
 
         // Inequality
         let constraint = Constraint {
-            element_selector: "div".to_string(),
-            constraint_type: ConstraintType::Expression("status != \"inactive\"".to_string()),
+            constraint_type: ConstraintType::Expression(compiled("status != \"inactive\"")),
             scope: None,
         };
         assert!(context.evaluate(&constraint).unwrap());
@@ -363,24 +1898,21 @@ mod tests {
 
         // Greater than
         let constraint = Constraint {
-            element_selector: "div".to_string(),
-            constraint_type: ConstraintType::Expression("age > 20".to_string()),
+            constraint_type: ConstraintType::Expression(compiled("age > 20")),
             scope: None,
         };
         assert!(context.evaluate(&constraint).unwrap());
 
         // Less than
         let constraint = Constraint {
-            element_selector: "div".to_string(),
-            constraint_type: ConstraintType::Expression("age < limit".to_string()),
+            constraint_type: ConstraintType::Expression(compiled("age < limit")),
             scope: None,
         };
         assert!(context.evaluate(&constraint).unwrap());
 
         // Greater than or equal
         let constraint = Constraint {
-            element_selector: "div".to_string(),
-            constraint_type: ConstraintType::Expression("age >= 25".to_string()),
+            constraint_type: ConstraintType::Expression(compiled("age >= 25")),
             scope: None,
         };
         assert!(context.evaluate(&constraint).unwrap());
@@ -393,7 +1925,6 @@ mod tests {
         let context = ConstraintContext::new(&data).with_scope("admin");
 
         let constraint = Constraint {
-            element_selector: "div".to_string(),
             constraint_type: ConstraintType::Scope("admin".to_string()),
             scope: Some("admin".to_string()),
         };
@@ -401,7 +1932,6 @@ mod tests {
         assert!(context.evaluate(&constraint).unwrap());
 
         let constraint = Constraint {
-            element_selector: "div".to_string(),
             constraint_type: ConstraintType::Scope("user".to_string()),
             scope: Some("user".to_string()),
         };
@@ -421,11 +1951,429 @@ mod tests {
         context.register_id("limit", &reference_data);
 
         let constraint = Constraint {
-            element_selector: "div".to_string(),
-            constraint_type: ConstraintType::Expression("current < @limit".to_string()),
+            constraint_type: ConstraintType::Expression(compiled("current < @limit")),
             scope: None,
         };
 
         assert!(context.evaluate(&constraint).unwrap());
     }
+
+    #[test]
+    fn test_collected_item_ids() {
+        let data = json!({
+            "author": {"@id": "user-42", "role": "admin"},
+            "team": [
+                {"@id": "https://example.com/team#user-7", "role": "editor"},
+                {"@id": "user-42", "role": "duplicate"}
+            ]
+        });
+        let ids = ItemIds::collect(&data);
+        assert_eq!(ids.len(), 3);
+
+        let item = json!({"owner": "user-7"});
+        let context = ConstraintContext::new(&item).with_ids(&ids);
+        assert!(context
+            .evaluate_expression("@user-42.role == 'admin'")
+            .unwrap());
+        assert!(context
+            .evaluate_expression("@user-7.role == 'editor'")
+            .unwrap());
+        assert!(context
+            .evaluate_expression("@user-7.missing == null")
+            .map(|holds| !holds)
+            .unwrap());
+
+        let error = context.evaluate_expression("@user-99.role").unwrap_err();
+        assert!(error.to_string().contains("Unknown item id '@user-99'"));
+    }
+
+    fn holds(data: &serde_json::Value, expr: &str) -> bool {
+        ConstraintContext::new(data)
+            .evaluate_expression(expr)
+            .unwrap()
+    }
+
+    #[test]
+    fn test_precedence_and_grouping() {
+        let data = json!({"a": true, "b": false, "c": true});
+
+        assert!(holds(&data, "a && (b || c)"));
+        assert!(!holds(&data, "(a && b) || !c"));
+        assert!(holds(&data, "b && c || a"));
+        assert!(!holds(&data, "!a && c"));
+        assert!(holds(&data, "!(a && b)"));
+        assert!(holds(&data, "!!a"));
+
+        // && binds tighter than ||, comparisons tighter than both
+        let expr = Expression::parse("a || b && c == 1").unwrap();
+        match expr {
+            Expression::Binary {
+                op: BinaryOp::Or,
+                right,
+                ..
+            } => match *right {
+                Expression::Binary {
+                    op: BinaryOp::And,
+                    right,
+                    ..
+                } => assert!(matches!(
+                    *right,
+                    Expression::Binary {
+                        op: BinaryOp::Eq,
+                        ..
+                    }
+                )),
+                other => panic!("unexpected {:?}", other),
+            },
+            other => panic!("unexpected {:?}", other),
+        }
+    }
+
+    #[test]
+    fn test_string_literals() {
+        let data = json!({"label": "a > b && c", "quote": "it's \"here\""});
+
+        assert!(holds(&data, "label == 'a > b && c'"));
+        assert!(holds(&data, r#"quote == 'it\'s "here"'"#));
+        assert!(holds(&data, r#"quote == "it's \"here\"""#));
+        assert!(holds(&data, r#"'\u0041\n' == "A\n""#));
+    }
+
+    #[test]
+    fn test_paths_with_indices() {
+        let data = json!({
+            "items": [{"name": "first"}, {"name": "second", "tags": ["new"]}],
+            "user": {"roles": ["admin"]}
+        });
+
+        assert!(holds(&data, "items[1].name == 'second'"));
+        assert!(holds(&data, "items.0.name == 'first'"));
+        assert!(holds(&data, "user.roles[0] == 'admin'"));
+        assert!(holds(&data, "items[1]['tags'][0] == 'new'"));
+        assert!(!holds(&data, "items[5].name"));
+        assert_eq!(
+            Expression::parse("@user-42.roles[0]").unwrap(),
+            Expression::IdRef {
+                id: "user-42".to_string(),
+                path: vec![
+                    PathSegment::Property("roles".to_string()),
+                    PathSegment::Index(0)
+                ],
+            }
+        );
+    }
+
+    #[test]
+    fn test_typed_comparisons() {
+        let data = json!({
+            "price": 12.5,
+            "label": "9",
+            "active": true,
+            "missing_value": null,
+            "tags": ["new", "sale"],
+            "owner": {"name": "Ann"}
+        });
+
+        // Numbers compare numerically, even against numeric strings
+        assert!(holds(&data, "price > 10"));
+        assert!(holds(&data, "label < 10"));
+        assert!(holds(&data, "label == 9"));
+        assert!(!holds(&data, "'abc' < 10"));
+
+        // Booleans, null and missing properties are distinct
+        assert!(holds(&data, "active == true"));
+        assert!(holds(&data, "active == 'true'"));
+        assert!(!holds(&data, "active == 1"));
+        assert!(holds(&data, "missing_value == null"));
+        assert!(!holds(&data, "nothing == null"));
+        assert!(!holds(&data, "nothing == other"));
+        assert!(holds(&data, "nothing != null"));
+
+        // Arrays and objects compare structurally
+        assert!(holds(&data, "tags == ['new', 'sale']"));
+        assert!(!holds(&data, "tags == ['sale', 'new']"));
+        assert!(!holds(&data, "owner > 1"));
+    }
+
+    #[test]
+    fn test_length_and_membership() {
+        let data = json!({
+            "items": [1, 2, 3],
+            "empty": [],
+            "name": "héllo",
+            "status": "review",
+            "roles": ["admin", "editor"],
+            "sized": {"length": 7}
+        });
+
+        assert!(holds(&data, "items.length > 0"));
+        assert!(holds(&data, "items.length == 3"));
+        assert!(!holds(&data, "empty.length"));
+        assert!(!holds(&data, "empty"));
+        assert!(holds(&data, "name.length == 5"));
+        assert!(holds(&data, "sized.length == 7"));
+
+        assert!(holds(&data, "status in ['draft', 'review']"));
+        assert!(!holds(&data, "status in ['draft', 'published']"));
+        assert!(holds(&data, "'admin' in roles"));
+        assert!(holds(&data, "'ell' in 'hello'"));
+        assert!(holds(&data, "'length' in sized"));
+        assert!(!holds(&data, "2 in []"));
+        assert!(holds(&data, "2 in items && !(5 in items)"));
+    }
+
+    #[test]
+    fn test_iso8601_comparisons() {
+        let data = json!({
+            "published": "2024-05-01",
+            "updated": "2024-05-01T09:30:00+02:00"
+        });
+
+        assert!(holds(&data, "published > '2024-04-30'"));
+        assert!(holds(&data, "published <= '2024-05-01T00:00:00Z'"));
+        assert!(holds(&data, "updated < '2024-05-01T08:00:00Z'"));
+        assert!(holds(&data, "updated > published"));
+        assert!(holds(&data, "'2024-12-31' < '2025-01-01'"));
+    }
+
+    #[test]
+    fn test_builtin_functions() {
+        let data = json!({
+            "tags": ["sale", "new"],
+            "items": [1, 2, 3, 4],
+            "url": "https://example.com/page",
+            "sku": "AB-1234",
+            "blank": "",
+            "endsAt": "2999-01-01T00:00:00Z"
+        });
+
+        assert!(holds(&data, "contains(tags, 'sale')"));
+        assert!(!holds(&data, "contains(tags, 'old')"));
+        assert!(holds(&data, "len(items) > 3"));
+        assert!(holds(&data, "len(url) == 24"));
+        assert!(holds(&data, "len(missing) == 0"));
+        assert!(holds(&data, "startsWith(url, 'https')"));
+        assert!(holds(&data, "endsWith(url, '/page')"));
+        assert!(holds(&data, r"matches(sku, '^[A-Z]{2}-\\d+$')"));
+        assert!(holds(
+            &data,
+            "empty(blank) && empty(missing) && !empty(tags)"
+        ));
+        assert!(holds(&data, "now() < endsAt"));
+        assert!(holds(&data, "now() > '2020-01-01'"));
+    }
+
+    #[test]
+    fn test_custom_functions() {
+        let mut functions = ConstraintFunctionRegistry::new();
+        functions.register("double", |args| {
+            Ok(json!(
+                args.first().and_then(JsonValue::as_f64).unwrap_or(0.0) * 2.0
+            ))
+        });
+        let data = json!({"count": 4});
+        let context = ConstraintContext::new(&data).with_functions(&functions);
+
+        assert!(context.evaluate_expression("double(count) == 8").unwrap());
+        assert!(context
+            .evaluate_expression("double(double(1)) == count")
+            .unwrap());
+
+        // A registry without the built-ins doesn't know them
+        let error = context.evaluate_expression("len(count)").unwrap_err();
+        assert!(error
+            .to_string()
+            .contains("Unknown constraint function 'len'"));
+    }
+
+    #[test]
+    fn test_function_errors() {
+        let data = json!({"n": 5});
+        let context = ConstraintContext::new(&data);
+        let error = |expr: &str| context.evaluate_expression(expr).unwrap_err().to_string();
+
+        assert!(error("len()").contains("len() expects 1 argument, got 0"));
+        assert!(error("contains(n)").contains("contains() expects 2 arguments, got 1"));
+        assert!(error("len(n)").contains("expects an array, string or object"));
+        assert!(error("matches('a', '(')").contains("invalid pattern"));
+        assert!(Expression::parse("len(a,)")
+            .unwrap_err()
+            .to_string()
+            .contains("expected a value, found ')'"));
+        assert_eq!(
+            Expression::parse("now()").unwrap(),
+            Expression::Call {
+                name: "now".to_string(),
+                args: vec![]
+            }
+        );
+    }
+
+    #[test]
+    fn test_format_iso8601() {
+        let time = std::time::UNIX_EPOCH + std::time::Duration::from_secs(1_714_555_800);
+        assert_eq!(format_iso8601(time), "2024-05-01T09:30:00Z");
+        assert_eq!(
+            format_iso8601(std::time::UNIX_EPOCH),
+            "1970-01-01T00:00:00Z"
+        );
+    }
+
+    #[test]
+    fn test_parse_errors_report_column() {
+        let error = |expr: &str| Expression::parse(expr).unwrap_err().to_string();
+
+        assert!(
+            error("a && (b || c").contains("expected ')', found end of expression at column 13")
+        );
+        assert!(error("a && && b").contains("expected a value, found '&&' at column 6"));
+        assert!(error("name == 'open").contains("unterminated string at column 9"));
+        assert!(error("a ? b").contains("unexpected character '?' at column 3"));
+        assert!(error("a b").contains("unexpected 'b' at column 3"));
+        assert!(error("items[x]").contains("expected an array index, found 'x' at column 7"));
+        assert!(error(r#"'\q'"#).contains("unknown escape '\\q' at column 2"));
+    }
+
+    #[test]
+    fn test_default_constraint_evaluator() {
+        let data = json!({"count": 3, "owner": {"@id": "u1", "name": "Ada"}});
+        let ids = ItemIds::collect(&data);
+        let context = ConstraintContext::new(&data).with_ids(&ids);
+        let evaluator = DefaultConstraintEvaluator::new();
+
+        assert!(evaluator.evaluate("count > 2", &context).unwrap());
+        assert!(!evaluator.evaluate("count > 5", &context).unwrap());
+        assert!(evaluator.validate("count > 2 && owner").is_ok());
+        assert!(evaluator.validate("count >").is_err());
+
+        // Custom evaluators reach the data and items through the context
+        assert_eq!(
+            context
+                .data()
+                .get_property(&["count".to_string()])
+                .as_deref(),
+            Some("3")
+        );
+        let owner = context.item("u1").unwrap();
+        assert_eq!(
+            owner.get_property(&["name".to_string()]).as_deref(),
+            Some("Ada")
+        );
+        assert!(context.item("u2").is_none());
+    }
+
+    #[test]
+    fn test_expression_display_round_trips() {
+        for source in [
+            "a && (b || !c)",
+            "!(count > 5) || tags.length == 0",
+            "items[0].name == 'it\\'s'",
+            "status in ['draft', 'review'] && @user-42.role != null",
+            "contains(tags, 'sale') && len(items) >= 2.5",
+            "(a == b) == true",
+        ] {
+            let expr = Expression::parse(source).unwrap();
+            let written = expr.to_string();
+            assert_eq!(Expression::parse(&written).unwrap(), expr, "{}", written);
+        }
+        assert_eq!(
+            Expression::parse("a&&(b||c)").unwrap().to_string(),
+            "a && (b || c)"
+        );
+    }
+
+    #[test]
+    fn test_constraint_checker() {
+        let bindings: BTreeSet<String> = ["hasDescription", "title", "items"]
+            .into_iter()
+            .map(String::from)
+            .collect();
+        let checker = ConstraintChecker::new(&bindings, None);
+        let check = |source: &str| {
+            let expr = Expression::parse(source).unwrap();
+            checker
+                .check(source, &expr)
+                .into_iter()
+                .map(|warning| warning.kind)
+                .collect::<Vec<_>>()
+        };
+
+        assert!(check("hasDescription && items.length > 0").is_empty());
+        assert_eq!(
+            check("hasDescripton"),
+            [ConstraintWarningKind::UnboundPath {
+                path: "hasDescripton".to_string(),
+                suggestion: Some("hasDescription".to_string()),
+            }]
+        );
+        assert_eq!(
+            check("zzz"),
+            [ConstraintWarningKind::UnboundPath {
+                path: "zzz".to_string(),
+                suggestion: None,
+            }]
+        );
+        assert_eq!(
+            check("title > true"),
+            [ConstraintWarningKind::IncompatibleComparison {
+                comparison: "title > true".to_string(),
+            }]
+        );
+        assert_eq!(
+            check("'abc' < 5"),
+            [ConstraintWarningKind::IncompatibleComparison {
+                comparison: "'abc' < 5".to_string(),
+            }]
+        );
+        assert_eq!(
+            check("title || 1 == 1"),
+            [ConstraintWarningKind::Constant {
+                condition: "title || 1 == 1".to_string(),
+                value: true,
+            }]
+        );
+        assert_eq!(
+            check("title && !(2 > 1)"),
+            [ConstraintWarningKind::Constant {
+                condition: "title && !(2 > 1)".to_string(),
+                value: false,
+            }]
+        );
+        assert_eq!(
+            check("isEven(items.length)"),
+            [ConstraintWarningKind::UnknownFunction {
+                name: "isEven".to_string(),
+            }]
+        );
+        // Literals compared with paths are values, not conditions
+        assert!(check("title == 'x' || items in ['a', 'b']").is_empty());
+
+        let sample = json!({
+            "owner": {"@id": "u1"},
+            "items": [{"sku": "A-1"}]
+        });
+        let checker = ConstraintChecker::new(&bindings, None).with_sample(&sample);
+        let check = |source: &str| {
+            let expr = Expression::parse(source).unwrap();
+            checker
+                .check(source, &expr)
+                .into_iter()
+                .map(|warning| warning.kind)
+                .collect::<Vec<_>>()
+        };
+
+        // Paths may resolve inside array items, as constraints there see them
+        assert!(check("sku && items.length > 0 && @u1").is_empty());
+        assert_eq!(
+            check("title || @u2"),
+            [
+                ConstraintWarningKind::MissingPath {
+                    path: "title".to_string(),
+                },
+                ConstraintWarningKind::UnknownId {
+                    id: "u2".to_string(),
+                },
+            ]
+        );
+    }
 }
@@ -3,16 +3,25 @@
 //! This module provides functionality for working with external documents,
 //! including fetching, caching, and integrating external content with templates.
 
-use std::collections::HashMap;
-use std::time::Duration;
+use std::cell::RefCell;
+use std::collections::{HashMap, HashSet};
+use std::net::{IpAddr, Ipv4Addr, SocketAddr, ToSocketAddrs};
+use std::path::{Component, Path, PathBuf};
+use std::sync::Arc;
+use std::time::{Duration, Instant};
 
 use dom_query::Document;
+use once_cell::sync::OnceCell;
+use reqwest::header::{HeaderMap, HeaderName, HeaderValue};
 use serde_json::Value as JsonValue;
 
-use crate::cache::CachedDocument;
+#[cfg(feature = "async")]
+use crate::cache::CacheLookup;
+use crate::cache::{CachedDocument, DocumentCacheKey, TemplateCache};
 use crate::error::{Error, Result};
 use crate::microdata;
 use crate::types::*;
+use crate::url_resolver;
 
 /// Configuration for cross-document operations
 #[derive(Debug, Clone)]
@@ -31,6 +40,10 @@ pub struct CrossDocumentConfig {
     pub verify_ssl: bool,
     /// User agent string
     pub user_agent: String,
+    /// How failed HTTP fetches are retried
+    pub retry_policy: RetryPolicy,
+    /// Which hosts, addresses and responses HTTP fetches may use
+    pub fetch_policy: FetchPolicy,
 }
 
 impl Default for CrossDocumentConfig {
@@ -43,27 +56,1111 @@ impl Default for CrossDocumentConfig {
             default_headers: HashMap::new(),
             verify_ssl: true,
             user_agent: "html-template-rs/0.1.0".to_string(),
+            retry_policy: RetryPolicy::none(),
+            fetch_policy: FetchPolicy::default(),
         }
     }
 }
 
+/// Kinds of transport failure that a [`RetryPolicy`] can retry
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum NetworkErrorKind {
+    /// The request did not complete within the fetch timeout
+    Timeout,
+    /// A connection to the server could not be established
+    Connect,
+    /// The response body could not be read
+    Body,
+    /// Any other transport failure
+    Other,
+}
+
+impl NetworkErrorKind {
+    fn of(err: &reqwest::Error) -> Self {
+        if err.is_timeout() {
+            Self::Timeout
+        } else if err.is_connect() {
+            Self::Connect
+        } else if err.is_body() || err.is_decode() {
+            Self::Body
+        } else {
+            Self::Other
+        }
+    }
+}
+
+/// Retry behaviour for failed HTTP fetches
+///
+/// After failed attempt `n` the fetch waits
+/// `initial_backoff * backoff_multiplier^(n - 1)`, capped at `max_backoff`,
+/// with up to `jitter` of that delay (a fraction between 0 and 1) randomly
+/// taken off so that concurrent clients spread out their retries.
+#[derive(Debug, Clone, PartialEq)]
+pub struct RetryPolicy {
+    /// Maximum number of attempts, including the first
+    pub max_attempts: usize,
+    /// Delay before the first retry
+    pub initial_backoff: Duration,
+    /// Factor the delay grows by after each retry
+    pub backoff_multiplier: f64,
+    /// Upper bound on the delay between attempts
+    pub max_backoff: Duration,
+    /// Fraction of each delay that is randomised
+    pub jitter: f64,
+    /// Response statuses that are retried
+    pub retryable_status_codes: Vec<u16>,
+    /// Transport failures that are retried
+    pub retryable_errors: Vec<NetworkErrorKind>,
+    /// Time limit for all attempts together, including the delays between them
+    pub deadline: Option<Duration>,
+}
+
+impl Default for RetryPolicy {
+    fn default() -> Self {
+        Self {
+            max_attempts: 3,
+            initial_backoff: Duration::from_millis(200),
+            backoff_multiplier: 2.0,
+            max_backoff: Duration::from_secs(5),
+            jitter: 0.5,
+            retryable_status_codes: vec![408, 429, 500, 502, 503, 504],
+            retryable_errors: vec![NetworkErrorKind::Timeout, NetworkErrorKind::Connect],
+            deadline: None,
+        }
+    }
+}
+
+impl RetryPolicy {
+    /// Create a policy that makes a single attempt
+    pub fn none() -> Self {
+        Self {
+            max_attempts: 1,
+            ..Self::default()
+        }
+    }
+
+    /// Get the delay after failed attempt `attempt` (1-based), before jitter
+    pub fn backoff(&self, attempt: usize) -> Duration {
+        let exponent = attempt.saturating_sub(1).min(i32::MAX as usize) as i32;
+        let delay = self.initial_backoff.as_secs_f64() * self.backoff_multiplier.powi(exponent);
+        if delay.is_finite() && delay < self.max_backoff.as_secs_f64() {
+            Duration::from_secs_f64(delay.max(0.0))
+        } else {
+            self.max_backoff
+        }
+    }
+
+    /// Get the jittered delay after failed attempt `attempt` (1-based)
+    pub fn jittered_backoff(&self, attempt: usize) -> Duration {
+        let delay = self.backoff(attempt);
+        let jitter = self.jitter.clamp(0.0, 1.0);
+        delay.mul_f64(1.0 - jitter * random_fraction())
+    }
+
+    /// Check if a status code is retried
+    pub fn retries_status(&self, status: u16) -> bool {
+        self.retryable_status_codes.contains(&status)
+    }
+
+    /// Check if a transport failure is retried
+    pub fn retries_error(&self, kind: NetworkErrorKind) -> bool {
+        self.retryable_errors.contains(&kind)
+    }
+
+    /// Decide whether to retry after failed attempt `attempt`, returning the delay
+    fn retry_delay(
+        &self,
+        attempt: usize,
+        failure: &Failure,
+        elapsed: Duration,
+    ) -> Option<Duration> {
+        let retryable = match failure {
+            Failure::Status(status) => self.retries_status(*status),
+            Failure::Network(kind) => self.retries_error(*kind),
+            Failure::Fatal => false,
+        };
+        if !retryable || attempt >= self.max_attempts {
+            return None;
+        }
+
+        let delay = self.jittered_backoff(attempt);
+        match self.deadline {
+            Some(deadline) if elapsed + delay >= deadline => None,
+            _ => Some(delay),
+        }
+    }
+
+    /// Get the time left before the deadline, if there is one
+    fn remaining(&self, elapsed: Duration) -> Option<Duration> {
+        self.deadline
+            .map(|deadline| deadline.saturating_sub(elapsed))
+    }
+}
+
+/// Get a random number in `[0, 1)` for backoff jitter
+fn random_fraction() -> f64 {
+    use std::hash::{BuildHasher, Hasher};
+
+    // Each RandomState is randomly keyed, which is plenty for jitter
+    let mut hasher = std::collections::hash_map::RandomState::new().build_hasher();
+    let now = std::time::SystemTime::now()
+        .duration_since(std::time::UNIX_EPOCH)
+        .map_or(0, |elapsed| elapsed.as_nanos());
+    hasher.write_u128(now);
+    (hasher.finish() >> 11) as f64 / (1u64 << 53) as f64
+}
+
+/// Record of a single attempt to fetch a document
+#[derive(Debug, Clone, PartialEq)]
+pub struct FetchAttempt {
+    /// Attempt number, starting at 1
+    pub number: usize,
+    /// How long the attempt took
+    pub duration: Duration,
+    /// Response status, if a response was received
+    pub status: Option<u16>,
+    /// Why the attempt failed, if it did
+    pub error: Option<String>,
+    /// Delay before the next attempt, if one was made
+    pub backoff: Option<Duration>,
+}
+
+impl FetchAttempt {
+    /// Check if the attempt produced a document
+    pub fn succeeded(&self) -> bool {
+        self.error.is_none()
+    }
+
+    /// Record an attempt that was made with a single call to a source
+    fn single<T>(duration: Duration, result: &Result<T>) -> Self {
+        Self {
+            number: 1,
+            duration,
+            status: None,
+            error: result.as_ref().err().map(|e| e.to_string()),
+            backoff: None,
+        }
+    }
+}
+
+/// Why an HTTP attempt failed, for deciding whether to retry
+#[derive(Debug, Clone, Copy)]
+enum Failure {
+    /// The server responded with an error status
+    Status(u16),
+    /// The request failed in transport
+    Network(NetworkErrorKind),
+    /// The request cannot succeed on retry
+    Fatal,
+}
+
+/// A failed HTTP attempt, classified for retrying
+type AttemptFailure = (Failure, Error);
+
+/// Outcome of one HTTP attempt: the status and document (`None` for `304`)
+type AttemptResult = std::result::Result<(u16, Option<CachedDocument>), AttemptFailure>;
+
+/// Append a record of one HTTP attempt to `attempts`
+fn record_attempt(
+    attempts: &mut Vec<FetchAttempt>,
+    number: usize,
+    started: Instant,
+    result: &AttemptResult,
+) {
+    let (status, error) = match result {
+        Ok((status, _)) => (Some(*status), None),
+        Err((Failure::Status(status), e)) => (Some(*status), Some(e.to_string())),
+        Err((_, e)) => (None, Some(e.to_string())),
+    };
+    attempts.push(FetchAttempt {
+        number,
+        duration: started.elapsed(),
+        status,
+        error,
+        backoff: None,
+    });
+}
+
+/// Safeguards applied to every HTTP fetch
+///
+/// Guards against server-side request forgery when URLs come from untrusted
+/// content. By default, hosts that resolve to private, loopback or link-local
+/// addresses are refused and response bodies are limited to 10 MiB. Redirect
+/// targets are checked like the original URL, and violations are reported as
+/// [`Error::PolicyError`]. Host names are checked as they are resolved for the
+/// connection, so a request only ever connects to addresses that passed.
+///
+/// ```rust,ignore
+/// let config = CrossDocumentConfig {
+///     fetch_policy: FetchPolicy {
+///         allowed_hosts: vec!["*.example.com".to_string()],
+///         allowed_content_types: vec!["text/html".to_string()],
+///         ..Default::default()
+///     },
+///     ..Default::default()
+/// };
+/// let fetcher = DocumentFetcher::with_config(config);
+/// ```
+#[derive(Debug, Clone, PartialEq)]
+pub struct FetchPolicy {
+    /// Hosts that may be fetched, e.g. `example.com` or `*.example.com` for
+    /// its subdomains; when empty, any host that is not denied may be
+    pub allowed_hosts: Vec<String>,
+    /// Hosts that may never be fetched, in the same format as `allowed_hosts`
+    pub denied_hosts: Vec<String>,
+    /// Whether private network addresses (`10.0.0.0/8`, `172.16.0.0/12`,
+    /// `192.168.0.0/16`, `100.64.0.0/10`, `fc00::/7`) may be fetched
+    pub allow_private: bool,
+    /// Whether loopback and unspecified addresses (`127.0.0.0/8`, `::1`,
+    /// `0.0.0.0/8`, `::`) may be fetched
+    pub allow_loopback: bool,
+    /// Whether link-local addresses (`169.254.0.0/16`, `fe80::/10`) may be fetched
+    pub allow_link_local: bool,
+    /// Largest response body accepted, in bytes
+    pub max_body_size: Option<usize>,
+    /// Accepted media types such as `text/html` or `text/*`; when empty, any
+    /// content type is accepted
+    pub allowed_content_types: Vec<String>,
+}
+
+impl Default for FetchPolicy {
+    fn default() -> Self {
+        Self {
+            allowed_hosts: Vec::new(),
+            denied_hosts: Vec::new(),
+            allow_private: false,
+            allow_loopback: false,
+            allow_link_local: false,
+            max_body_size: Some(10 * 1024 * 1024),
+            allowed_content_types: Vec::new(),
+        }
+    }
+}
+
+impl FetchPolicy {
+    /// Create a policy that allows every host, address, body size and content type
+    pub fn permissive() -> Self {
+        Self {
+            allow_private: true,
+            allow_loopback: true,
+            allow_link_local: true,
+            max_body_size: None,
+            ..Self::default()
+        }
+    }
+
+    /// Check if a host name or IP address passes the allow and deny lists
+    pub fn allows_host(&self, host: &str) -> bool {
+        let host = host.trim_end_matches('.').to_ascii_lowercase();
+        let matches = |pattern: &String| host_matches(pattern, &host);
+
+        !self.denied_hosts.iter().any(matches)
+            && (self.allowed_hosts.is_empty() || self.allowed_hosts.iter().any(matches))
+    }
+
+    /// Check if an address may be connected to
+    ///
+    /// IPv4 addresses embedded in IPv6 (`::ffff:0:0/96`, the NAT64 prefix
+    /// `64:ff9b::/96` and 6to4 `2002::/16`) are checked as the IPv4 address.
+    pub fn allows_address(&self, ip: IpAddr) -> bool {
+        match address_range(ip) {
+            Some(AddressRange::Loopback) => self.allow_loopback,
+            Some(AddressRange::Private) => self.allow_private,
+            Some(AddressRange::LinkLocal) => self.allow_link_local,
+            None => true,
+        }
+    }
+
+    /// Check if a response `Content-Type` is accepted
+    pub fn allows_content_type(&self, content_type: Option<&str>) -> bool {
+        if self.allowed_content_types.is_empty() {
+            return true;
+        }
+
+        let essence = match content_type {
+            Some(content_type) => content_type
+                .split(';')
+                .next()
+                .unwrap_or_default()
+                .trim()
+                .to_ascii_lowercase(),
+            None => return false,
+        };
+        self.allowed_content_types.iter().any(|allowed| {
+            let allowed = allowed.trim().to_ascii_lowercase();
+            match allowed.strip_suffix('*') {
+                Some(prefix) => essence.starts_with(prefix),
+                None => essence == allowed,
+            }
+        })
+    }
+
+    /// Check a URL against the host lists and the addresses its host resolves to
+    ///
+    /// This performs a blocking DNS lookup. Fetches do not rely on it: they
+    /// check each address as the connection resolves the host.
+    pub fn check_url(&self, url: &str) -> Result<()> {
+        let host = self.check_host(url)?;
+        if let Some((host, port)) = host {
+            // A host that does not resolve fails when the request is sent
+            if let Ok(addrs) = (host.as_str(), port).to_socket_addrs() {
+                if let Some(reason) = self.address_refusal(&host, addrs.map(|addr| addr.ip())) {
+                    return Err(refused(url, reason));
+                }
+            }
+        }
+        Ok(())
+    }
+
+    /// Check a URL against the host lists and, for IP literals, the address
+    /// ranges, returning the host name and port still to be resolved
+    fn check_host(&self, url: &str) -> Result<Option<HostLookup>> {
+        let parsed = url::Url::parse(url)
+            .map_err(|e| Error::policy_owned(format!("Invalid URL '{}': {}", url, e)))?;
+        match self.host_refusal(&parsed) {
+            Some(reason) => Err(refused(url, reason)),
+            None => match parsed.host() {
+                Some(url::Host::Domain(domain)) => Ok(Some((
+                    domain.to_string(),
+                    parsed.port_or_known_default().unwrap_or(80),
+                ))),
+                _ => Ok(None),
+            },
+        }
+    }
+
+    /// Get the reason a URL's host is refused without resolving it
+    fn host_refusal(&self, url: &url::Url) -> Option<String> {
+        let (host, ip) = match url.host() {
+            Some(url::Host::Domain(domain)) => (domain.to_string(), None),
+            Some(url::Host::Ipv4(ip)) => (ip.to_string(), Some(IpAddr::V4(ip))),
+            Some(url::Host::Ipv6(ip)) => (ip.to_string(), Some(IpAddr::V6(ip))),
+            None => return Some("URL has no host".to_string()),
+        };
+
+        if !self.allows_host(&host) {
+            return Some(format!("host '{}' is not allowed", host));
+        }
+        ip.and_then(|ip| self.address_refusal(&host, [ip]))
+    }
+
+    /// Get the reason a host is refused, if any address it resolves to is blocked
+    fn address_refusal(
+        &self,
+        host: &str,
+        addrs: impl IntoIterator<Item = IpAddr>,
+    ) -> Option<String> {
+        addrs
+            .into_iter()
+            .find(|ip| !self.allows_address(*ip))
+            .map(|ip| {
+                let range = address_range(ip).map_or("blocked", AddressRange::name);
+                format!("host '{}' resolves to {} address {}", host, range, ip)
+            })
+    }
+
+    /// Check a response's declared content type and length before reading it
+    fn check_response(&self, url: &str, headers: &HeaderMap) -> Result<()> {
+        let content_type = header_string(headers, reqwest::header::CONTENT_TYPE);
+        if !self.allows_content_type(content_type.as_deref()) {
+            return Err(Error::policy_owned(format!(
+                "GET {} refused: content type '{}' is not allowed",
+                url,
+                content_type.as_deref().unwrap_or("none")
+            )));
+        }
+
+        let length = header_string(headers, reqwest::header::CONTENT_LENGTH)
+            .and_then(|length| length.parse::<usize>().ok());
+        match length {
+            Some(length) => self.check_body_size(url, length),
+            None => Ok(()),
+        }
+    }
+
+    /// Check the size of a response body
+    fn check_body_size(&self, url: &str, size: usize) -> Result<()> {
+        match self.max_body_size {
+            Some(max) if size > max => Err(Error::policy_owned(format!(
+                "GET {} refused: response body exceeds {} bytes",
+                url, max
+            ))),
+            _ => Ok(()),
+        }
+    }
+}
+
+/// A host name and port still to be resolved
+type HostLookup = (String, u16);
+
+/// Build the error for a URL refused by the fetch policy
+fn refused(url: &str, reason: impl std::fmt::Display) -> Error {
+    Error::policy_owned(format!("GET {} refused: {}", url, reason))
+}
+
+/// Address ranges a [`FetchPolicy`] blocks by default
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum AddressRange {
+    Loopback,
+    Private,
+    LinkLocal,
+}
+
+impl AddressRange {
+    fn name(self) -> &'static str {
+        match self {
+            Self::Loopback => "loopback",
+            Self::Private => "private",
+            Self::LinkLocal => "link-local",
+        }
+    }
+}
+
+/// Classify an address into one of the ranges blocked by default
+fn address_range(ip: IpAddr) -> Option<AddressRange> {
+    match ip {
+        IpAddr::V4(ip) => {
+            let [a, b, ..] = ip.octets();
+            if ip.is_loopback() || a == 0 {
+                Some(AddressRange::Loopback)
+            } else if ip.is_private() || (a == 100 && (64..128).contains(&b)) {
+                Some(AddressRange::Private)
+            } else if ip.is_link_local() {
+                Some(AddressRange::LinkLocal)
+            } else {
+                None
+            }
+        }
+        IpAddr::V6(ip) => {
+            if let Some(embedded) = embedded_ipv4(ip) {
+                return address_range(IpAddr::V4(embedded));
+            }
+            let first = ip.segments()[0];
+            if ip.is_loopback() || ip.is_unspecified() {
+                Some(AddressRange::Loopback)
+            } else if first & 0xfe00 == 0xfc00 {
+                Some(AddressRange::Private)
+            } else if first & 0xffc0 == 0xfe80 {
+                Some(AddressRange::LinkLocal)
+            } else {
+                None
+            }
+        }
+    }
+}
+
+/// Get the IPv4 address carried by an IPv4-mapped, NAT64 or 6to4 address
+fn embedded_ipv4(ip: std::net::Ipv6Addr) -> Option<Ipv4Addr> {
+    let segments = ip.segments();
+    let [.., a, b, c, d] = ip.octets();
+    if let Some(mapped) = ip.to_ipv4_mapped() {
+        Some(mapped)
+    } else if segments[..6] == [0x64, 0xff9b, 0, 0, 0, 0] {
+        Some(Ipv4Addr::new(a, b, c, d))
+    } else if segments[0] == 0x2002 {
+        let [.., a, b] = segments[1].to_be_bytes();
+        let [.., c, d] = segments[2].to_be_bytes();
+        Some(Ipv4Addr::new(a, b, c, d))
+    } else {
+        None
+    }
+}
+
+/// Match a lowercase host against a pattern such as `example.com` or `*.example.com`
+fn host_matches(pattern: &str, host: &str) -> bool {
+    let pattern = pattern.trim().trim_end_matches('.').to_ascii_lowercase();
+    match pattern.strip_prefix("*.") {
+        Some(domain) => host
+            .strip_suffix(domain)
+            .is_some_and(|prefix| prefix.ends_with('.')),
+        None => host == pattern,
+    }
+}
+
+/// A fetch policy violation raised from inside the HTTP client, holding the
+/// reason the request was refused
+#[derive(Debug)]
+struct PolicyViolation(String);
+
+impl std::fmt::Display for PolicyViolation {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str(&self.0)
+    }
+}
+
+impl std::error::Error for PolicyViolation {}
+
+/// A DNS resolver that refuses hosts resolving to addresses the fetch policy blocks
+///
+/// The HTTP clients connect through it, so the addresses that are checked are
+/// the ones connected to, for the original URL and every redirect target.
+#[derive(Debug)]
+struct PolicyResolver {
+    policy: FetchPolicy,
+}
+
+impl reqwest::dns::Resolve for PolicyResolver {
+    fn resolve(&self, name: hyper::client::connect::dns::Name) -> reqwest::dns::Resolving {
+        let policy = self.policy.clone();
+        Box::pin(async move {
+            let host = name.as_str();
+            let addrs: Vec<SocketAddr> = tokio::net::lookup_host((host, 0)).await?.collect();
+            if let Some(reason) = policy.address_refusal(host, addrs.iter().map(|addr| addr.ip())) {
+                return Err(PolicyViolation(reason).into());
+            }
+            let addrs: reqwest::dns::Addrs = Box::new(addrs.into_iter());
+            Ok(addrs)
+        })
+    }
+}
+
+/// A loader for documents addressed by URL
+///
+/// Implement this to plug custom storage into cross-document rendering, then
+/// register it for a URL scheme with [`SchemeRouter`] or
+/// [`DocumentFetcher::register_source`].
+///
+/// ```rust,ignore
+/// struct CmsSource;
+///
+/// impl DocumentSource for CmsSource {
+///     fn load(&self, url: &str, _headers: &[(String, String)]) -> Result<CachedDocument> {
+///         let slug = url.trim_start_matches("cms://");
+///         Ok(CachedDocument::html(load_page_from_cms(slug)?))
+///     }
+/// }
+///
+/// let mut fetcher = DocumentFetcher::new();
+/// fetcher.register_source("cms", CmsSource);
+/// let renderer = CrossDocumentRenderer::with_fetcher(fetcher);
+/// ```
+pub trait DocumentSource: Send + Sync {
+    /// Load the document at `url`, sending `headers` where the transport supports them
+    fn load(&self, url: &str, headers: &[(String, String)]) -> Result<CachedDocument>;
+
+    /// Check whether a previously loaded document is still current
+    ///
+    /// Returns `None` if `cached` is unchanged, or the new document. The default
+    /// implementation reloads the document unconditionally.
+    fn revalidate(
+        &self,
+        url: &str,
+        headers: &[(String, String)],
+        cached: &CachedDocument,
+    ) -> Result<Option<CachedDocument>> {
+        let _ = cached;
+        self.load(url, headers).map(Some)
+    }
+
+    /// Load a document, appending a record of each attempt made to `attempts`
+    ///
+    /// Sources that retry override this to report every attempt; by default
+    /// the single call to [`load`](Self::load) is recorded.
+    fn load_recorded(
+        &self,
+        url: &str,
+        headers: &[(String, String)],
+        attempts: &mut Vec<FetchAttempt>,
+    ) -> Result<CachedDocument> {
+        let started = Instant::now();
+        let result = self.load(url, headers);
+        attempts.push(FetchAttempt::single(started.elapsed(), &result));
+        result
+    }
+
+    /// Revalidate a document, appending a record of each attempt made to `attempts`
+    fn revalidate_recorded(
+        &self,
+        url: &str,
+        headers: &[(String, String)],
+        cached: &CachedDocument,
+        attempts: &mut Vec<FetchAttempt>,
+    ) -> Result<Option<CachedDocument>> {
+        let started = Instant::now();
+        let result = self.revalidate(url, headers, cached);
+        attempts.push(FetchAttempt::single(started.elapsed(), &result));
+        result
+    }
+}
+
+impl<S: DocumentSource + ?Sized> DocumentSource for Arc<S> {
+    fn load(&self, url: &str, headers: &[(String, String)]) -> Result<CachedDocument> {
+        (**self).load(url, headers)
+    }
+
+    fn revalidate(
+        &self,
+        url: &str,
+        headers: &[(String, String)],
+        cached: &CachedDocument,
+    ) -> Result<Option<CachedDocument>> {
+        (**self).revalidate(url, headers, cached)
+    }
+
+    fn load_recorded(
+        &self,
+        url: &str,
+        headers: &[(String, String)],
+        attempts: &mut Vec<FetchAttempt>,
+    ) -> Result<CachedDocument> {
+        (**self).load_recorded(url, headers, attempts)
+    }
+
+    fn revalidate_recorded(
+        &self,
+        url: &str,
+        headers: &[(String, String)],
+        cached: &CachedDocument,
+        attempts: &mut Vec<FetchAttempt>,
+    ) -> Result<Option<CachedDocument>> {
+        (**self).revalidate_recorded(url, headers, cached, attempts)
+    }
+}
+
+impl<S: DocumentSource + ?Sized> DocumentSource for Box<S> {
+    fn load(&self, url: &str, headers: &[(String, String)]) -> Result<CachedDocument> {
+        (**self).load(url, headers)
+    }
+
+    fn revalidate(
+        &self,
+        url: &str,
+        headers: &[(String, String)],
+        cached: &CachedDocument,
+    ) -> Result<Option<CachedDocument>> {
+        (**self).revalidate(url, headers, cached)
+    }
+
+    fn load_recorded(
+        &self,
+        url: &str,
+        headers: &[(String, String)],
+        attempts: &mut Vec<FetchAttempt>,
+    ) -> Result<CachedDocument> {
+        (**self).load_recorded(url, headers, attempts)
+    }
+
+    fn revalidate_recorded(
+        &self,
+        url: &str,
+        headers: &[(String, String)],
+        cached: &CachedDocument,
+        attempts: &mut Vec<FetchAttempt>,
+    ) -> Result<Option<CachedDocument>> {
+        (**self).revalidate_recorded(url, headers, cached, attempts)
+    }
+}
+
+/// Get the scheme of a URL, e.g. `"https"` for `https://example.com/`
+fn url_scheme(url: &str) -> Option<&str> {
+    url.split_once("://").map(|(scheme, _)| scheme)
+}
+
+/// Document source that fetches `http://` and `https://` URLs
+///
+/// Requests are made with a blocking HTTP client that honours every field of
+/// [`CrossDocumentConfig`].
+pub struct HttpSource {
+    config: CrossDocumentConfig,
+    client: OnceCell<reqwest::blocking::Client>,
+}
+
+impl HttpSource {
+    /// Create a new HTTP source with default configuration
+    pub fn new() -> Self {
+        Self::with_config(CrossDocumentConfig::default())
+    }
+
+    /// Create a new HTTP source with custom configuration
+    pub fn with_config(config: CrossDocumentConfig) -> Self {
+        Self {
+            config,
+            client: OnceCell::new(),
+        }
+    }
+}
+
+impl Default for HttpSource {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl HttpSource {
+    /// Perform a GET request with retries, returning `None` for `304 Not Modified`
+    fn send(
+        &self,
+        url: &str,
+        headers: &[(String, String)],
+        attempts: &mut Vec<FetchAttempt>,
+    ) -> Result<Option<CachedDocument>> {
+        let policy = &self.config.retry_policy;
+        let started = Instant::now();
+
+        for number in 1.. {
+            let timeout = policy
+                .remaining(started.elapsed())
+                .map_or(self.config.fetch_timeout, |remaining| {
+                    remaining.min(self.config.fetch_timeout)
+                });
+            let attempt_started = Instant::now();
+            let result = self.send_once(url, headers, timeout);
+            record_attempt(attempts, number, attempt_started, &result);
+
+            let (failure, error) = match result {
+                Ok((_, document)) => return Ok(document),
+                Err(failure) => failure,
+            };
+            match policy.retry_delay(number, &failure, started.elapsed()) {
+                Some(delay) => {
+                    if let Some(attempt) = attempts.last_mut() {
+                        attempt.backoff = Some(delay);
+                    }
+                    std::thread::sleep(delay);
+                }
+                None => return Err(error),
+            }
+        }
+
+        unreachable!("retry loop only exits by returning")
+    }
+
+    /// Make a single GET request
+    fn send_once(
+        &self,
+        url: &str,
+        headers: &[(String, String)],
+        timeout: Duration,
+    ) -> AttemptResult {
+        let policy = &self.config.fetch_policy;
+        policy.check_host(url).map_err(|e| (Failure::Fatal, e))?;
+
+        // The client is built lazily so that constructing a source inside an
+        // async runtime does not create a blocking client there
+        let client = self
+            .client
+            .get_or_try_init(|| build_http_client(&self.config))
+            .map_err(|e| (Failure::Fatal, e))?;
+
+        let mut request = client.get(url).timeout(timeout);
+        for (name, value) in headers {
+            request = request.header(name.as_str(), value.as_str());
+        }
+
+        let response = request
+            .send()
+            .map_err(|e| request_failure(url, &self.config, timeout, e))?;
+
+        let status = check_status(url, response.status())?;
+        if status == reqwest::StatusCode::NOT_MODIFIED.as_u16() {
+            return Ok((status, None));
+        }
+
+        let response_headers = response.headers().clone();
+        policy
+            .check_response(url, &response_headers)
+            .map_err(|e| (Failure::Fatal, e))?;
+
+        use std::io::Read;
+
+        // Read one byte past the limit to detect oversized bodies
+        let limit = policy.max_body_size.map_or(u64::MAX, |max| max as u64 + 1);
+        let mut body = Vec::new();
+        response.take(limit).read_to_end(&mut body).map_err(|e| {
+            (
+                Failure::Network(NetworkErrorKind::Body),
+                Error::http_owned(format!("GET {} failed: {}", url, e)),
+            )
+        })?;
+        policy
+            .check_body_size(url, body.len())
+            .map_err(|e| (Failure::Fatal, e))?;
+
+        let content = body_text(body);
+        Ok((
+            status,
+            Some(document_from_response(&response_headers, content)),
+        ))
+    }
+}
+
+impl DocumentSource for HttpSource {
+    fn load(&self, url: &str, headers: &[(String, String)]) -> Result<CachedDocument> {
+        self.load_recorded(url, headers, &mut Vec::new())
+    }
+
+    /// Send a conditional request using the cached document's validators
+    fn revalidate(
+        &self,
+        url: &str,
+        headers: &[(String, String)],
+        cached: &CachedDocument,
+    ) -> Result<Option<CachedDocument>> {
+        self.revalidate_recorded(url, headers, cached, &mut Vec::new())
+    }
+
+    fn load_recorded(
+        &self,
+        url: &str,
+        headers: &[(String, String)],
+        attempts: &mut Vec<FetchAttempt>,
+    ) -> Result<CachedDocument> {
+        self.send(url, headers, attempts)?
+            .ok_or_else(|| not_modified_error(url))
+    }
+
+    fn revalidate_recorded(
+        &self,
+        url: &str,
+        headers: &[(String, String)],
+        cached: &CachedDocument,
+        attempts: &mut Vec<FetchAttempt>,
+    ) -> Result<Option<CachedDocument>> {
+        if !cached.has_validators() {
+            // Nothing to validate against
+            return self.load_recorded(url, headers, attempts).map(Some);
+        }
+
+        self.send(url, &conditional_headers(headers, cached), attempts)
+    }
+}
+
+/// Document source that reads files from the local filesystem
+///
+/// The part of the URL after `scheme://` is used as the path, so the same
+/// source serves `file:///srv/page.html` or, with a root directory,
+/// `s3-local://bucket/page.html`.
+#[derive(Debug, Clone, Default)]
+pub struct FileSource {
+    root: Option<PathBuf>,
+}
+
+impl FileSource {
+    /// Create a file source that resolves paths as given
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Create a file source that resolves paths relative to `root`
+    ///
+    /// Paths that would climb out of `root`, such as `file:///../secret` or
+    /// `%2e%2e` segments, are rejected.
+    pub fn with_root(root: impl Into<PathBuf>) -> Self {
+        Self {
+            root: Some(root.into()),
+        }
+    }
+
+    fn resolve(&self, url: &str) -> Result<PathBuf> {
+        let path = url.split_once("://").map_or(url, |(_, path)| path);
+        let Some(root) = &self.root else {
+            return Ok(PathBuf::from(path));
+        };
+
+        // Dots may be percent-encoded, which the filesystem won't decode
+        let dot_segment = path
+            .split(['/', '\\'])
+            .any(|segment| segment.to_ascii_lowercase().replace("%2e", ".") == "..");
+        let relative = Path::new(path.trim_start_matches('/'));
+        let escapes = relative
+            .components()
+            .any(|component| !matches!(component, Component::Normal(_) | Component::CurDir));
+        if dot_segment || escapes {
+            return Err(Error::policy_owned(format!(
+                "Path '{}' is outside the source root",
+                path
+            )));
+        }
+        Ok(root.join(relative))
+    }
+}
+
+impl DocumentSource for FileSource {
+    fn load(&self, url: &str, _headers: &[(String, String)]) -> Result<CachedDocument> {
+        let path = self.resolve(url)?;
+        std::fs::read_to_string(&path)
+            .map(CachedDocument::html)
+            .map_err(|e| Error::io(format!("Failed to read file '{}': {}", path.display(), e)))
+    }
+}
+
+/// Document source that serves documents registered in memory
+///
+/// Useful as a deterministic fixture in tests and for pre-loaded content.
+#[derive(Debug, Clone, Default)]
+pub struct MemorySource {
+    documents: HashMap<String, CachedDocument>,
+}
+
+impl MemorySource {
+    /// Create an empty in-memory source
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Register HTML content for a URL
+    pub fn with_html(mut self, url: impl Into<String>, content: impl Into<String>) -> Self {
+        self.insert(url, CachedDocument::html(content));
+        self
+    }
+
+    /// Register a document for a URL
+    pub fn insert(&mut self, url: impl Into<String>, document: CachedDocument) {
+        self.documents.insert(url.into(), document);
+    }
+
+    /// Get the number of registered documents
+    pub fn len(&self) -> usize {
+        self.documents.len()
+    }
+
+    /// Check if no documents are registered
+    pub fn is_empty(&self) -> bool {
+        self.documents.is_empty()
+    }
+}
+
+impl DocumentSource for MemorySource {
+    fn load(&self, url: &str, _headers: &[(String, String)]) -> Result<CachedDocument> {
+        self.documents
+            .get(url)
+            .cloned()
+            .ok_or_else(|| Error::io(format!("No document registered for '{}'", url)))
+    }
+}
+
+/// Shared handle to a document source
+type SharedSource = Arc<dyn DocumentSource>;
+
+/// Document source that dispatches each URL to the source registered for its scheme
+#[derive(Default)]
+pub struct SchemeRouter {
+    sources: HashMap<String, SharedSource>,
+}
+
+impl SchemeRouter {
+    /// Create a router with no registered schemes
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Register a source for a scheme, replacing any existing one
+    pub fn register<S: DocumentSource + 'static>(&mut self, scheme: &str, source: S) {
+        self.sources
+            .insert(scheme.to_ascii_lowercase(), Arc::new(source));
+    }
+
+    /// Register a source for a scheme (builder style)
+    pub fn with_source<S: DocumentSource + 'static>(mut self, scheme: &str, source: S) -> Self {
+        self.register(scheme, source);
+        self
+    }
+
+    /// Check if a source is registered for a scheme
+    pub fn handles(&self, scheme: &str) -> bool {
+        self.sources.contains_key(&scheme.to_ascii_lowercase())
+    }
+}
+
+impl SchemeRouter {
+    fn source_for(&self, url: &str) -> Result<&SharedSource> {
+        url_scheme(url)
+            .and_then(|scheme| self.sources.get(&scheme.to_ascii_lowercase()))
+            .ok_or_else(|| Error::parse(format!("Unsupported URL scheme: {}", url)))
+    }
+}
+
+impl DocumentSource for SchemeRouter {
+    fn load(&self, url: &str, headers: &[(String, String)]) -> Result<CachedDocument> {
+        self.source_for(url)?.load(url, headers)
+    }
+
+    fn revalidate(
+        &self,
+        url: &str,
+        headers: &[(String, String)],
+        cached: &CachedDocument,
+    ) -> Result<Option<CachedDocument>> {
+        self.source_for(url)?.revalidate(url, headers, cached)
+    }
+
+    fn load_recorded(
+        &self,
+        url: &str,
+        headers: &[(String, String)],
+        attempts: &mut Vec<FetchAttempt>,
+    ) -> Result<CachedDocument> {
+        self.source_for(url)?.load_recorded(url, headers, attempts)
+    }
+
+    fn revalidate_recorded(
+        &self,
+        url: &str,
+        headers: &[(String, String)],
+        cached: &CachedDocument,
+        attempts: &mut Vec<FetchAttempt>,
+    ) -> Result<Option<CachedDocument>> {
+        self.source_for(url)?
+            .revalidate_recorded(url, headers, cached, attempts)
+    }
+}
+
 /// Document fetcher for retrieving external content
+///
+/// A [`SchemeRouter`] pre-loaded with an [`HttpSource`] for `http://` and
+/// `https://`. Local files are only read once a source is registered for
+/// them, such as `register_source("file", FileSource::with_root(dir))`;
+/// further schemes are added the same way with
+/// [`register_source`](Self::register_source).
+///
+/// With a cache attached via [`with_cache`](Self::with_cache), documents are
+/// kept for the cache's `document_ttl` and then revalidated with
+/// `If-None-Match`/`If-Modified-Since` rather than downloaded again.
 pub struct DocumentFetcher {
-    #[allow(dead_code)]
     config: CrossDocumentConfig,
+    router: SchemeRouter,
+    cache: Option<TemplateCache>,
 }
 
 impl DocumentFetcher {
     /// Create a new document fetcher with default configuration
     pub fn new() -> Self {
-        Self {
-            config: CrossDocumentConfig::default(),
-        }
+        Self::with_config(CrossDocumentConfig::default())
     }
 
     /// Create a new document fetcher with custom configuration
     pub fn with_config(config: CrossDocumentConfig) -> Self {
-        Self { config }
+        let http = Arc::new(HttpSource::with_config(config.clone()));
+        let router = SchemeRouter::new()
+            .with_source("http", http.clone())
+            .with_source("https", http);
+
+        Self {
+            config,
+            router,
+            cache: None,
+        }
+    }
+
+    /// Cache fetched documents in `cache`, revalidating them once they expire
+    pub fn with_cache(mut self, cache: TemplateCache) -> Self {
+        self.cache = Some(cache);
+        self
+    }
+
+    /// Get the document cache, if one is attached
+    pub fn cache(&self) -> Option<&TemplateCache> {
+        self.cache.as_ref()
+    }
+
+    /// Get the configuration used by this fetcher
+    pub fn config(&self) -> &CrossDocumentConfig {
+        &self.config
+    }
+
+    /// Register a source for a URL scheme, replacing any existing one
+    pub fn register_source<S: DocumentSource + 'static>(&mut self, scheme: &str, source: S) {
+        self.router.register(scheme, source);
     }
 
     /// Fetch a document from the given URL
@@ -77,70 +1174,242 @@ impl DocumentFetcher {
         url: &str,
         headers: &[(String, String)],
     ) -> Result<CachedDocument> {
-        // For now, directly fetch without caching to avoid global cache issues
-        // TODO: Implement proper caching that doesn't cause memory safety issues
-        self.fetch_document_impl(url, headers)
+        self.fetch_recorded(url, headers, &mut Vec::new())
+    }
+
+    /// Fetch a document, appending a record of each request made to `attempts`
+    ///
+    /// Nothing is recorded when the document is served fresh from the cache.
+    fn fetch_recorded(
+        &self,
+        url: &str,
+        headers: &[(String, String)],
+        attempts: &mut Vec<FetchAttempt>,
+    ) -> Result<CachedDocument> {
+        match &self.cache {
+            Some(cache) => {
+                let key = DocumentCacheKey::with_headers(url, headers.to_vec());
+                // Only one of the two callbacks runs, but both need the log
+                let log = RefCell::new(std::mem::take(attempts));
+                let result = cache.get_or_revalidate_document(
+                    &key,
+                    || {
+                        self.router
+                            .load_recorded(url, headers, &mut log.borrow_mut())
+                    },
+                    |cached| {
+                        self.router
+                            .revalidate_recorded(url, headers, cached, &mut log.borrow_mut())
+                    },
+                );
+                *attempts = log.into_inner();
+                result
+            }
+            None => self.router.load_recorded(url, headers, attempts),
+        }
+    }
+}
+
+impl DocumentSource for DocumentFetcher {
+    fn load(&self, url: &str, headers: &[(String, String)]) -> Result<CachedDocument> {
+        self.fetch_with_headers(url, headers)
+    }
+
+    fn revalidate(
+        &self,
+        url: &str,
+        headers: &[(String, String)],
+        cached: &CachedDocument,
+    ) -> Result<Option<CachedDocument>> {
+        self.router.revalidate(url, headers, cached)
+    }
+
+    fn load_recorded(
+        &self,
+        url: &str,
+        headers: &[(String, String)],
+        attempts: &mut Vec<FetchAttempt>,
+    ) -> Result<CachedDocument> {
+        self.fetch_recorded(url, headers, attempts)
     }
 
-    /// Implementation of document fetching (simulated for now)
-    fn fetch_document_impl(
+    fn revalidate_recorded(
         &self,
         url: &str,
-        _headers: &[(String, String)],
-    ) -> Result<CachedDocument> {
-        // For testing/simulation purposes, we'll create some mock content
-        // In a real implementation, this would use an HTTP client
-
-        if url.starts_with("http://") || url.starts_with("https://") {
-            // Simulate successful fetch
-            let content = format!(
-                r#"
-                <html>
-                    <head><title>External Document</title></head>
-                    <body>
-                        <article itemscope itemtype="https://schema.org/Article">
-                            <h1 itemprop="headline">External Article from {}</h1>
-                            <div itemprop="author" itemscope itemtype="https://schema.org/Person">
-                                <span itemprop="name">External Author</span>
-                                <span itemprop="email">author@external.com</span>
-                            </div>
-                            <p itemprop="articleBody">This is content from an external document.</p>
-                            <time itemprop="datePublished" datetime="2024-01-01">January 1, 2024</time>
-                        </article>
-                    </body>
-                </html>
-            "#,
-                url
-            );
+        headers: &[(String, String)],
+        cached: &CachedDocument,
+        attempts: &mut Vec<FetchAttempt>,
+    ) -> Result<Option<CachedDocument>> {
+        self.router
+            .revalidate_recorded(url, headers, cached, attempts)
+    }
+}
 
-            Ok(CachedDocument {
-                content,
-                content_type: Some("text/html".to_string()),
-                etag: Some(format!(
-                    "\"{}\"",
-                    url.chars().map(|c| c as u32).sum::<u32>()
-                )),
-                last_modified: Some("Mon, 01 Jan 2024 00:00:00 GMT".to_string()),
-            })
-        } else if url.starts_with("file://") {
-            // Handle file URLs
-            let file_path = &url[7..]; // Remove "file://" prefix
-            match std::fs::read_to_string(file_path) {
-                Ok(content) => Ok(CachedDocument {
-                    content,
-                    content_type: Some("text/html".to_string()),
-                    etag: None,
-                    last_modified: None,
-                }),
-                Err(e) => Err(Error::io(format!(
-                    "Failed to read file '{}': {}",
-                    file_path, e
-                ))),
+/// Add `If-None-Match`/`If-Modified-Since` for the cached document's validators
+fn conditional_headers(
+    headers: &[(String, String)],
+    cached: &CachedDocument,
+) -> Vec<(String, String)> {
+    let mut conditional_headers = headers.to_vec();
+    if let Some(etag) = &cached.etag {
+        conditional_headers.push(("If-None-Match".to_string(), etag.clone()));
+    }
+    if let Some(last_modified) = &cached.last_modified {
+        conditional_headers.push(("If-Modified-Since".to_string(), last_modified.clone()));
+    }
+    conditional_headers
+}
+
+/// Check that a response status is a success or `304 Not Modified`
+fn check_status(
+    url: &str,
+    status: reqwest::StatusCode,
+) -> std::result::Result<u16, AttemptFailure> {
+    if status.is_success() || status == reqwest::StatusCode::NOT_MODIFIED {
+        Ok(status.as_u16())
+    } else {
+        Err((
+            Failure::Status(status.as_u16()),
+            Error::http_owned(format!("GET {} failed with status {}", url, status)),
+        ))
+    }
+}
+
+/// Error for a `304 Not Modified` response to an unconditional request
+fn not_modified_error(url: &str) -> Error {
+    Error::http_owned(format!("GET {} failed with status 304 Not Modified", url))
+}
+
+/// Build a cached document from a successful response
+fn document_from_response(headers: &HeaderMap, content: String) -> CachedDocument {
+    CachedDocument {
+        content,
+        content_type: header_string(headers, reqwest::header::CONTENT_TYPE),
+        etag: header_string(headers, reqwest::header::ETAG),
+        last_modified: header_string(headers, reqwest::header::LAST_MODIFIED),
+    }
+}
+
+/// Convert the configured default headers into a header map
+fn default_header_map(config: &CrossDocumentConfig) -> Result<HeaderMap> {
+    let mut default_headers = HeaderMap::new();
+    for (name, value) in &config.default_headers {
+        let header_name = HeaderName::from_bytes(name.as_bytes())
+            .map_err(|_| Error::http_owned(format!("Invalid default header name '{}'", name)))?;
+        let header_value = HeaderValue::from_str(value).map_err(|_| {
+            Error::http_owned(format!("Invalid value for default header '{}'", name))
+        })?;
+        default_headers.insert(header_name, header_value);
+    }
+    Ok(default_headers)
+}
+
+/// Get the redirect policy described by a [`CrossDocumentConfig`]
+///
+/// Each redirect target's host is checked against the fetch policy before it
+/// is followed; its addresses are checked when the connection resolves them.
+fn redirect_policy(config: &CrossDocumentConfig) -> reqwest::redirect::Policy {
+    if !config.follow_redirects {
+        return reqwest::redirect::Policy::none();
+    }
+
+    let max_redirects = config.max_redirects;
+    let fetch_policy = config.fetch_policy.clone();
+    reqwest::redirect::Policy::custom(move |attempt| {
+        if attempt.previous().len() > max_redirects {
+            return attempt.error("too many redirects");
+        }
+        match fetch_policy.host_refusal(attempt.url()) {
+            Some(reason) => {
+                let reason = format!("redirect to {}: {}", attempt.url(), reason);
+                attempt.error(PolicyViolation(reason))
             }
-        } else {
-            Err(Error::parse(format!("Unsupported URL scheme: {}", url)))
+            None => attempt.follow(),
+        }
+    })
+}
+
+/// Get the DNS resolver enforcing a [`CrossDocumentConfig`]'s fetch policy
+fn policy_resolver(config: &CrossDocumentConfig) -> Arc<PolicyResolver> {
+    Arc::new(PolicyResolver {
+        policy: config.fetch_policy.clone(),
+    })
+}
+
+/// Build the blocking HTTP client described by a [`CrossDocumentConfig`]
+fn build_http_client(config: &CrossDocumentConfig) -> Result<reqwest::blocking::Client> {
+    // The blocking builder wraps an async one, which is where the resolver is set
+    reqwest::blocking::ClientBuilder::from(
+        reqwest::Client::builder().dns_resolver(policy_resolver(config)),
+    )
+    .timeout(config.fetch_timeout)
+    .redirect(redirect_policy(config))
+    .default_headers(default_header_map(config)?)
+    .danger_accept_invalid_certs(!config.verify_ssl)
+    .user_agent(config.user_agent.as_str())
+    .build()
+    .map_err(|e| Error::http_owned(format!("Failed to build HTTP client: {}", e)))
+}
+
+/// Map a transport-level reqwest error to [`Error::HttpError`], for an
+/// attempt that was given `timeout`
+fn http_request_error(
+    url: &str,
+    config: &CrossDocumentConfig,
+    timeout: Duration,
+    err: reqwest::Error,
+) -> Error {
+    if err.is_timeout() {
+        Error::http_owned(format!("GET {} timed out after {:?}", url, timeout))
+    } else if err.is_redirect() {
+        Error::http_owned(format!(
+            "GET {} exceeded the redirect limit of {}",
+            url, config.max_redirects
+        ))
+    } else if let Some(status) = err.status() {
+        Error::http_owned(format!("GET {} failed with status {}", url, status))
+    } else {
+        Error::http_owned(format!("GET {} failed: {}", url, err))
+    }
+}
+
+/// Classify a failed request for retrying and map it to [`Error::HttpError`],
+/// or [`Error::PolicyError`] if the fetch policy refused a redirect or address
+fn request_failure(
+    url: &str,
+    config: &CrossDocumentConfig,
+    timeout: Duration,
+    err: reqwest::Error,
+) -> AttemptFailure {
+    let mut source = std::error::Error::source(&err);
+    while let Some(cause) = source {
+        if let Some(violation) = cause.downcast_ref::<PolicyViolation>() {
+            return (Failure::Fatal, refused(url, violation));
         }
+        source = cause.source();
     }
+
+    let failure = if err.is_redirect() || err.is_builder() {
+        Failure::Fatal
+    } else if let Some(status) = err.status() {
+        Failure::Status(status.as_u16())
+    } else {
+        Failure::Network(NetworkErrorKind::of(&err))
+    };
+    (failure, http_request_error(url, config, timeout, err))
+}
+
+/// Decode a response body, replacing invalid UTF-8
+fn body_text(body: Vec<u8>) -> String {
+    String::from_utf8(body).unwrap_or_else(|e| String::from_utf8_lossy(e.as_bytes()).into_owned())
+}
+
+/// Read a response header as an owned string, ignoring non-UTF-8 values
+fn header_string(headers: &HeaderMap, name: HeaderName) -> Option<String> {
+    headers
+        .get(name)
+        .and_then(|value| value.to_str().ok())
+        .map(|value| value.to_string())
 }
 
 impl Default for DocumentFetcher {
@@ -151,40 +1420,51 @@ impl Default for DocumentFetcher {
 
 /// Cross-document template renderer
 pub struct CrossDocumentRenderer {
-    fetcher: DocumentFetcher,
+    source: SharedSource,
+    link_following: Option<LinkFollowing>,
 }
 
 impl CrossDocumentRenderer {
     /// Create a new cross-document renderer
     pub fn new() -> Self {
-        Self {
-            fetcher: DocumentFetcher::new(),
-        }
+        Self::with_fetcher(DocumentFetcher::new())
     }
 
     /// Create a new cross-document renderer with custom fetcher
     pub fn with_fetcher(fetcher: DocumentFetcher) -> Self {
-        Self { fetcher }
+        Self::with_source(fetcher)
     }
 
-    /// Render a template using data from an external document
-    pub fn render_from_url(&self, template: &HtmlTemplate, url: &str) -> Result<Vec<String>> {
-        // Fetch the external document
-        let cached_doc = self.fetcher.fetch(url)?;
-
-        // Parse the HTML content
-        let doc = Document::from(cached_doc.content.as_ref());
+    /// Create a new cross-document renderer that loads documents from `source`
+    pub fn with_source<S: DocumentSource + 'static>(source: S) -> Self {
+        Self {
+            source: Arc::new(source),
+            link_following: None,
+        }
+    }
 
-        // Extract microdata from the document
-        let items = microdata::extract_microdata_from_document(&doc)?;
+    /// Replace link-valued properties with the items they point to
+    ///
+    /// See [`LinkFollowing`] for which properties are followed and the limits
+    /// that apply.
+    pub fn with_link_following(mut self, options: LinkFollowing) -> Self {
+        self.link_following = Some(options);
+        self
+    }
 
-        // Render template for each extracted item
-        let mut results = Vec::new();
-        for item in items {
-            results.push(template.render(&item)?);
-        }
+    /// Get the source documents are loaded from
+    pub fn source(&self) -> &dyn DocumentSource {
+        self.source.as_ref()
+    }
 
-        Ok(results)
+    /// Render a template using data from an external document
+    ///
+    /// If `url` has a fragment, only the item it identifies is rendered: the
+    /// element with that `id`, or the item with that `itemid`.
+    pub fn render_from_url(&self, template: &HtmlTemplate, url: &str) -> Result<Vec<String>> {
+        // Fetch the external document
+        let cached_doc = self.source.load(without_fragment(url), &[])?;
+        self.render_document(template, url, &cached_doc, ItemSelection::All)
     }
 
     /// Render a template using specific microdata from an external document
@@ -195,29 +1475,40 @@ impl CrossDocumentRenderer {
         selector: &str,
     ) -> Result<Vec<String>> {
         // Fetch the external document
-        let cached_doc = self.fetcher.fetch(url)?;
-
-        // Parse the HTML content
-        let doc = Document::from(cached_doc.content.as_ref());
-
-        // Select specific elements
-        let selected_elements = doc.select(selector);
+        let cached_doc = self.source.load(without_fragment(url), &[])?;
+        self.render_document(
+            template,
+            url,
+            &cached_doc,
+            ItemSelection::Selector(selector),
+        )
+    }
 
-        // Extract microdata from selected elements
-        let mut items = Vec::new();
-        for element in selected_elements.nodes() {
-            if let Ok(item) = microdata::extract_microdata(&element) {
-                items.push(item);
-            }
-        }
+    /// Render a template for each item of type `itemtype` in an external document
+    ///
+    /// Types match with or without the URL scheme, so `schema.org/Product`
+    /// selects `https://schema.org/Product` items. Items nested inside another
+    /// matching item are rendered as part of it rather than on their own.
+    pub fn render_from_url_with_type(
+        &self,
+        template: &HtmlTemplate,
+        url: &str,
+        itemtype: &str,
+    ) -> Result<Vec<String>> {
+        let cached_doc = self.source.load(without_fragment(url), &[])?;
+        self.render_document(template, url, &cached_doc, ItemSelection::Type(itemtype))
+    }
 
-        // Render template for each extracted item
-        let mut results = Vec::new();
-        for item in items {
-            results.push(template.render(&item)?);
-        }
+    /// Fetch a document and extract its microdata items without rendering
+    pub fn extract_from_url(&self, url: &str, selector: Option<&str>) -> Result<Vec<JsonValue>> {
+        let cached_doc = self.source.load(without_fragment(url), &[])?;
+        self.extract_items(url, &cached_doc, ItemSelection::from(selector))
+    }
 
-        Ok(results)
+    /// Fetch a document and extract its items of type `itemtype` without rendering
+    pub fn extract_from_url_with_type(&self, url: &str, itemtype: &str) -> Result<Vec<JsonValue>> {
+        let cached_doc = self.source.load(without_fragment(url), &[])?;
+        self.extract_items(url, &cached_doc, ItemSelection::Type(itemtype))
     }
 
     /// Render a template by combining data from multiple external documents
@@ -238,28 +1529,368 @@ impl CrossDocumentRenderer {
 
     /// Batch render templates from external documents with error handling
     pub fn batch_render(&self, requests: &[CrossDocumentRequest]) -> Vec<CrossDocumentResponse> {
-        let mut responses = Vec::new();
+        requests
+            .iter()
+            .map(|request| {
+                let mut attempts = Vec::new();
+                let fetched = self
+                    .source
+                    .load_recorded(without_fragment(&request.url), &[], &mut attempts)
+                    .map(|document| {
+                        // Nothing fetched, or only a 304, means the cache served it
+                        let cache_hit = attempts
+                            .last()
+                            .is_none_or(|attempt| attempt.status == Some(304));
+                        (document, cache_hit)
+                    });
+                let fetch_time = Instant::now();
+                respond(&request.url, fetched, fetch_time, attempts, |document| {
+                    self.render_document(
+                        &request.template,
+                        &request.url,
+                        document,
+                        ItemSelection::from(request.selector.as_deref()),
+                    )
+                })
+            })
+            .collect()
+    }
+
+    /// Render a template for each item in a fetched document
+    fn render_document(
+        &self,
+        template: &HtmlTemplate,
+        url: &str,
+        document: &CachedDocument,
+        selection: ItemSelection,
+    ) -> Result<Vec<String>> {
+        let items = self.extract_items(url, document, selection)?;
+        items.iter().map(|item| template.render(item)).collect()
+    }
+
+    /// Extract the items of a fetched document, following links if enabled
+    fn extract_items(
+        &self,
+        url: &str,
+        document: &CachedDocument,
+        selection: ItemSelection,
+    ) -> Result<Vec<JsonValue>> {
+        let mut items = extract_items(url, document, selection)?;
+
+        if let Some(options) = &self.link_following {
+            let links = document_links(url, document);
+            let mut follower = LinkFollower::new(self.source.as_ref(), options);
+            let mut urls = vec![without_fragment(url).to_string()];
+            for item in &mut items {
+                follower.follow(item, &links, 0, &mut urls, &mut Vec::new());
+            }
+        }
+
+        Ok(items)
+    }
+}
+
+/// Options for following link-valued properties to the items they point to
+///
+/// A property whose value comes from the `href` of an `<a>`, `<link>` or
+/// `<area>` element is replaced by the item in the linked document: the one
+/// the link's fragment identifies, the one whose `itemid` is the link URL,
+/// or else the first top-level item. Links
+/// are left as URLs when they cannot be fetched, have no item, would form a
+/// cycle (by URL or `itemid`) or exceed the limits below. Only links with the
+/// linking document's scheme are followed, `http` and `https` counting as
+/// one, so a web page can't pull in local files.
+///
+/// ```rust,ignore
+/// let renderer = CrossDocumentRenderer::new().with_link_following(LinkFollowing {
+///     properties: vec!["author".to_string()],
+///     ..Default::default()
+/// });
+/// // `<a itemprop="author" href="/people/ada">` now renders `${author.name}`
+/// ```
+#[derive(Debug, Clone, PartialEq)]
+pub struct LinkFollowing {
+    /// How many links deep to follow from the rendered document
+    pub max_depth: usize,
+    /// Maximum number of documents fetched per render
+    pub max_fetches: usize,
+    /// Property names to follow; when empty, every link-valued property is
+    pub properties: Vec<String>,
+}
+
+impl Default for LinkFollowing {
+    fn default() -> Self {
+        Self {
+            max_depth: 1,
+            max_fetches: 10,
+            properties: Vec::new(),
+        }
+    }
+}
+
+/// A linked document and the URLs of its link-valued properties
+type LinkedDocument = (CachedDocument, HashSet<String>);
+
+/// Replaces link-valued properties with linked items during one render
+struct LinkFollower<'a> {
+    source: &'a dyn DocumentSource,
+    options: &'a LinkFollowing,
+    fetches: usize,
+    /// Documents already fetched, or `None` if loading failed
+    documents: HashMap<String, Option<LinkedDocument>>,
+}
+
+impl<'a> LinkFollower<'a> {
+    fn new(source: &'a dyn DocumentSource, options: &'a LinkFollowing) -> Self {
+        Self {
+            source,
+            options,
+            fetches: 0,
+            documents: HashMap::new(),
+        }
+    }
 
-        for request in requests {
-            let result = match &request.selector {
-                Some(selector) => {
-                    self.render_from_url_with_selector(&request.template, &request.url, selector)
+    /// Follow the links in `item`, which is `depth` links from the rendered
+    /// document and nested within the documents `urls` and items `ids`
+    fn follow(
+        &mut self,
+        item: &mut JsonValue,
+        links: &HashSet<String>,
+        depth: usize,
+        urls: &mut Vec<String>,
+        ids: &mut Vec<String>,
+    ) {
+        let object = match item {
+            JsonValue::Object(object) => object,
+            JsonValue::Array(values) => {
+                for value in values {
+                    self.follow(value, links, depth, urls, ids);
                 }
-                None => self.render_from_url(&request.template, &request.url),
-            };
+                return;
+            }
+            _ => return,
+        };
+
+        let id = object
+            .get("@id")
+            .and_then(JsonValue::as_str)
+            .map(str::to_string);
+        if let Some(id) = &id {
+            ids.push(id.clone());
+        }
+
+        for (name, value) in object.iter_mut() {
+            if name.starts_with('@') {
+                continue;
+            }
+            let followed = self.options.properties.is_empty()
+                || self
+                    .options
+                    .properties
+                    .iter()
+                    .any(|property| property == name);
+
+            match value {
+                JsonValue::Array(values) => {
+                    for value in values {
+                        self.follow_value(value, followed, links, depth, urls, ids);
+                    }
+                }
+                value => self.follow_value(value, followed, links, depth, urls, ids),
+            }
+        }
+
+        if id.is_some() {
+            ids.pop();
+        }
+    }
+
+    /// Replace a single property value if it is a link that should be followed
+    fn follow_value(
+        &mut self,
+        value: &mut JsonValue,
+        followed: bool,
+        links: &HashSet<String>,
+        depth: usize,
+        urls: &mut Vec<String>,
+        ids: &mut Vec<String>,
+    ) {
+        let url = match value {
+            JsonValue::String(url) if followed && links.contains(url.as_str()) => url.clone(),
+            // Nested items belong to the same document
+            _ => return self.follow(value, links, depth, urls, ids),
+        };
+
+        let document_url = without_fragment(&url).to_string();
+        let linking = urls.last().map_or("", String::as_str);
+        if depth >= self.options.max_depth
+            || urls.contains(&document_url)
+            || !same_scheme_family(linking, &url)
+        {
+            return;
+        }
+
+        let (mut linked, linked_links) = match self.load(&document_url) {
+            Some((document, links)) => {
+                // A link with a fragment must point at an item that exists
+                let items = match extract_items(&url, document, ItemSelection::All) {
+                    Ok(items) => items,
+                    Err(_) => return,
+                };
+                match linked_item(&items, &url) {
+                    Some(item) => (item.clone(), links.clone()),
+                    None => return,
+                }
+            }
+            None => return,
+        };
+
+        let linked_id = linked.get("@id").and_then(JsonValue::as_str);
+        if linked_id.is_some_and(|id| ids.iter().any(|ancestor| ancestor == id)) {
+            return;
+        }
 
-            responses.push(CrossDocumentResponse {
-                url: request.url.clone(),
-                result,
-                metadata: CrossDocumentMetadata {
-                    fetch_time: std::time::Instant::now(),
-                    cache_hit: false, // Would be determined by actual cache implementation
-                    content_type: None, // Would be filled by actual fetcher
-                },
+        urls.push(document_url);
+        self.follow(&mut linked, &linked_links, depth + 1, urls, ids);
+        urls.pop();
+        *value = linked;
+    }
+
+    /// Load a linked document, within the fetch budget
+    fn load(&mut self, url: &str) -> Option<&LinkedDocument> {
+        if !self.documents.contains_key(url) {
+            if self.fetches >= self.options.max_fetches {
+                return None;
+            }
+            self.fetches += 1;
+
+            let linked = self.source.load(url, &[]).ok().map(|document| {
+                let links = document_links(url, &document);
+                (document, links)
             });
+            self.documents.insert(url.to_string(), linked);
         }
 
-        responses
+        self.documents.get(url).and_then(Option::as_ref)
+    }
+}
+
+/// Pick the item a link points to: the one whose `itemid` is the link, or the first
+fn linked_item<'a>(items: &'a [JsonValue], url: &str) -> Option<&'a JsonValue> {
+    items
+        .iter()
+        .find(|item| item.get("@id").and_then(JsonValue::as_str) == Some(url))
+        .or_else(|| items.first())
+}
+
+/// Get the URLs of link-valued properties in a fetched document
+fn document_links(url: &str, document: &CachedDocument) -> HashSet<String> {
+    let doc = Document::from(document.content.as_ref());
+    microdata::extract_item_links(&doc, Some(url))
+}
+
+/// Whether `to` has the scheme of `from`, treating `http` and `https` alike
+fn same_scheme_family(from: &str, to: &str) -> bool {
+    let family = |url| {
+        url_scheme(url).map(|scheme| match scheme.to_ascii_lowercase().as_str() {
+            "https" => "http".to_string(),
+            scheme => scheme.to_string(),
+        })
+    };
+    family(from).is_some() && family(from) == family(to)
+}
+
+/// Strip the fragment from a URL
+fn without_fragment(url: &str) -> &str {
+    url.split_once('#').map_or(url, |(url, _)| url)
+}
+
+/// Get the fragment of a URL, if it has a non-empty one
+fn fragment(url: &str) -> Option<&str> {
+    url.split_once('#')
+        .map(|(_, fragment)| fragment)
+        .filter(|fragment| !fragment.is_empty())
+}
+
+/// Which items of a fetched document to extract
+#[derive(Debug, Clone, Copy)]
+enum ItemSelection<'a> {
+    /// The item named by the URL fragment, or every top-level item
+    All,
+    /// Items rooted at the elements matching a CSS selector
+    Selector(&'a str),
+    /// Items of an `itemtype`
+    Type(&'a str),
+}
+
+impl<'a> From<Option<&'a str>> for ItemSelection<'a> {
+    fn from(selector: Option<&'a str>) -> Self {
+        selector.map_or(ItemSelection::All, ItemSelection::Selector)
+    }
+}
+
+/// Render a template for each microdata item in a document fetched from `url`
+///
+/// The async renderer uses this directly as it does not follow links.
+#[cfg(feature = "async")]
+fn render_document(
+    template: &HtmlTemplate,
+    url: &str,
+    document: &CachedDocument,
+    selection: ItemSelection,
+) -> Result<Vec<String>> {
+    let items = extract_items(url, document, selection)?;
+
+    // Render template for each extracted item
+    let mut results = Vec::new();
+    for item in items {
+        results.push(template.render(&item)?);
+    }
+
+    Ok(results)
+}
+
+/// Extract the microdata items from a document fetched from `url`
+///
+/// With a selector, only items rooted at the selected elements are extracted,
+/// and with an itemtype only items of that type. Otherwise a fragment in `url`
+/// selects the one item it identifies, which must exist. Relative URL
+/// properties are resolved against the document's base URL.
+fn extract_items(
+    url: &str,
+    document: &CachedDocument,
+    selection: ItemSelection,
+) -> Result<Vec<JsonValue>> {
+    // Parse the HTML content
+    let doc = Document::from(document.content.as_ref());
+    let document_url = url::Url::parse(url).ok();
+    let base = url_resolver::document_base_url(&doc.root(), document_url.as_ref());
+
+    // Extract microdata from the document or the selected elements
+    match selection {
+        ItemSelection::Selector(selector) => Ok(doc
+            .select(selector)
+            .nodes()
+            .iter()
+            .filter_map(|element| {
+                microdata::extract_microdata_with_base(element, base.as_ref()).ok()
+            })
+            .collect()),
+        ItemSelection::Type(itemtype) => {
+            microdata::extract_microdata_by_type(&doc, Some(url), itemtype)
+        }
+        ItemSelection::All => match fragment(url) {
+            Some(fragment) => microdata::extract_microdata_by_fragment(&doc, url, fragment)?
+                .map(|item| vec![item])
+                .ok_or_else(|| {
+                    Error::dom_owned(format!(
+                        "No item with id or itemid '{}' in {}",
+                        fragment,
+                        without_fragment(url)
+                    ))
+                }),
+            None => microdata::extract_microdata_from_document_with_url(&doc, url),
+        },
     }
 }
 
@@ -291,18 +1922,364 @@ pub struct CrossDocumentResponse {
     pub metadata: CrossDocumentMetadata,
 }
 
-/// Metadata about cross-document operations
-#[derive(Debug)]
-pub struct CrossDocumentMetadata {
-    /// When the fetch operation completed
-    pub fetch_time: std::time::Instant,
-    /// Whether the document was served from cache
-    pub cache_hit: bool,
-    /// Content type of the fetched document
-    pub content_type: Option<String>,
+/// Metadata about cross-document operations
+#[derive(Debug)]
+pub struct CrossDocumentMetadata {
+    /// When the fetch operation completed
+    pub fetch_time: std::time::Instant,
+    /// Whether the document was served from cache, including documents
+    /// revalidated with `304 Not Modified`
+    pub cache_hit: bool,
+    /// Content type of the fetched document
+    pub content_type: Option<String>,
+    /// Each request made for the document, in order; empty when it was
+    /// served fresh from the cache
+    pub attempts: Vec<FetchAttempt>,
+}
+
+/// Asynchronous cross-document renderer
+///
+/// Fetches `http://` and `https://` documents concurrently, at most
+/// [`CrossDocumentConfig::max_concurrent_fetches`] at a time, and returns one
+/// [`CrossDocumentResponse`] per URL in input order. Other schemes are loaded
+/// from sources added with [`register_source`](Self::register_source); these
+/// run on the rendering task, so they should not block for long.
+///
+/// ```rust,ignore
+/// let renderer = AsyncCrossDocumentRenderer::new().with_cache(TemplateCache::new());
+/// let responses = renderer.render_from_urls(&template, &urls).await;
+/// for response in responses {
+///     println!("{} cache_hit={}", response.url, response.metadata.cache_hit);
+/// }
+/// ```
+#[cfg(feature = "async")]
+pub struct AsyncCrossDocumentRenderer {
+    config: CrossDocumentConfig,
+    client: OnceCell<reqwest::Client>,
+    sources: SchemeRouter,
+    cache: Option<TemplateCache>,
+}
+
+#[cfg(feature = "async")]
+impl AsyncCrossDocumentRenderer {
+    /// Create a new async renderer with default configuration
+    pub fn new() -> Self {
+        Self::with_config(CrossDocumentConfig::default())
+    }
+
+    /// Create a new async renderer with custom configuration
+    pub fn with_config(config: CrossDocumentConfig) -> Self {
+        Self {
+            config,
+            client: OnceCell::new(),
+            sources: SchemeRouter::new(),
+            cache: None,
+        }
+    }
+
+    /// Cache fetched documents in `cache`, revalidating them once they expire
+    pub fn with_cache(mut self, cache: TemplateCache) -> Self {
+        self.cache = Some(cache);
+        self
+    }
+
+    /// Register a source for a non-HTTP URL scheme, replacing any existing one
+    pub fn register_source<S: DocumentSource + 'static>(&mut self, scheme: &str, source: S) {
+        self.sources.register(scheme, source);
+    }
+
+    /// Get the configuration used by this renderer
+    pub fn config(&self) -> &CrossDocumentConfig {
+        &self.config
+    }
+
+    /// Get the document cache, if one is attached
+    pub fn cache(&self) -> Option<&TemplateCache> {
+        self.cache.as_ref()
+    }
+
+    /// Render a template with data from each URL, fetching concurrently
+    pub async fn render_from_urls(
+        &self,
+        template: &HtmlTemplate,
+        urls: &[&str],
+    ) -> Vec<CrossDocumentResponse> {
+        use futures::StreamExt;
+
+        futures::stream::iter(urls)
+            .map(|url| async move {
+                let mut attempts = Vec::new();
+                let fetched = self.fetch(url, &mut attempts).await;
+                (*url, fetched, Instant::now(), attempts)
+            })
+            .buffered(self.config.max_concurrent_fetches.max(1))
+            .map(|(url, fetched, fetch_time, attempts)| {
+                respond(url, fetched, fetch_time, attempts, |document| {
+                    render_document(template, url, document, ItemSelection::All)
+                })
+            })
+            .collect()
+            .await
+    }
+
+    /// Batch render templates from external documents, fetching concurrently
+    pub async fn batch_render(
+        &self,
+        requests: &[CrossDocumentRequest],
+    ) -> Vec<CrossDocumentResponse> {
+        use futures::StreamExt;
+
+        futures::stream::iter(requests)
+            .map(|request| async move {
+                let mut attempts = Vec::new();
+                let fetched = self.fetch(&request.url, &mut attempts).await;
+                (request, fetched, Instant::now(), attempts)
+            })
+            .buffered(self.config.max_concurrent_fetches.max(1))
+            .map(|(request, fetched, fetch_time, attempts)| {
+                respond(&request.url, fetched, fetch_time, attempts, |document| {
+                    render_document(
+                        &request.template,
+                        &request.url,
+                        document,
+                        ItemSelection::from(request.selector.as_deref()),
+                    )
+                })
+            })
+            .collect()
+            .await
+    }
+
+    /// Fetch a document through the cache, returning whether it was served from cache
+    async fn fetch(
+        &self,
+        url: &str,
+        attempts: &mut Vec<FetchAttempt>,
+    ) -> Result<(CachedDocument, bool)> {
+        let url = without_fragment(url);
+        let key = DocumentCacheKey::new(url);
+        let lookup = match &self.cache {
+            Some(cache) => cache.lookup_document(&key),
+            None => CacheLookup::Missing,
+        };
+
+        match lookup {
+            CacheLookup::Fresh(document) => Ok((document, true)),
+            CacheLookup::Stale(stale) => match self.revalidate(url, &stale, attempts).await? {
+                None => {
+                    if let Some(cache) = &self.cache {
+                        cache.mark_document_revalidated(&key);
+                    }
+                    Ok((stale, true))
+                }
+                Some(document) => {
+                    if let Some(cache) = &self.cache {
+                        cache.store_document(&key, document.clone(), true);
+                    }
+                    Ok((document, false))
+                }
+            },
+            CacheLookup::Missing => {
+                let document = self.load(url, attempts).await?;
+                if let Some(cache) = &self.cache {
+                    cache.store_document(&key, document.clone(), false);
+                }
+                Ok((document, false))
+            }
+        }
+    }
+
+    /// Load a document from its source
+    async fn load(&self, url: &str, attempts: &mut Vec<FetchAttempt>) -> Result<CachedDocument> {
+        if !is_http_url(url) {
+            return self.sources.load_recorded(url, &[], attempts);
+        }
+
+        self.send(url, &[], attempts)
+            .await?
+            .ok_or_else(|| not_modified_error(url))
+    }
+
+    /// Check whether a cached document is still current
+    async fn revalidate(
+        &self,
+        url: &str,
+        cached: &CachedDocument,
+        attempts: &mut Vec<FetchAttempt>,
+    ) -> Result<Option<CachedDocument>> {
+        if !is_http_url(url) {
+            return self.sources.revalidate_recorded(url, &[], cached, attempts);
+        }
+
+        if !cached.has_validators() {
+            return self.load(url, attempts).await.map(Some);
+        }
+
+        self.send(url, &conditional_headers(&[], cached), attempts)
+            .await
+    }
+
+    /// Perform a GET request with retries, returning `None` for `304 Not Modified`
+    async fn send(
+        &self,
+        url: &str,
+        headers: &[(String, String)],
+        attempts: &mut Vec<FetchAttempt>,
+    ) -> Result<Option<CachedDocument>> {
+        let policy = &self.config.retry_policy;
+        let started = Instant::now();
+
+        for number in 1.. {
+            let timeout = policy
+                .remaining(started.elapsed())
+                .map_or(self.config.fetch_timeout, |remaining| {
+                    remaining.min(self.config.fetch_timeout)
+                });
+            let attempt_started = Instant::now();
+            let result = self.send_once(url, headers, timeout).await;
+            record_attempt(attempts, number, attempt_started, &result);
+
+            let (failure, error) = match result {
+                Ok((_, document)) => return Ok(document),
+                Err(failure) => failure,
+            };
+            match policy.retry_delay(number, &failure, started.elapsed()) {
+                Some(delay) => {
+                    if let Some(attempt) = attempts.last_mut() {
+                        attempt.backoff = Some(delay);
+                    }
+                    tokio::time::sleep(delay).await;
+                }
+                None => return Err(error),
+            }
+        }
+
+        unreachable!("retry loop only exits by returning")
+    }
+
+    /// Make a single GET request
+    async fn send_once(
+        &self,
+        url: &str,
+        headers: &[(String, String)],
+        timeout: Duration,
+    ) -> AttemptResult {
+        let policy = &self.config.fetch_policy;
+        policy.check_host(url).map_err(|e| (Failure::Fatal, e))?;
+
+        let client = self
+            .client
+            .get_or_try_init(|| build_async_http_client(&self.config))
+            .map_err(|e| (Failure::Fatal, e))?;
+
+        let mut request = client.get(url).timeout(timeout);
+        for (name, value) in headers {
+            request = request.header(name.as_str(), value.as_str());
+        }
+
+        let mut response = request
+            .send()
+            .await
+            .map_err(|e| request_failure(url, &self.config, timeout, e))?;
+
+        let status = check_status(url, response.status())?;
+        if status == reqwest::StatusCode::NOT_MODIFIED.as_u16() {
+            return Ok((status, None));
+        }
+
+        let response_headers = response.headers().clone();
+        policy
+            .check_response(url, &response_headers)
+            .map_err(|e| (Failure::Fatal, e))?;
+
+        let mut body = Vec::new();
+        while let Some(chunk) = response
+            .chunk()
+            .await
+            .map_err(|e| request_failure(url, &self.config, timeout, e))?
+        {
+            body.extend_from_slice(&chunk);
+            policy
+                .check_body_size(url, body.len())
+                .map_err(|e| (Failure::Fatal, e))?;
+        }
+
+        let content = body_text(body);
+        Ok((
+            status,
+            Some(document_from_response(&response_headers, content)),
+        ))
+    }
+}
+
+#[cfg(feature = "async")]
+impl Default for AsyncCrossDocumentRenderer {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+/// Check if a URL is fetched over HTTP
+#[cfg(feature = "async")]
+fn is_http_url(url: &str) -> bool {
+    url_scheme(url).is_some_and(|scheme| {
+        scheme.eq_ignore_ascii_case("http") || scheme.eq_ignore_ascii_case("https")
+    })
+}
+
+/// Build the async HTTP client described by a [`CrossDocumentConfig`]
+#[cfg(feature = "async")]
+fn build_async_http_client(config: &CrossDocumentConfig) -> Result<reqwest::Client> {
+    reqwest::Client::builder()
+        .dns_resolver(policy_resolver(config))
+        .timeout(config.fetch_timeout)
+        .redirect(redirect_policy(config))
+        .default_headers(default_header_map(config)?)
+        .danger_accept_invalid_certs(!config.verify_ssl)
+        .user_agent(config.user_agent.as_str())
+        .build()
+        .map_err(|e| Error::http_owned(format!("Failed to build HTTP client: {}", e)))
+}
+
+/// Render a fetched document into a response with its metadata, where
+/// `fetch_time` is when the fetch completed
+fn respond(
+    url: &str,
+    fetched: Result<(CachedDocument, bool)>,
+    fetch_time: Instant,
+    attempts: Vec<FetchAttempt>,
+    render: impl FnOnce(&CachedDocument) -> Result<Vec<String>>,
+) -> CrossDocumentResponse {
+    let (result, cache_hit, content_type) = match fetched {
+        Ok((document, cache_hit)) => (render(&document), cache_hit, document.content_type),
+        Err(e) => (Err(e), false, None),
+    };
+
+    CrossDocumentResponse {
+        url: url.to_string(),
+        result,
+        metadata: CrossDocumentMetadata {
+            fetch_time,
+            cache_hit,
+            content_type,
+            attempts,
+        },
+    }
 }
 
 /// Cross-document template with embedded external data sources
+///
+/// [`render`](Self::render) renders the template once per item from each
+/// source. [`render_merged`](Self::render_merged) instead combines every source
+/// into one data value and renders the template once:
+///
+/// ```rust,ignore
+/// let mut page = CrossDocumentTemplate::new(template);
+/// page.add_data_source(DataSource::url("https://shop.example/product/1").mount_at("product"));
+/// page.add_data_source(DataSource::url("https://reviews.example/product/1").mount_at("reviews"));
+/// page.add_data_source(DataSource::Static { data: json!({"site": {"name": "Shop"}}) });
+/// let html = page.render_merged()?;
+/// ```
 pub struct CrossDocumentTemplate {
     /// Base template
     pub template: HtmlTemplate,
@@ -310,18 +2287,37 @@ pub struct CrossDocumentTemplate {
     pub data_sources: Vec<DataSource>,
     /// Renderer for cross-document operations
     pub renderer: CrossDocumentRenderer,
+    /// How conflicting values are resolved when sources are merged
+    pub conflict_policy: ConflictPolicy,
 }
 
 impl CrossDocumentTemplate {
     /// Create a new cross-document template
     pub fn new(template: HtmlTemplate) -> Self {
+        Self::with_renderer(template, CrossDocumentRenderer::new())
+    }
+
+    /// Create a new cross-document template that loads URL data sources from `source`
+    pub fn with_source<S: DocumentSource + 'static>(template: HtmlTemplate, source: S) -> Self {
+        Self::with_renderer(template, CrossDocumentRenderer::with_source(source))
+    }
+
+    /// Create a new cross-document template with a custom renderer
+    pub fn with_renderer(template: HtmlTemplate, renderer: CrossDocumentRenderer) -> Self {
         Self {
             template,
             data_sources: Vec::new(),
-            renderer: CrossDocumentRenderer::new(),
+            renderer,
+            conflict_policy: ConflictPolicy::default(),
         }
     }
 
+    /// Set how conflicting values are resolved when sources are merged
+    pub fn with_conflict_policy(mut self, policy: ConflictPolicy) -> Self {
+        self.conflict_policy = policy;
+        self
+    }
+
     /// Add an external data source
     pub fn add_data_source(&mut self, source: DataSource) {
         self.data_sources.push(source);
@@ -333,7 +2329,7 @@ impl CrossDocumentTemplate {
 
         // Collect data from all sources
         for source in &self.data_sources {
-            match source {
+            match source.unmounted() {
                 DataSource::Url { url, selector } => {
                     let items = match selector {
                         Some(sel) => {
@@ -348,11 +2344,162 @@ impl CrossDocumentTemplate {
                     let rendered = self.template.render(data)?;
                     all_data.push(rendered);
                 }
+                DataSource::Mounted { .. } => unreachable!("unmounted() strips mounts"),
             }
         }
 
         Ok(all_data)
     }
+
+    /// Render the template once with the data of all sources merged
+    pub fn render_merged(&self) -> Result<String> {
+        let data = self.merged_data()?;
+        self.template.render(&data)
+    }
+
+    /// Fetch every source and merge its data into one value
+    ///
+    /// Sources are merged in order. Mounted sources are placed at their path;
+    /// the others must be objects and are deep-merged into the root. A URL
+    /// source always contributes an array of its microdata items, however
+    /// many the document has, so it must be mounted.
+    pub fn merged_data(&self) -> Result<JsonValue> {
+        let mut merged = JsonValue::Object(serde_json::Map::new());
+
+        for source in &self.data_sources {
+            let mut path = Vec::new();
+            let mut source = source;
+            while let DataSource::Mounted {
+                path: mount,
+                source: inner,
+            } = source
+            {
+                path.extend(mount.split('.').filter(|segment| !segment.is_empty()));
+                source = inner;
+            }
+
+            let value = match source {
+                DataSource::Url { url, selector } => {
+                    JsonValue::Array(self.renderer.extract_from_url(url, selector.as_deref())?)
+                }
+                DataSource::Static { data } => data.clone(),
+                DataSource::Mounted { .. } => unreachable!("mounts are unwrapped above"),
+            };
+
+            mount_value(&mut merged, &path, value, self.conflict_policy)?;
+        }
+
+        Ok(merged)
+    }
+}
+
+/// How conflicting values are resolved when data sources are merged
+///
+/// Objects are always merged key by key; a conflict is any other pair of
+/// differing values at the same path.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum ConflictPolicy {
+    /// Later sources replace values from earlier ones
+    #[default]
+    Overwrite,
+    /// The first source to set a value keeps it
+    KeepFirst,
+    /// Fail with a render error naming the conflicting path
+    Error,
+}
+
+/// Merge `value` into `target` at `path`, creating objects along the way
+fn mount_value(
+    target: &mut JsonValue,
+    path: &[&str],
+    value: JsonValue,
+    policy: ConflictPolicy,
+) -> Result<()> {
+    let mut current = target;
+    let mut current_path = String::new();
+
+    for (depth, segment) in path.iter().enumerate() {
+        // The root is always an object, so `current_path` names the conflict
+        if !current.is_object() {
+            match policy {
+                ConflictPolicy::Overwrite => *current = JsonValue::Object(serde_json::Map::new()),
+                ConflictPolicy::KeepFirst => return Ok(()),
+                ConflictPolicy::Error => return Err(merge_conflict(&current_path)),
+            }
+        }
+
+        if !current_path.is_empty() {
+            current_path.push('.');
+        }
+        current_path.push_str(segment);
+        let object = current
+            .as_object_mut()
+            .expect("ensured to be an object above");
+
+        // A value mounted at a new path is inserted as is, so no placeholder
+        // is ever mistaken for data
+        if depth + 1 == path.len() && !object.contains_key(*segment) {
+            object.insert(segment.to_string(), value);
+            return Ok(());
+        }
+        current = object
+            .entry(segment.to_string())
+            .or_insert_with(|| JsonValue::Object(serde_json::Map::new()));
+    }
+
+    if path.is_empty() && !value.is_object() {
+        return Err(Error::render_owned(
+            "Only objects can be merged at the root; mount other data at a path".to_string(),
+        ));
+    }
+
+    deep_merge(current, value, &mut current_path, policy)
+}
+
+/// Deep-merge `value` into `target`, resolving conflicts with `policy`
+fn deep_merge(
+    target: &mut JsonValue,
+    value: JsonValue,
+    path: &mut String,
+    policy: ConflictPolicy,
+) -> Result<()> {
+    match (target, value) {
+        (JsonValue::Object(target), JsonValue::Object(value)) => {
+            for (key, value) in value {
+                match target.get_mut(&key) {
+                    Some(existing) => {
+                        let len = path.len();
+                        if !path.is_empty() {
+                            path.push('.');
+                        }
+                        path.push_str(&key);
+                        deep_merge(existing, value, path, policy)?;
+                        path.truncate(len);
+                    }
+                    None => {
+                        target.insert(key, value);
+                    }
+                }
+            }
+            Ok(())
+        }
+        (target, value) if *target == value => Ok(()),
+        (target, value) => match policy {
+            ConflictPolicy::Overwrite => {
+                *target = value;
+                Ok(())
+            }
+            ConflictPolicy::KeepFirst => Ok(()),
+            ConflictPolicy::Error => Err(merge_conflict(path)),
+        },
+    }
+}
+
+fn merge_conflict(path: &str) -> Error {
+    Error::render_owned(format!(
+        "Conflicting values for '{}' while merging data sources",
+        path
+    ))
 }
 
 /// External data source for cross-document templates
@@ -365,13 +2512,99 @@ pub enum DataSource {
     },
     /// Static data source
     Static { data: JsonValue },
+    /// Another source placed at a dot-separated path when sources are merged
+    Mounted {
+        path: String,
+        source: Box<DataSource>,
+    },
+}
+
+impl DataSource {
+    /// Create a source for all microdata in the document at `url`
+    pub fn url(url: impl Into<String>) -> Self {
+        DataSource::Url {
+            url: url.into(),
+            selector: None,
+        }
+    }
+
+    /// Place this source at a dot-separated path, such as `"product"` or
+    /// `"site.config"`, when sources are merged
+    pub fn mount_at(self, path: impl Into<String>) -> Self {
+        DataSource::Mounted {
+            path: path.into(),
+            source: Box::new(self),
+        }
+    }
+
+    /// Get the source with any mount paths removed
+    fn unmounted(&self) -> &DataSource {
+        match self {
+            DataSource::Mounted { source, .. } => source.unmounted(),
+            source => source,
+        }
+    }
 }
 
 #[cfg(test)]
 mod tests {
     use super::*;
+    use crate::test_utils::{MockHttpServer, MockResponse};
     use serde_json::json;
 
+    fn article_html(title: &str) -> String {
+        format!(
+            r#"
+            <html>
+                <head><title>External Document</title></head>
+                <body>
+                    <article itemscope itemtype="https://schema.org/Article">
+                        <h1 itemprop="headline">External Article from {}</h1>
+                        <div itemprop="author" itemscope itemtype="https://schema.org/Person">
+                            <span itemprop="name">External Author</span>
+                        </div>
+                        <p itemprop="articleBody">This is content from an external document.</p>
+                    </article>
+                </body>
+            </html>
+        "#,
+            title
+        )
+    }
+
+    /// Start a loopback server serving an article at each of the given paths
+    fn article_server(paths: &[&str]) -> MockHttpServer {
+        let server = MockHttpServer::start();
+        for path in paths {
+            server.route(
+                path,
+                MockResponse::html(article_html(path))
+                    .with_header("ETag", "\"v1\"")
+                    .with_header("Last-Modified", "Mon, 01 Jan 2024 00:00:00 GMT"),
+            );
+        }
+        server
+    }
+
+    /// Configuration that allows fetching from the loopback mock server
+    fn local_config() -> CrossDocumentConfig {
+        CrossDocumentConfig {
+            fetch_policy: FetchPolicy {
+                allow_loopback: true,
+                ..Default::default()
+            },
+            ..Default::default()
+        }
+    }
+
+    fn local_fetcher() -> DocumentFetcher {
+        DocumentFetcher::with_config(local_config())
+    }
+
+    fn local_renderer() -> CrossDocumentRenderer {
+        CrossDocumentRenderer::with_fetcher(local_fetcher())
+    }
+
     #[test]
     fn test_document_fetcher_creation() {
         let fetcher = DocumentFetcher::new();
@@ -395,6 +2628,8 @@ mod tests {
                 .collect(),
             verify_ssl: false,
             user_agent: "test-agent/1.0".to_string(),
+            retry_policy: RetryPolicy::default(),
+            fetch_policy: FetchPolicy::permissive(),
         };
 
         let fetcher = DocumentFetcher::with_config(config.clone());
@@ -404,6 +2639,8 @@ mod tests {
         assert_eq!(fetcher.config.max_redirects, 2);
         assert_eq!(fetcher.config.verify_ssl, false);
         assert_eq!(fetcher.config.user_agent, "test-agent/1.0");
+        assert_eq!(fetcher.config.retry_policy.max_attempts, 3);
+        assert_eq!(fetcher.config.fetch_policy.max_body_size, None);
         assert_eq!(
             fetcher.config.default_headers.get("Accept"),
             Some(&"text/html".to_string())
@@ -411,67 +2648,602 @@ mod tests {
     }
 
     #[test]
-    fn test_fetch_http_document() {
-        let fetcher = DocumentFetcher::new();
-        let result = fetcher.fetch("https://example.com/article");
+    fn test_fetch_http_document() {
+        let server = article_server(&["/article"]);
+        let fetcher = local_fetcher();
+        let result = fetcher.fetch(&server.url("/article"));
+
+        assert!(result.is_ok());
+        let doc = result.unwrap();
+        assert!(doc.content.contains("External Article from /article"));
+        assert_eq!(doc.content_type, Some("text/html".to_string()));
+        assert_eq!(doc.etag, Some("\"v1\"".to_string()));
+        assert_eq!(
+            doc.last_modified,
+            Some("Mon, 01 Jan 2024 00:00:00 GMT".to_string())
+        );
+    }
+
+    #[test]
+    fn test_fetch_with_headers() {
+        let server = article_server(&["/data"]);
+        let fetcher = local_fetcher();
+        let headers = vec![
+            ("Authorization".to_string(), "Bearer token123".to_string()),
+            ("Accept".to_string(), "application/json".to_string()),
+        ];
+
+        let result = fetcher.fetch_with_headers(&server.url("/data"), &headers);
+        assert!(result.is_ok());
+
+        let requests = server.requests();
+        assert_eq!(requests.len(), 1);
+        assert_eq!(requests[0].method, "GET");
+        assert_eq!(requests[0].header("authorization"), Some("Bearer token123"));
+        assert_eq!(requests[0].header("accept"), Some("application/json"));
+    }
+
+    #[test]
+    fn test_fetch_sends_config_headers_and_user_agent() {
+        let server = article_server(&["/article"]);
+        let config = CrossDocumentConfig {
+            default_headers: [("X-Api-Key".to_string(), "secret".to_string())]
+                .into_iter()
+                .collect(),
+            user_agent: "test-agent/1.0".to_string(),
+            ..local_config()
+        };
+        let fetcher = DocumentFetcher::with_config(config);
+
+        fetcher.fetch(&server.url("/article")).unwrap();
+
+        let request = &server.requests()[0];
+        assert_eq!(request.header("x-api-key"), Some("secret"));
+        assert_eq!(request.header("user-agent"), Some("test-agent/1.0"));
+    }
+
+    #[test]
+    fn test_fetch_status_error() {
+        let server = MockHttpServer::start();
+        let url = server.url("/missing");
+        let fetcher = local_fetcher();
+
+        let err = fetcher.fetch(&url).unwrap_err();
+        assert!(matches!(err, Error::HttpError(_)));
+        let message = err.to_string();
+        assert!(message.contains(&url));
+        assert!(message.contains("404"));
+    }
+
+    #[test]
+    fn test_fetch_follows_redirects() {
+        let server = article_server(&["/target"]);
+        server.route(
+            "/old",
+            MockResponse::status(302).with_header("Location", "/target"),
+        );
+
+        let fetcher = local_fetcher();
+        let doc = fetcher.fetch(&server.url("/old")).unwrap();
+        assert!(doc.content.contains("External Article from /target"));
+    }
+
+    #[test]
+    fn test_fetch_redirects_disabled() {
+        let server = article_server(&["/target"]);
+        server.route(
+            "/old",
+            MockResponse::status(302).with_header("Location", "/target"),
+        );
+
+        let config = CrossDocumentConfig {
+            follow_redirects: false,
+            ..local_config()
+        };
+        let fetcher = DocumentFetcher::with_config(config);
+
+        let err = fetcher.fetch(&server.url("/old")).unwrap_err();
+        assert!(err.to_string().contains("302"));
+        assert_eq!(server.request_count("/target"), 0);
+    }
+
+    #[test]
+    fn test_fetch_redirect_limit() {
+        let server = MockHttpServer::start();
+        server.route(
+            "/a",
+            MockResponse::status(302).with_header("Location", "/b"),
+        );
+        server.route(
+            "/b",
+            MockResponse::status(302).with_header("Location", "/a"),
+        );
+
+        let config = CrossDocumentConfig {
+            max_redirects: 2,
+            ..local_config()
+        };
+        let fetcher = DocumentFetcher::with_config(config);
+
+        let err = fetcher.fetch(&server.url("/a")).unwrap_err();
+        assert!(matches!(err, Error::HttpError(_)));
+        assert!(err.to_string().contains("redirect limit of 2"));
+    }
+
+    #[test]
+    fn test_fetch_timeout() {
+        let server = MockHttpServer::start();
+        server.route(
+            "/slow",
+            MockResponse::html("<div></div>").with_delay(Duration::from_millis(500)),
+        );
+
+        let config = CrossDocumentConfig {
+            fetch_timeout: Duration::from_millis(100),
+            ..local_config()
+        };
+        let fetcher = DocumentFetcher::with_config(config);
+
+        let err = fetcher.fetch(&server.url("/slow")).unwrap_err();
+        assert!(matches!(err, Error::HttpError(_)));
+        assert!(err.to_string().contains("timed out"));
+    }
+
+    #[test]
+    fn test_invalid_default_header() {
+        let config = CrossDocumentConfig {
+            default_headers: [("Bad Header".to_string(), "x".to_string())]
+                .into_iter()
+                .collect(),
+            ..local_config()
+        };
+        let fetcher = DocumentFetcher::with_config(config);
+
+        let err = fetcher.fetch("http://127.0.0.1:9/").unwrap_err();
+        assert!(err.to_string().contains("Invalid default header name"));
+    }
+
+    /// A retry policy with short delays for tests
+    fn quick_retries(max_attempts: usize) -> RetryPolicy {
+        RetryPolicy {
+            max_attempts,
+            initial_backoff: Duration::from_millis(10),
+            max_backoff: Duration::from_millis(50),
+            ..Default::default()
+        }
+    }
+
+    fn retrying_fetcher(retry_policy: RetryPolicy) -> DocumentFetcher {
+        DocumentFetcher::with_config(CrossDocumentConfig {
+            retry_policy,
+            ..local_config()
+        })
+    }
+
+    #[test]
+    fn test_retry_policy_backoff() {
+        let policy = RetryPolicy {
+            initial_backoff: Duration::from_millis(100),
+            backoff_multiplier: 2.0,
+            max_backoff: Duration::from_millis(500),
+            jitter: 0.5,
+            ..Default::default()
+        };
+
+        assert_eq!(policy.backoff(1), Duration::from_millis(100));
+        assert_eq!(policy.backoff(2), Duration::from_millis(200));
+        assert_eq!(policy.backoff(3), Duration::from_millis(400));
+        assert_eq!(policy.backoff(4), Duration::from_millis(500));
+        assert_eq!(policy.backoff(1000), Duration::from_millis(500));
+
+        for attempt in 1..5 {
+            let delay = policy.jittered_backoff(attempt);
+            assert!(delay <= policy.backoff(attempt));
+            assert!(delay >= policy.backoff(attempt) / 2);
+        }
+
+        let exact = RetryPolicy {
+            jitter: 0.0,
+            ..policy
+        };
+        assert_eq!(exact.jittered_backoff(2), Duration::from_millis(200));
+    }
+
+    #[test]
+    fn test_retry_policy_decisions() {
+        let policy = RetryPolicy::default();
+        assert!(policy.retries_status(503));
+        assert!(!policy.retries_status(404));
+        assert!(policy.retries_error(NetworkErrorKind::Timeout));
+        assert!(!policy.retries_error(NetworkErrorKind::Other));
+
+        let elapsed = Duration::ZERO;
+        assert!(policy
+            .retry_delay(1, &Failure::Status(503), elapsed)
+            .is_some());
+        assert!(policy
+            .retry_delay(3, &Failure::Status(503), elapsed)
+            .is_none());
+        assert!(policy
+            .retry_delay(1, &Failure::Status(404), elapsed)
+            .is_none());
+        assert!(policy.retry_delay(1, &Failure::Fatal, elapsed).is_none());
+        assert!(RetryPolicy::none()
+            .retry_delay(1, &Failure::Status(503), elapsed)
+            .is_none());
+
+        let with_deadline = RetryPolicy {
+            deadline: Some(Duration::from_millis(100)),
+            jitter: 0.0,
+            ..Default::default()
+        };
+        assert!(with_deadline
+            .retry_delay(1, &Failure::Status(503), Duration::from_millis(50))
+            .is_none());
+    }
+
+    #[test]
+    fn test_fetch_retries_retryable_status() {
+        let server = MockHttpServer::start();
+        server.route("/flaky", MockResponse::status(503));
+        server.route("/flaky", MockResponse::status(502));
+        server.route("/flaky", MockResponse::html(article_html("/flaky")));
+
+        let fetcher = retrying_fetcher(quick_retries(3));
+        let mut attempts = Vec::new();
+        let doc = fetcher
+            .load_recorded(&server.url("/flaky"), &[], &mut attempts)
+            .unwrap();
+
+        assert!(doc.content.contains("External Article from /flaky"));
+        assert_eq!(server.request_count("/flaky"), 3);
+        let statuses: Vec<_> = attempts.iter().map(|attempt| attempt.status).collect();
+        assert_eq!(statuses, vec![Some(503), Some(502), Some(200)]);
+        let numbers: Vec<_> = attempts.iter().map(|attempt| attempt.number).collect();
+        assert_eq!(numbers, vec![1, 2, 3]);
+        assert!(!attempts[0].succeeded());
+        assert!(attempts[0].backoff.is_some());
+        assert!(attempts[2].succeeded());
+        assert_eq!(attempts[2].backoff, None);
+    }
+
+    #[test]
+    fn test_fetch_does_not_retry_other_statuses() {
+        let server = MockHttpServer::start();
+        let fetcher = retrying_fetcher(quick_retries(3));
+        let mut attempts = Vec::new();
+
+        let err = fetcher
+            .load_recorded(&server.url("/missing"), &[], &mut attempts)
+            .unwrap_err();
+
+        assert!(err.to_string().contains("404"));
+        assert_eq!(server.request_count("/missing"), 1);
+        assert_eq!(attempts.len(), 1);
+        assert_eq!(attempts[0].status, Some(404));
+    }
+
+    #[test]
+    fn test_fetch_gives_up_after_max_attempts() {
+        let server = MockHttpServer::start();
+        server.route("/down", MockResponse::status(500));
+
+        let fetcher = retrying_fetcher(quick_retries(3));
+        let mut attempts = Vec::new();
+        let err = fetcher
+            .load_recorded(&server.url("/down"), &[], &mut attempts)
+            .unwrap_err();
+
+        assert!(matches!(err, Error::HttpError(_)));
+        assert!(err.to_string().contains("500"));
+        assert_eq!(server.request_count("/down"), 3);
+        assert_eq!(attempts.len(), 3);
+        assert_eq!(attempts[2].backoff, None);
+    }
+
+    #[test]
+    fn test_fetch_retries_timeouts() {
+        let server = MockHttpServer::start();
+        server.route(
+            "/slow",
+            MockResponse::html("<div></div>").with_delay(Duration::from_millis(500)),
+        );
+        server.route("/slow", MockResponse::html(article_html("/slow")));
+
+        let fetcher = DocumentFetcher::with_config(CrossDocumentConfig {
+            fetch_timeout: Duration::from_millis(100),
+            retry_policy: quick_retries(2),
+            ..local_config()
+        });
+        let mut attempts = Vec::new();
+        let doc = fetcher
+            .load_recorded(&server.url("/slow"), &[], &mut attempts)
+            .unwrap();
+
+        assert!(doc.content.contains("External Article from /slow"));
+        assert_eq!(attempts.len(), 2);
+        assert_eq!(attempts[0].status, None);
+        assert!(attempts[0].error.as_ref().unwrap().contains("timed out"));
+    }
+
+    #[test]
+    fn test_timeout_error_reports_attempt_timeout() {
+        let server = MockHttpServer::start();
+        server.route(
+            "/slow",
+            MockResponse::html("<div></div>").with_delay(Duration::from_millis(500)),
+        );
+
+        // The deadline cuts the attempt short of the 5s fetch timeout
+        let fetcher = DocumentFetcher::with_config(CrossDocumentConfig {
+            fetch_timeout: Duration::from_secs(5),
+            retry_policy: RetryPolicy {
+                deadline: Some(Duration::from_millis(100)),
+                ..quick_retries(1)
+            },
+            ..local_config()
+        });
+        let mut attempts = Vec::new();
+        let err = fetcher
+            .load_recorded(&server.url("/slow"), &[], &mut attempts)
+            .unwrap_err();
+
+        let message = err.to_string();
+        assert!(message.contains("timed out after"));
+        assert!(message.contains("ms"));
+        assert!(!message.contains("5s"));
+    }
+
+    #[test]
+    fn test_fetch_retry_deadline() {
+        let server = MockHttpServer::start();
+        server.route("/down", MockResponse::status(503));
+
+        let fetcher = retrying_fetcher(RetryPolicy {
+            max_attempts: 10,
+            initial_backoff: Duration::from_millis(40),
+            backoff_multiplier: 1.0,
+            jitter: 0.0,
+            deadline: Some(Duration::from_millis(100)),
+            ..Default::default()
+        });
+        let started = Instant::now();
+        let mut attempts = Vec::new();
+        let err = fetcher
+            .load_recorded(&server.url("/down"), &[], &mut attempts)
+            .unwrap_err();
+
+        assert!(err.to_string().contains("503"));
+        assert!(attempts.len() < 10);
+        assert!(started.elapsed() < Duration::from_secs(1));
+    }
+
+    #[test]
+    fn test_batch_render_records_attempts() {
+        let server = MockHttpServer::start();
+        server.route("/flaky", MockResponse::status(503));
+        server.route("/flaky", MockResponse::html(article_html("/flaky")));
+
+        let renderer = CrossDocumentRenderer::with_fetcher(retrying_fetcher(quick_retries(2)));
+        let requests = vec![CrossDocumentRequest {
+            template: headline_template(),
+            url: server.url("/flaky"),
+            selector: None,
+        }];
+
+        let responses = renderer.batch_render(&requests);
+        let metadata = &responses[0].metadata;
+        assert!(responses[0].result.is_ok());
+        assert_eq!(metadata.attempts.len(), 2);
+        assert!(!metadata.cache_hit);
+        assert_eq!(metadata.content_type.as_deref(), Some("text/html"));
+    }
+
+    fn policy_fetcher(fetch_policy: FetchPolicy) -> DocumentFetcher {
+        DocumentFetcher::with_config(CrossDocumentConfig {
+            fetch_policy,
+            ..Default::default()
+        })
+    }
+
+    #[test]
+    fn test_fetch_policy_blocks_loopback_by_default() {
+        let server = article_server(&["/article"]);
+        let fetcher = DocumentFetcher::new();
+
+        let err = fetcher.fetch(&server.url("/article")).unwrap_err();
+        assert!(matches!(err, Error::PolicyError(_)));
+        assert!(err.to_string().contains("loopback address 127.0.0.1"));
+        assert_eq!(server.request_count("/article"), 0);
+
+        // Host names are checked as the connection resolves them
+        let err = fetcher.fetch("http://localhost:9/").unwrap_err();
+        assert!(matches!(err, Error::PolicyError(_)));
+        assert!(err
+            .to_string()
+            .contains("host 'localhost' resolves to loopback"));
+    }
+
+    #[test]
+    fn test_fetch_policy_hosts() {
+        let policy = FetchPolicy {
+            allowed_hosts: vec!["*.example.com".to_string(), "example.org".to_string()],
+            denied_hosts: vec!["internal.example.com".to_string()],
+            ..Default::default()
+        };
+
+        assert!(policy.allows_host("www.example.com"));
+        assert!(policy.allows_host("A.B.Example.COM."));
+        assert!(!policy.allows_host("example.com"));
+        assert!(!policy.allows_host("badexample.com"));
+        assert!(!policy.allows_host("internal.example.com"));
+        assert!(policy.allows_host("example.org"));
+        assert!(!policy.allows_host("www.example.org"));
+
+        let err = policy_fetcher(policy)
+            .fetch("https://evil.test/page")
+            .unwrap_err();
+        assert!(matches!(err, Error::PolicyError(_)));
+        assert!(err.to_string().contains("host 'evil.test' is not allowed"));
+    }
+
+    #[test]
+    fn test_fetch_policy_addresses() {
+        let policy = FetchPolicy::default();
+        let blocked = [
+            "127.0.0.1",
+            "0.0.0.0",
+            "10.1.2.3",
+            "172.16.0.1",
+            "192.168.1.1",
+            "100.64.0.1",
+            "169.254.169.254",
+            "::1",
+            "fd00::1",
+            "fe80::1",
+            "::ffff:127.0.0.1",
+            "0.1.2.3",
+            "64:ff9b::7f00:1",
+            "64:ff9b::a9fe:a9fe",
+            "2002:a00:1::1",
+        ];
+        for ip in blocked {
+            assert!(!policy.allows_address(ip.parse().unwrap()), "{}", ip);
+        }
+        let public = [
+            "93.184.216.34",
+            "172.32.0.1",
+            "2606:4700::1111",
+            "64:ff9b::5db8:d822",
+            "2002:5db8:d822::1",
+        ];
+        for ip in public {
+            assert!(policy.allows_address(ip.parse().unwrap()), "{}", ip);
+        }
+
+        let private = FetchPolicy {
+            allow_private: true,
+            ..Default::default()
+        };
+        assert!(private.allows_address("10.0.0.1".parse().unwrap()));
+        assert!(!private.allows_address("127.0.0.1".parse().unwrap()));
+        assert!(FetchPolicy::permissive().allows_address("169.254.169.254".parse().unwrap()));
+
+        let err = policy_fetcher(policy)
+            .fetch("http://169.254.169.254/latest/meta-data")
+            .unwrap_err();
+        assert!(err.to_string().contains("link-local address"));
+    }
+
+    #[test]
+    fn test_fetch_policy_content_types() {
+        let server = article_server(&["/article"]);
+        let url = server.url("/article");
+
+        let json_only = policy_fetcher(FetchPolicy {
+            allowed_content_types: vec!["application/json".to_string()],
+            ..FetchPolicy::permissive()
+        });
+        let err = json_only.fetch(&url).unwrap_err();
+        assert!(matches!(err, Error::PolicyError(_)));
+        assert!(err.to_string().contains("content type 'text/html'"));
+
+        let any_text = policy_fetcher(FetchPolicy {
+            allowed_content_types: vec!["text/*".to_string()],
+            ..FetchPolicy::permissive()
+        });
+        assert!(any_text.fetch(&url).is_ok());
+
+        let policy = FetchPolicy {
+            allowed_content_types: vec!["text/html".to_string()],
+            ..Default::default()
+        };
+        assert!(policy.allows_content_type(Some("Text/HTML; charset=utf-8")));
+        assert!(!policy.allows_content_type(None));
+    }
+
+    #[test]
+    fn test_fetch_policy_body_size() {
+        let server = article_server(&["/article"]);
+        let fetcher = DocumentFetcher::with_config(CrossDocumentConfig {
+            fetch_policy: FetchPolicy {
+                max_body_size: Some(64),
+                ..FetchPolicy::permissive()
+            },
+            retry_policy: quick_retries(3),
+            ..Default::default()
+        });
 
-        assert!(result.is_ok());
-        let doc = result.unwrap();
-        assert!(doc
-            .content
-            .contains("External Article from https://example.com/article"));
-        assert_eq!(doc.content_type, Some("text/html".to_string()));
-        assert!(doc.etag.is_some());
-        assert!(doc.last_modified.is_some());
+        let err = fetcher.fetch(&server.url("/article")).unwrap_err();
+        assert!(matches!(err, Error::PolicyError(_)));
+        assert!(err.to_string().contains("exceeds 64 bytes"));
+        // Violations are never retried
+        assert_eq!(server.request_count("/article"), 1);
     }
 
     #[test]
-    fn test_fetch_with_headers() {
-        let fetcher = DocumentFetcher::new();
-        let headers = vec![
-            ("Authorization".to_string(), "Bearer token123".to_string()),
-            ("Accept".to_string(), "application/json".to_string()),
-        ];
+    fn test_fetch_policy_checks_redirects() {
+        let server = article_server(&["/target"]);
+        let target = server.url("/target").replace("127.0.0.1", "localhost");
+        server.route(
+            "/old",
+            MockResponse::status(302).with_header("Location", &target),
+        );
 
-        let result = fetcher.fetch_with_headers("https://api.example.com/data", &headers);
-        assert!(result.is_ok());
+        let fetcher = policy_fetcher(FetchPolicy {
+            allowed_hosts: vec!["127.0.0.1".to_string()],
+            ..FetchPolicy::permissive()
+        });
+        let err = fetcher.fetch(&server.url("/old")).unwrap_err();
+        assert!(matches!(err, Error::PolicyError(_)));
+        assert!(err.to_string().contains("redirect to http://localhost:"));
+        assert!(err.to_string().contains("host 'localhost' is not allowed"));
+        assert_eq!(server.request_count("/target"), 0);
     }
 
     #[test]
     fn test_cross_document_renderer() {
-        // Test just the document fetching for now to avoid template issues
-        let renderer = CrossDocumentRenderer::new();
+        let server = article_server(&["/article"]);
+        let renderer = local_renderer();
 
-        // Test fetching a document
-        let cached_doc = renderer.fetcher.fetch("https://example.com/article");
-        assert!(cached_doc.is_ok());
+        let template_html = r#"
+            <template>
+                <div>
+                    <h3 itemprop="headline"></h3>
+                </div>
+            </template>
+        "#;
+        let config = TemplateConfig::no_caching();
+        let template =
+            HtmlTemplate::from_str_with_config(template_html, Some("div"), config).unwrap();
 
-        let doc = cached_doc.unwrap();
-        assert!(doc.content.contains("External Article"));
-        assert_eq!(doc.content_type, Some("text/html".to_string()));
-        assert!(doc.etag.is_some());
-        assert!(doc.last_modified.is_some());
+        let rendered = renderer
+            .render_from_url(&template, &server.url("/article"))
+            .unwrap();
+        assert_eq!(rendered.len(), 1);
+        assert!(rendered[0].contains("External Article from /article"));
     }
 
     #[test]
     fn test_cross_document_renderer_with_selector() {
-        // Test document fetching and parsing without template rendering
-        let renderer = CrossDocumentRenderer::new();
+        let server = article_server(&["/article"]);
+        let renderer = local_renderer();
 
-        let cached_doc = renderer.fetcher.fetch("https://example.com/article");
+        let cached_doc = renderer.source().load(&server.url("/article"), &[]);
         assert!(cached_doc.is_ok());
 
         let doc = cached_doc.unwrap();
         let parsed_doc = Document::from(doc.content.as_ref());
         let selected_elements = parsed_doc.select("[itemscope]");
 
-        // Should find the itemscope elements in our mock data
+        // Should find the itemscope elements in the served document
         assert!(!selected_elements.is_empty());
     }
 
     #[test]
     fn test_cross_document_renderer_multiple_urls() {
-        let renderer = CrossDocumentRenderer::new();
+        let server = article_server(&["/article1", "/article2", "/article3"]);
+        let renderer = local_renderer();
 
         let template_html = r#"
             <template>
@@ -484,11 +3256,12 @@ mod tests {
         let config = TemplateConfig::no_caching();
         let template =
             HtmlTemplate::from_str_with_config(template_html, Some("div"), config).unwrap();
-        let urls = vec![
-            "https://example.com/article1",
-            "https://example.com/article2",
-            "https://example.com/article3",
+        let owned_urls = vec![
+            server.url("/article1"),
+            server.url("/article2"),
+            server.url("/article3"),
         ];
+        let urls: Vec<&str> = owned_urls.iter().map(String::as_str).collect();
 
         let results = renderer.render_from_multiple_urls(&template, &urls);
         assert!(results.is_ok());
@@ -499,7 +3272,8 @@ mod tests {
 
     #[test]
     fn test_batch_render() {
-        let renderer = CrossDocumentRenderer::new();
+        let server = article_server(&["/article1", "/article2"]);
+        let renderer = local_renderer();
 
         let template_html = r#"
             <template>
@@ -514,12 +3288,12 @@ mod tests {
         let requests = vec![
             CrossDocumentRequest {
                 template: template.clone(),
-                url: "https://example.com/article1".to_string(),
+                url: server.url("/article1"),
                 selector: None,
             },
             CrossDocumentRequest {
                 template: template.clone(),
-                url: "https://example.com/article2".to_string(),
+                url: server.url("/article2"),
                 selector: Some("[itemscope]".to_string()),
             },
         ];
@@ -535,6 +3309,7 @@ mod tests {
 
     #[test]
     fn test_cross_document_template() {
+        let server = article_server(&["/article"]);
         let template_html = r#"
             <template>
                 <article>
@@ -547,11 +3322,12 @@ mod tests {
         let config = TemplateConfig::no_caching();
         let template =
             HtmlTemplate::from_str_with_config(template_html, Some("article"), config).unwrap();
-        let mut cross_doc_template = CrossDocumentTemplate::new(template);
+        let mut cross_doc_template =
+            CrossDocumentTemplate::with_renderer(template, local_renderer());
 
         // Add external data source
         cross_doc_template.add_data_source(DataSource::Url {
-            url: "https://example.com/article".to_string(),
+            url: server.url("/article"),
             selector: None,
         });
 
@@ -579,9 +3355,806 @@ mod tests {
         assert!(rendered.iter().any(|r| r.contains("External Article")));
     }
 
+    fn short_ttl_cache() -> TemplateCache {
+        TemplateCache::with_config(crate::cache::CacheConfig {
+            document_ttl: Some(Duration::from_millis(50)),
+            ..Default::default()
+        })
+    }
+
     #[test]
-    fn test_unsupported_url_scheme() {
+    fn test_fetch_offline_from_persisted_cache() {
+        let dir =
+            std::env::temp_dir().join(format!("html-template-documents-{}", std::process::id()));
+        let disk_cache = |offline| {
+            TemplateCache::with_config(crate::cache::CacheConfig {
+                document_ttl: Some(Duration::from_millis(1)),
+                document_dir: Some(dir.clone()),
+                offline,
+                ..Default::default()
+            })
+        };
+
+        let server = article_server(&["/article"]);
+        let url = server.url("/article");
+        let warm = local_fetcher().with_cache(disk_cache(false));
+        let fetched = warm.fetch(&url).unwrap();
+        drop(server);
+
+        std::thread::sleep(Duration::from_millis(5));
+        let offline = local_fetcher().with_cache(disk_cache(true));
+        let cached = offline.fetch(&url).unwrap();
+        assert_eq!(cached.content, fetched.content);
+        assert_eq!(cached.etag.as_deref(), Some("\"v1\""));
+
+        std::fs::remove_dir_all(&dir).ok();
+    }
+
+    #[test]
+    fn test_fetch_revalidates_with_etag() {
+        let server = article_server(&["/article"]);
+        server.route("/article", MockResponse::status(304));
+        let fetcher = local_fetcher().with_cache(short_ttl_cache());
+        let url = server.url("/article");
+
+        let first = fetcher.fetch(&url).unwrap();
+        // Served from cache while fresh
+        fetcher.fetch(&url).unwrap();
+        assert_eq!(server.request_count("/article"), 1);
+
+        std::thread::sleep(Duration::from_millis(100));
+
+        let revalidated = fetcher.fetch(&url).unwrap();
+        assert_eq!(revalidated.content, first.content);
+
+        let requests = server.requests();
+        assert_eq!(requests.len(), 2);
+        assert_eq!(requests[1].header("if-none-match"), Some("\"v1\""));
+        assert_eq!(
+            requests[1].header("if-modified-since"),
+            Some("Mon, 01 Jan 2024 00:00:00 GMT")
+        );
+
+        // The refreshed entry is fresh again
+        fetcher.fetch(&url).unwrap();
+        assert_eq!(server.request_count("/article"), 2);
+
+        let stats = fetcher.cache().unwrap().get_stats().external_documents;
+        assert_eq!(stats.hits, 2);
+        assert_eq!(stats.misses, 1);
+        assert_eq!(stats.revalidations, 1);
+    }
+
+    #[test]
+    fn test_fetch_revalidation_replaces_changed_document() {
+        let server = MockHttpServer::start();
+        server.route(
+            "/page",
+            MockResponse::html("<p>old</p>")
+                .with_header("Last-Modified", "Mon, 01 Jan 2024 00:00:00 GMT"),
+        );
+        server.route("/page", MockResponse::html("<p>new</p>"));
+        let fetcher = local_fetcher().with_cache(short_ttl_cache());
+        let url = server.url("/page");
+
+        assert_eq!(fetcher.fetch(&url).unwrap().content, "<p>old</p>");
+        std::thread::sleep(Duration::from_millis(100));
+
+        assert_eq!(fetcher.fetch(&url).unwrap().content, "<p>new</p>");
+        assert_eq!(
+            server.requests()[1].header("if-modified-since"),
+            Some("Mon, 01 Jan 2024 00:00:00 GMT")
+        );
+        assert_eq!(server.requests()[1].header("if-none-match"), None);
+
+        let stats = fetcher.cache().unwrap().get_stats().external_documents;
+        assert_eq!(stats.misses, 2);
+        assert_eq!(stats.revalidations, 0);
+    }
+
+    fn headline_template() -> HtmlTemplate {
+        let template_html = r#"
+            <template>
+                <div>
+                    <h3 itemprop="headline"></h3>
+                </div>
+            </template>
+        "#;
+        HtmlTemplate::from_str_with_config(template_html, Some("div"), TemplateConfig::no_caching())
+            .unwrap()
+    }
+
+    #[test]
+    fn test_memory_source() {
+        let source = MemorySource::new().with_html("mem://article", article_html("memory"));
+        assert_eq!(source.len(), 1);
+
+        let doc = source.load("mem://article", &[]).unwrap();
+        assert!(doc.content.contains("External Article from memory"));
+        assert_eq!(doc.content_type, Some("text/html".to_string()));
+
+        let err = source.load("mem://missing", &[]).unwrap_err();
+        assert!(err.to_string().contains("mem://missing"));
+    }
+
+    #[test]
+    fn test_file_source_with_root() {
+        let root = std::env::temp_dir().join(format!("html-template-files-{}", std::process::id()));
+        std::fs::create_dir_all(root.join("bucket")).unwrap();
+        std::fs::write(root.join("bucket/page.html"), article_html("disk")).unwrap();
+
+        let source = FileSource::with_root(&root);
+        let doc = source.load("s3-local://bucket/page.html", &[]).unwrap();
+        assert!(doc.content.contains("External Article from disk"));
+
+        let err = source
+            .load("s3-local://bucket/missing.html", &[])
+            .unwrap_err();
+        assert!(matches!(err, Error::IoError(_)));
+
+        std::fs::remove_dir_all(&root).unwrap();
+    }
+
+    #[test]
+    fn test_file_source_rejects_traversal() {
+        let base = std::env::temp_dir().join(format!("html-template-jail-{}", std::process::id()));
+        let root = base.join("root");
+        std::fs::create_dir_all(root.join("docs")).unwrap();
+        std::fs::write(base.join("secret.html"), article_html("secret")).unwrap();
+        std::fs::write(root.join("docs/page.html"), article_html("inside")).unwrap();
+
+        let source = FileSource::with_root(&root);
+        for url in [
+            "file:///../secret.html",
+            "file://docs/../../secret.html",
+            "file:///%2e%2e/secret.html",
+            "file:///docs/%2E%2e/%2e%2E/secret.html",
+            "file:///docs\\..\\..\\secret.html",
+        ] {
+            let err = source.load(url, &[]).unwrap_err();
+            assert!(matches!(err, Error::PolicyError(_)), "{}", url);
+        }
+
+        // Dots inside names and `.` segments stay within the root
+        let doc = source.load("file:///./docs/page.html", &[]).unwrap();
+        assert!(doc.content.contains("External Article from inside"));
+
+        std::fs::remove_dir_all(&base).unwrap();
+    }
+
+    #[test]
+    fn test_default_fetcher_refuses_local_files() {
         let fetcher = DocumentFetcher::new();
+        let err = fetcher.fetch("file:///etc/passwd").unwrap_err();
+        assert!(
+            err.to_string().contains("Unsupported URL scheme"),
+            "{}",
+            err
+        );
+
+        let template =
+            HtmlTemplate::from_str(r#"<template><p itemprop="name"></p></template>"#, Some("p"))
+                .unwrap();
+        assert!(CrossDocumentRenderer::new()
+            .render_from_url(&template, "file:///etc/passwd")
+            .is_err());
+
+        // Local files are read once a source is registered for them
+        let root = std::env::temp_dir().join(format!("html-template-optin-{}", std::process::id()));
+        std::fs::create_dir_all(&root).unwrap();
+        std::fs::write(root.join("page.html"), article_html("opt-in")).unwrap();
+        let mut fetcher = DocumentFetcher::new();
+        fetcher.register_source("file", FileSource::with_root(&root));
+        let doc = fetcher.fetch("file:///page.html").unwrap();
+        assert!(doc.content.contains("External Article from opt-in"));
+
+        std::fs::remove_dir_all(&root).unwrap();
+    }
+
+    #[test]
+    fn test_scheme_router() {
+        let router = SchemeRouter::new().with_source(
+            "cms",
+            MemorySource::new().with_html("cms://home", article_html("cms")),
+        );
+        assert!(router.handles("CMS"));
+        assert!(!router.handles("http"));
+
+        let doc = router.load("cms://home", &[]).unwrap();
+        assert!(doc.content.contains("External Article from cms"));
+
+        let err = router.load("http://example.com/", &[]).unwrap_err();
+        assert!(err.to_string().contains("Unsupported URL scheme"));
+    }
+
+    #[test]
+    fn test_fetcher_custom_scheme() {
+        let mut fetcher = local_fetcher();
+        fetcher.register_source(
+            "cms",
+            MemorySource::new().with_html("cms://home", article_html("cms")),
+        );
+
+        let doc = fetcher.fetch("cms://home").unwrap();
+        assert!(doc.content.contains("External Article from cms"));
+    }
+
+    #[test]
+    fn test_renderer_with_source() {
+        let source = MemorySource::new()
+            .with_html("mem://one", article_html("one"))
+            .with_html("mem://two", article_html("two"));
+        let renderer = CrossDocumentRenderer::with_source(source);
+
+        let rendered = renderer
+            .render_from_multiple_urls(&headline_template(), &["mem://one", "mem://two"])
+            .unwrap();
+        assert_eq!(rendered.len(), 2);
+        assert!(rendered[0].contains("External Article from one"));
+        assert!(rendered[1].contains("External Article from two"));
+    }
+
+    #[test]
+    fn test_cross_document_template_with_source() {
+        let source = Arc::new(MemorySource::new().with_html("mem://article", article_html("mem")));
+        let mut cross_doc_template =
+            CrossDocumentTemplate::with_source(headline_template(), source.clone());
+        cross_doc_template.add_data_source(DataSource::Url {
+            url: "mem://article".to_string(),
+            selector: Some("article".to_string()),
+        });
+
+        let rendered = cross_doc_template.render().unwrap();
+        assert_eq!(rendered.len(), 1);
+        assert!(rendered[0].contains("External Article from mem"));
+    }
+
+    fn static_source(data: JsonValue) -> DataSource {
+        DataSource::Static { data }
+    }
+
+    fn merge_template(sources: Vec<DataSource>, policy: ConflictPolicy) -> CrossDocumentTemplate {
+        let mut template =
+            CrossDocumentTemplate::with_source(headline_template(), MemorySource::new())
+                .with_conflict_policy(policy);
+        for source in sources {
+            template.add_data_source(source);
+        }
+        template
+    }
+
+    #[test]
+    fn test_merged_data_deep_merges_and_mounts() {
+        let template = merge_template(
+            vec![
+                static_source(json!({"site": {"name": "Shop", "theme": "dark"}})),
+                static_source(json!({"site": {"locale": "en"}})),
+                static_source(json!(["a", "b"])).mount_at("nav.links"),
+                static_source(json!(42)).mount_at("stats.count"),
+            ],
+            ConflictPolicy::Error,
+        );
+
+        assert_eq!(
+            template.merged_data().unwrap(),
+            json!({
+                "site": {"name": "Shop", "theme": "dark", "locale": "en"},
+                "nav": {"links": ["a", "b"]},
+                "stats": {"count": 42}
+            })
+        );
+    }
+
+    #[test]
+    fn test_merged_data_conflict_policies() {
+        let sources = || {
+            vec![
+                static_source(json!({"title": "First", "meta": {"lang": "en"}})),
+                static_source(json!({"title": "Second", "meta": {"lang": "en"}})),
+            ]
+        };
+
+        let merged = merge_template(sources(), ConflictPolicy::Overwrite)
+            .merged_data()
+            .unwrap();
+        assert_eq!(merged["title"], "Second");
+
+        let merged = merge_template(sources(), ConflictPolicy::KeepFirst)
+            .merged_data()
+            .unwrap();
+        assert_eq!(merged["title"], "First");
+
+        let err = merge_template(sources(), ConflictPolicy::Error)
+            .merged_data()
+            .unwrap_err();
+        assert!(matches!(err, Error::RenderError(_)));
+        assert!(err.to_string().contains("'title'"));
+
+        // Mounting beneath a scalar is a conflict too
+        let err = merge_template(
+            vec![
+                static_source(json!({"meta": "flat"})),
+                static_source(json!("en")).mount_at("meta.lang"),
+            ],
+            ConflictPolicy::Error,
+        )
+        .merged_data()
+        .unwrap_err();
+        assert!(err.to_string().contains("'meta'"));
+
+        // An empty object is data like any other value
+        for sources in [
+            vec![
+                static_source(json!({"meta": {}})),
+                static_source(json!({"meta": "flat"})),
+            ],
+            vec![
+                static_source(json!({})).mount_at("meta"),
+                static_source(json!("flat")).mount_at("meta"),
+            ],
+        ] {
+            let err = merge_template(sources, ConflictPolicy::Error)
+                .merged_data()
+                .unwrap_err();
+            assert!(err.to_string().contains("'meta'"));
+        }
+    }
+
+    #[test]
+    fn test_merged_data_requires_objects_at_root() {
+        let err = merge_template(
+            vec![static_source(json!([1, 2]))],
+            ConflictPolicy::Overwrite,
+        )
+        .merged_data()
+        .unwrap_err();
+        assert!(err.to_string().contains("mount other data at a path"));
+    }
+
+    #[test]
+    fn test_render_merged_from_urls() {
+        let source = MemorySource::new()
+            .with_html("mem://article", article_html("mem"))
+            .with_html(
+                "mem://comments",
+                r#"<div itemscope><span itemprop="text">Great</span></div>
+                   <div itemscope><span itemprop="text">Thanks</span></div>"#,
+            );
+        let template_html = r#"
+            <template>
+                <main>
+                    <h1 itemprop="siteName"></h1>
+                    <article itemprop="article[]" itemscope>
+                        <h2 itemprop="headline"></h2>
+                    </article>
+                    <p itemprop="comments[]"><span itemprop="text"></span></p>
+                </main>
+            </template>
+        "#;
+        let template = HtmlTemplate::from_str_with_config(
+            template_html,
+            Some("main"),
+            TemplateConfig::no_caching(),
+        )
+        .unwrap();
+
+        let mut page = CrossDocumentTemplate::with_source(template, source);
+        page.add_data_source(DataSource::url("mem://article").mount_at("article"));
+        page.add_data_source(DataSource::url("mem://comments").mount_at("comments"));
+        page.add_data_source(static_source(json!({"siteName": "Example Site"})));
+
+        // A URL source is an array even when its document has one item
+        let data = page.merged_data().unwrap();
+        assert_eq!(data["article"].as_array().map(Vec::len), Some(1));
+        assert_eq!(data["comments"].as_array().map(Vec::len), Some(2));
+
+        let html = page.render_merged().unwrap();
+        assert!(html.contains("Example Site"));
+        assert!(html.contains("External Article from mem"));
+        assert!(html.contains("Great"));
+        assert!(html.contains("Thanks"));
+
+        // Mounted sources still render one fragment per item without merging
+        assert_eq!(page.render().unwrap().len(), 4);
+    }
+
+    /// A post linking to its author, who links to an employer and back to the post
+    fn linked_pages() -> MemorySource {
+        MemorySource::new()
+            .with_html(
+                "https://example.com/post",
+                r#"<article itemscope itemtype="https://schema.org/BlogPosting">
+                    <h1 itemprop="headline">Linked Data</h1>
+                    <a itemprop="author" href="/people/ada">Ada</a>
+                    <a itemprop="publisher" href="/org">Example Org</a>
+                    <img itemprop="image" src="/cover.jpg">
+                </article>"#,
+            )
+            .with_html(
+                "https://example.com/people/ada",
+                r#"<div itemscope itemtype="https://schema.org/Person" itemid="https://example.com/people/ada">
+                    <span itemprop="name">Ada Lovelace</span>
+                    <a itemprop="worksFor" href="/org">Example Org</a>
+                    <a itemprop="subjectOf" href="/post">Linked Data</a>
+                </div>"#,
+            )
+            .with_html(
+                "https://example.com/org",
+                r#"<div itemscope itemtype="https://schema.org/Organization">
+                    <span itemprop="name">Example Org</span>
+                </div>"#,
+            )
+    }
+
+    fn linked_post(options: LinkFollowing) -> JsonValue {
+        CrossDocumentRenderer::with_source(linked_pages())
+            .with_link_following(options)
+            .extract_from_url("https://example.com/post", None)
+            .unwrap()
+            .remove(0)
+    }
+
+    #[test]
+    fn test_link_following_replaces_linked_items() {
+        let post = linked_post(LinkFollowing {
+            max_depth: 3,
+            ..Default::default()
+        });
+
+        assert_eq!(post["author"]["name"], "Ada Lovelace");
+        assert_eq!(post["author"]["worksFor"]["name"], "Example Org");
+        assert_eq!(post["publisher"]["name"], "Example Org");
+        // Only links are followed, not embedded resources
+        assert_eq!(post["image"], "https://example.com/cover.jpg");
+        // Linking back to the rendered page is a cycle
+        assert_eq!(post["author"]["subjectOf"], "https://example.com/post");
+    }
+
+    #[test]
+    fn test_link_following_limits() {
+        let shallow = linked_post(LinkFollowing::default());
+        assert_eq!(shallow["author"]["name"], "Ada Lovelace");
+        assert_eq!(shallow["author"]["worksFor"], "https://example.com/org");
+
+        let author_only = linked_post(LinkFollowing {
+            properties: vec!["author".to_string()],
+            ..Default::default()
+        });
+        assert_eq!(author_only["author"]["name"], "Ada Lovelace");
+        assert_eq!(author_only["publisher"], "https://example.com/org");
+
+        // With a budget of one fetch, only the first link is dereferenced
+        let budgeted = linked_post(LinkFollowing {
+            max_depth: 3,
+            max_fetches: 1,
+            ..Default::default()
+        });
+        assert_eq!(budgeted["author"]["name"], "Ada Lovelace");
+        assert_eq!(budgeted["author"]["worksFor"], "https://example.com/org");
+        assert_eq!(budgeted["publisher"], "https://example.com/org");
+
+        let disabled = CrossDocumentRenderer::with_source(linked_pages())
+            .extract_from_url("https://example.com/post", None)
+            .unwrap();
+        assert_eq!(disabled[0]["author"], "https://example.com/people/ada");
+    }
+
+    #[test]
+    fn test_link_following_detects_itemid_cycles() {
+        let source = MemorySource::new()
+            .with_html(
+                "https://example.com/a",
+                r#"<div itemscope itemid="urn:item:1">
+                    <span itemprop="name">A</span>
+                    <a itemprop="sameAs" href="/b">B</a>
+                </div>"#,
+            )
+            .with_html(
+                "https://example.com/b",
+                r#"<div itemscope itemid="urn:item:1"><span itemprop="name">B</span></div>"#,
+            );
+
+        let items = CrossDocumentRenderer::with_source(source)
+            .with_link_following(LinkFollowing::default())
+            .extract_from_url("https://example.com/a", None)
+            .unwrap();
+        assert_eq!(items[0]["sameAs"], "https://example.com/b");
+    }
+
+    fn catalog_page() -> MemorySource {
+        MemorySource::new().with_html(
+            "https://example.com/catalog",
+            r##"<div itemscope itemtype="https://schema.org/WebPage">
+                <h1 itemprop="headline">Catalog</h1>
+            </div>
+            <div id="product-42" itemscope itemtype="https://schema.org/Product">
+                <h3 itemprop="headline">Desk</h3>
+            </div>
+            <div itemscope itemtype="https://schema.org/Product" itemid="#product-7">
+                <h3 itemprop="headline">Lamp</h3>
+            </div>
+            <div itemscope itemtype="https://schema.org/Person">
+                <a itemprop="owns" href="/catalog#product-7">Lamp</a>
+            </div>"##,
+        )
+    }
+
+    #[test]
+    fn test_render_item_selected_by_fragment() {
+        let renderer = CrossDocumentRenderer::with_source(catalog_page());
+
+        let rendered = renderer
+            .render_from_url(
+                &headline_template(),
+                "https://example.com/catalog#product-42",
+            )
+            .unwrap();
+        assert_eq!(rendered.len(), 1);
+        assert!(rendered[0].contains("Desk"));
+
+        let items = renderer
+            .extract_from_url("https://example.com/catalog#product-7", None)
+            .unwrap();
+        assert_eq!(items.len(), 1);
+        assert_eq!(items[0]["headline"], "Lamp");
+
+        let error = renderer
+            .extract_from_url("https://example.com/catalog#product-1", None)
+            .unwrap_err();
+        assert!(error
+            .to_string()
+            .contains("No item with id or itemid 'product-1'"));
+
+        // A selector takes precedence over the fragment
+        let items = renderer
+            .extract_from_url("https://example.com/catalog#product-42", Some("[itemid]"))
+            .unwrap();
+        assert_eq!(items[0]["headline"], "Lamp");
+    }
+
+    #[test]
+    fn test_render_items_filtered_by_type() {
+        let renderer = CrossDocumentRenderer::with_source(catalog_page());
+
+        let rendered = renderer
+            .render_from_url_with_type(
+                &headline_template(),
+                "https://example.com/catalog",
+                "schema.org/Product",
+            )
+            .unwrap();
+        assert_eq!(rendered.len(), 2);
+        assert!(rendered[0].contains("Desk"));
+        assert!(rendered[1].contains("Lamp"));
+
+        let people = renderer
+            .extract_from_url_with_type("https://example.com/catalog", "https://schema.org/Person")
+            .unwrap();
+        assert_eq!(people.len(), 1);
+        assert!(renderer
+            .extract_from_url_with_type("https://example.com/catalog", "schema.org/Event")
+            .unwrap()
+            .is_empty());
+    }
+
+    #[test]
+    fn test_link_following_selects_fragment_item() {
+        let people = CrossDocumentRenderer::with_source(catalog_page())
+            .with_link_following(LinkFollowing::default())
+            .extract_from_url_with_type("https://example.com/catalog", "schema.org/Person")
+            .unwrap();
+
+        // Linking into the rendered document itself is a cycle
+        assert_eq!(people[0]["owns"], "https://example.com/catalog#product-7");
+
+        let source = catalog_page().with_html(
+            "https://example.com/people/ada",
+            r#"<div itemscope><a itemprop="owns" href="/catalog#product-7">Lamp</a></div>"#,
+        );
+        let ada = CrossDocumentRenderer::with_source(source)
+            .with_link_following(LinkFollowing::default())
+            .extract_from_url("https://example.com/people/ada", None)
+            .unwrap();
+        assert_eq!(ada[0]["owns"]["headline"], "Lamp");
+    }
+
+    #[test]
+    fn test_link_following_stays_on_the_linking_scheme() {
+        let web = MemorySource::new()
+            .with_html(
+                "https://example.com/people/ada",
+                r#"<div itemscope>
+                <a itemprop="friend" href="file:///tmp/secret.html">Secret</a>
+                <a itemprop="colleague" href="http://example.com/people/grace">Grace</a>
+            </div>"#,
+            )
+            .with_html(
+                "http://example.com/people/grace",
+                r#"<div itemscope><span itemprop="name">Grace Hopper</span></div>"#,
+            );
+        let local = MemorySource::new().with_html(
+            "file:///tmp/secret.html",
+            r#"<div itemscope><span itemprop="name">Secret</span></div>"#,
+        );
+        let router = SchemeRouter::new()
+            .with_source("https", web.clone())
+            .with_source("http", web)
+            .with_source("file", local);
+
+        let ada = CrossDocumentRenderer::with_source(router)
+            .with_link_following(LinkFollowing::default())
+            .extract_from_url("https://example.com/people/ada", None)
+            .unwrap();
+        assert_eq!(ada[0]["friend"], "file:///tmp/secret.html");
+        assert_eq!(ada[0]["colleague"]["name"], "Grace Hopper");
+    }
+
+    #[test]
+    fn test_render_with_link_following() {
+        let template_html = r#"
+            <template>
+                <article>
+                    <h1 itemprop="headline" title="${author.name}"></h1>
+                    <div itemprop="author" itemscope>
+                        <span itemprop="name"></span>
+                    </div>
+                </article>
+            </template>
+        "#;
+        let template = HtmlTemplate::from_str_with_config(
+            template_html,
+            Some("article"),
+            TemplateConfig::no_caching(),
+        )
+        .unwrap();
+
+        let renderer = CrossDocumentRenderer::with_source(linked_pages())
+            .with_link_following(LinkFollowing::default());
+        let rendered = renderer
+            .render_from_url(&template, "https://example.com/post")
+            .unwrap();
+
+        assert!(rendered[0].contains("<span itemprop=\"name\">Ada Lovelace</span>"));
+        assert!(rendered[0].contains(r#"title="Ada Lovelace""#));
+    }
+
+    #[cfg(feature = "async")]
+    #[tokio::test]
+    async fn test_async_render_preserves_order_with_bounded_concurrency() {
+        let server = MockHttpServer::start();
+        let paths = ["/a", "/b", "/c", "/d", "/e"];
+        for (i, path) in paths.iter().enumerate() {
+            // Earlier URLs respond more slowly so completion order is reversed
+            let delay = Duration::from_millis(150 - 30 * i as u64);
+            server.route(
+                path,
+                MockResponse::html(article_html(path)).with_delay(delay),
+            );
+        }
+
+        let config = CrossDocumentConfig {
+            max_concurrent_fetches: 2,
+            ..local_config()
+        };
+        let renderer = AsyncCrossDocumentRenderer::with_config(config);
+        let owned_urls: Vec<String> = paths.iter().map(|path| server.url(path)).collect();
+        let urls: Vec<&str> = owned_urls.iter().map(String::as_str).collect();
+
+        let responses = renderer.render_from_urls(&headline_template(), &urls).await;
+
+        assert_eq!(responses.len(), paths.len());
+        for (response, path) in responses.iter().zip(paths) {
+            assert_eq!(response.url, server.url(path));
+            let rendered = response.result.as_ref().unwrap();
+            assert!(rendered[0].contains(&format!("External Article from {}", path)));
+        }
+        assert_eq!(server.max_concurrent_requests(), 2);
+        // Fetch times record when each fetch finished, not the output order
+        assert!(responses[1].metadata.fetch_time < responses[0].metadata.fetch_time);
+    }
+
+    #[cfg(feature = "async")]
+    #[tokio::test]
+    async fn test_async_render_metadata() {
+        let server = article_server(&["/article"]);
+        let renderer = AsyncCrossDocumentRenderer::with_config(local_config())
+            .with_cache(TemplateCache::new());
+        let url = server.url("/article");
+        let missing = server.url("/missing");
+        let template = headline_template();
+
+        let first = renderer
+            .render_from_urls(&template, &[&url, &missing])
+            .await;
+        assert!(!first[0].metadata.cache_hit);
+        assert_eq!(first[0].metadata.content_type.as_deref(), Some("text/html"));
+        assert!(first[1]
+            .result
+            .as_ref()
+            .unwrap_err()
+            .to_string()
+            .contains("404"));
+        assert!(!first[1].metadata.cache_hit);
+        assert_eq!(first[1].metadata.content_type, None);
+
+        let second = renderer.render_from_urls(&template, &[&url]).await;
+        assert!(second[0].metadata.cache_hit);
+        assert!(second[0].result.is_ok());
+        assert_eq!(server.request_count("/article"), 1);
+    }
+
+    #[cfg(feature = "async")]
+    #[tokio::test]
+    async fn test_async_render_retries() {
+        let server = MockHttpServer::start();
+        server.route("/flaky", MockResponse::status(429));
+        server.route("/flaky", MockResponse::html(article_html("/flaky")));
+
+        let renderer = AsyncCrossDocumentRenderer::with_config(CrossDocumentConfig {
+            retry_policy: quick_retries(3),
+            ..local_config()
+        });
+        let url = server.url("/flaky");
+
+        let responses = renderer
+            .render_from_urls(&headline_template(), &[&url])
+            .await;
+
+        assert!(responses[0].result.is_ok());
+        let statuses: Vec<_> = responses[0]
+            .metadata
+            .attempts
+            .iter()
+            .map(|attempt| attempt.status)
+            .collect();
+        assert_eq!(statuses, vec![Some(429), Some(200)]);
+    }
+
+    #[cfg(feature = "async")]
+    #[tokio::test]
+    async fn test_async_render_applies_fetch_policy() {
+        let server = article_server(&["/article"]);
+        let renderer = AsyncCrossDocumentRenderer::new();
+        let url = server.url("/article");
+
+        let by_name = url.replace("127.0.0.1", "localhost");
+
+        let responses = renderer
+            .render_from_urls(&headline_template(), &[&url, &by_name])
+            .await;
+
+        assert!(matches!(responses[0].result, Err(Error::PolicyError(_))));
+        assert!(matches!(responses[1].result, Err(Error::PolicyError(_))));
+        assert_eq!(server.request_count("/article"), 0);
+    }
+
+    #[cfg(feature = "async")]
+    #[tokio::test]
+    async fn test_async_batch_render_with_custom_source() {
+        let mut renderer = AsyncCrossDocumentRenderer::with_config(local_config());
+        renderer.register_source(
+            "mem",
+            MemorySource::new().with_html("mem://article", article_html("mem")),
+        );
+
+        let requests = vec![CrossDocumentRequest {
+            template: headline_template(),
+            url: "mem://article".to_string(),
+            selector: Some("article".to_string()),
+        }];
+
+        let responses = renderer.batch_render(&requests).await;
+        let rendered = responses[0].result.as_ref().unwrap();
+        assert!(rendered[0].contains("External Article from mem"));
+        assert_eq!(
+            responses[0].metadata.content_type.as_deref(),
+            Some("text/html")
+        );
+    }
+
+    #[test]
+    fn test_unsupported_url_scheme() {
+        let fetcher = local_fetcher();
         let result = fetcher.fetch("ftp://example.com/file.html");
 
         assert!(result.is_err());
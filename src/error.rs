@@ -13,6 +13,8 @@
 //! - [`Error::DomError`] - DOM manipulation and traversal errors
 //! - [`Error::JsonError`] - JSON parsing and serialization errors
 //! - [`Error::HttpError`] - HTTP client and networking errors
+//! - [`Error::PolicyError`] - Fetches refused by a fetch policy
+//! - [`Error::MissingDataError`] - Bound paths the data lacks in strict rendering
 //! - [`Error::IoError`] - File system and I/O errors
 //!
 //! # Usage
@@ -39,6 +41,8 @@
 use std::borrow::Cow;
 use thiserror::Error;
 
+use crate::program::MissingPath;
+
 /// Comprehensive error type for all html-template operations
 ///
 /// This enum covers all possible error conditions that can occur during
@@ -108,6 +112,21 @@ pub enum Error {
     #[error("HTTP error: {0}")]
     HttpError(Cow<'static, str>),
 
+    /// Fetch policy violations
+    ///
+    /// Occurs during cross-document rendering when a URL, the address it
+    /// resolves to, or the response is refused by the configured fetch policy.
+    #[error("Fetch policy violation: {0}")]
+    PolicyError(Cow<'static, str>),
+
+    /// Bound paths missing from the data
+    ///
+    /// Occurs when rendering with
+    /// [`RenderBuilder::error_on_missing_properties`](crate::RenderBuilder::error_on_missing_properties)
+    /// and the data lacks paths the rendered template binds.
+    #[error("Missing data: {} bound path(s) don't resolve:{}", .0.len(), list_paths(.0))]
+    MissingDataError(Vec<MissingPath>),
+
     /// File system and I/O errors
     ///
     /// Automatically converted from `std::io::Error` when file operations fail.
@@ -218,6 +237,36 @@ impl Error {
         Error::SelectorError(Cow::Owned(msg))
     }
 
+    /// Create an HTTP error with a static string
+    ///
+    /// Use this for compile-time known error messages to avoid allocations.
+    pub fn http_static(msg: &'static str) -> Self {
+        Error::HttpError(Cow::Borrowed(msg))
+    }
+
+    /// Create an HTTP error with an owned string
+    ///
+    /// Use this for dynamic error messages that include runtime context,
+    /// such as the URL and status code of a failed request.
+    pub fn http_owned(msg: String) -> Self {
+        Error::HttpError(Cow::Owned(msg))
+    }
+
+    /// Create a fetch policy error with a static string
+    ///
+    /// Use this for compile-time known error messages to avoid allocations.
+    pub fn policy_static(msg: &'static str) -> Self {
+        Error::PolicyError(Cow::Borrowed(msg))
+    }
+
+    /// Create a fetch policy error with an owned string
+    ///
+    /// Use this for dynamic error messages that include runtime context,
+    /// such as the refused URL or address.
+    pub fn policy_owned(msg: String) -> Self {
+        Error::PolicyError(Cow::Owned(msg))
+    }
+
     /// Create a parse error (alias for compatibility)
     ///
     /// This is a convenience method that calls [`Error::parse_owned`].
@@ -252,6 +301,11 @@ impl Error {
 /// ```
 pub type Result<T> = std::result::Result<T, Error>;
 
+/// List missing paths one per line, each with the element binding it
+fn list_paths(paths: &[MissingPath]) -> String {
+    paths.iter().map(|path| format!("\n  {}", path)).collect()
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -295,6 +349,12 @@ mod tests {
         let dom_err = Error::dom_owned("dom error".to_string());
         assert_eq!(dom_err.to_string(), "DOM error: dom error");
 
+        let policy_err = Error::policy_owned("host denied".to_string());
+        assert_eq!(
+            policy_err.to_string(),
+            "Fetch policy violation: host denied"
+        );
+
         let io_err = Error::io("io error message".to_string());
         assert_eq!(io_err.to_string(), "IO error: io error message");
     }
@@ -82,6 +82,7 @@ use std::borrow::Cow;
 use std::collections::BTreeMap;
 
 use crate::error::Result;
+use crate::node_ext::NodeExt;
 use crate::value::RenderValue;
 
 /// Trait for custom element handlers in template rendering
@@ -234,6 +235,9 @@ pub trait ElementHandler: Send + Sync {
     }
 }
 
+/// Element handlers keyed by lowercase tag name
+pub type HandlerMap = std::collections::HashMap<String, Box<dyn ElementHandler>>;
+
 /// Built-in handler for HTML input elements
 ///
 /// This handler sets the `value` attribute on input elements based on the
@@ -433,14 +437,10 @@ impl ElementHandler for TextareaHandler {
 
     fn handle(&self, element: &Selection, value: &dyn RenderValue) -> Result<()> {
         if let Some(val) = value.get_property(&[]) {
-            // For textarea, we need to escape HTML entities
-            let escaped = val
-                .replace('&', "&amp;")
-                .replace('<', "&lt;")
-                .replace('>', "&gt;")
-                .replace('"', "&quot;")
-                .replace('\'', "&#39;");
-            element.set_html(escaped);
+            // The value becomes a text node, escaped when serialized
+            for node in element.nodes() {
+                node.set_text_content(&val);
+            }
         }
         Ok(())
     }
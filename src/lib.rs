@@ -93,8 +93,8 @@
 //! use html_template::{StreamingRenderer, HtmlTemplate};
 //!
 //! # fn streaming_example() -> html_template::Result<()> {
-//! let renderer = StreamingRenderer::new(&template, &handlers, 1000);
-//! let mut stream = renderer.render_owned(large_dataset)?;
+//! let renderer = StreamingRenderer::new(&template).with_buffer_size(1000);
+//! let mut stream = renderer.render_stream_owned(large_dataset);
 //!
 //! while let Some(chunk) = stream.next_chunk()? {
 //!     println!("{}", chunk);
@@ -162,8 +162,14 @@ pub mod node_ext;
 #[doc(hidden)]
 pub mod parser;
 #[doc(hidden)]
+pub mod program;
+#[doc(hidden)]
 pub mod renderer;
 #[doc(hidden)]
+pub mod template_tree;
+#[doc(hidden)]
+pub mod url_resolver;
+#[doc(hidden)]
 pub mod utils;
 
 // ============================================================================
@@ -173,7 +179,7 @@ pub mod utils;
 /// Core template type and result types
 pub use error::{Error, Result};
 /// Main template struct and configuration
-pub use types::{CacheMode, HtmlTemplate, TemplateConfig};
+pub use types::{CacheMode, HtmlTemplate, RenderPosition, TemplateConfig};
 /// Trait for types that can be rendered in templates
 pub use value::RenderValue;
 
@@ -197,18 +203,38 @@ pub use handlers::{
     LoggingHandler,
 };
 
+/// Constraint evaluation and functions callable from `data-constraint` expressions
+pub use constraints::{
+    ConstraintAttributes, ConstraintContext, ConstraintEvaluator, ConstraintFunction,
+    ConstraintFunctionRegistry, ConstraintWarning, ConstraintWarningKind,
+    DefaultConstraintEvaluator,
+};
+
+/// Compiled render programs, printable for troubleshooting, and the paths
+/// strict rendering finds missing
+pub use program::{MissingPath, RenderProgram};
+
 /// Streaming rendering for large datasets
 pub use streaming::{OwnedStreamingResult, StreamingRenderer, StreamingResult};
 
 /// Caching system for improved performance
-pub use cache::{CacheConfig, CacheStats, EvictionStrategy, TemplateCache, TemplateCacheStats};
+pub use cache::{
+    CacheConfig, CacheStats, CachedDocument, DiskDocumentStore, EvictionStrategy, TemplateCache,
+    TemplateCacheStats,
+};
 
 /// Cross-document rendering with external data sources
 pub use cross_document::{
-    CrossDocumentConfig, CrossDocumentMetadata, CrossDocumentRenderer, CrossDocumentRequest,
-    CrossDocumentResponse, CrossDocumentTemplate, DataSource, DocumentFetcher,
+    ConflictPolicy, CrossDocumentConfig, CrossDocumentMetadata, CrossDocumentRenderer,
+    CrossDocumentRequest, CrossDocumentResponse, CrossDocumentTemplate, DataSource,
+    DocumentFetcher, DocumentSource, FetchAttempt, FetchPolicy, FileSource, HttpSource,
+    LinkFollowing, MemorySource, NetworkErrorKind, RetryPolicy, SchemeRouter,
 };
 
+/// Concurrent cross-document rendering (requires the "async" feature)
+#[cfg(feature = "async")]
+pub use cross_document::AsyncCrossDocumentRenderer;
+
 // ============================================================================
 // Derive Macro - Type-safe template rendering
 // ============================================================================
@@ -5,16 +5,30 @@
 
 use dom_query::{Document, Selection};
 use serde_json::{Map, Value as JsonValue};
-use std::collections::HashMap;
+use std::collections::{HashMap, HashSet};
+use url::Url;
 
 use crate::error::Result;
+use crate::url_resolver;
 
 /// Extract microdata from a DOM element
+///
+/// Relative URL properties are resolved against the document's `<base href>`
+/// when it is absolute, and returned verbatim otherwise.
 pub fn extract_microdata(element: &dom_query::Node) -> Result<JsonValue> {
+    let base = url_resolver::document_base_url(element, None);
+    extract_microdata_with_base(element, base.as_ref())
+}
+
+/// Extract microdata from a DOM element, resolving URL properties against `base`
+pub fn extract_microdata_with_base(
+    element: &dom_query::Node,
+    base: Option<&Url>,
+) -> Result<JsonValue> {
     // Check if this is a microdata item
     if !element.has_attr("itemscope") {
         // If not itemscope, just extract the value
-        return Ok(extract_value(element));
+        return Ok(extract_value(element, base));
     }
 
     // Create object for this item
@@ -31,7 +45,7 @@ pub fn extract_microdata(element: &dom_query::Node) -> Result<JsonValue> {
     }
 
     // Find all properties within this item
-    let properties = find_properties(element);
+    let properties = find_properties(element, base);
 
     // Group properties by name
     let mut property_map: HashMap<String, Vec<JsonValue>> = HashMap::new();
@@ -56,7 +70,7 @@ pub fn extract_microdata(element: &dom_query::Node) -> Result<JsonValue> {
 }
 
 /// Find all properties within an item
-fn find_properties(item: &dom_query::Node) -> Vec<(String, JsonValue)> {
+fn find_properties(item: &dom_query::Node, base: Option<&Url>) -> Vec<(String, JsonValue)> {
     let mut properties = Vec::new();
 
     // Create a selection from the item
@@ -75,9 +89,9 @@ fn find_properties(item: &dom_query::Node) -> Vec<(String, JsonValue)> {
         if let Some(itemprop) = element.attr("itemprop") {
             let value = if element.has_attr("itemscope") {
                 // Nested item - extract recursively
-                extract_microdata(&element).unwrap_or(JsonValue::Null)
+                extract_microdata_with_base(element, base).unwrap_or(JsonValue::Null)
             } else {
-                extract_value(&element)
+                extract_value(element, base)
             };
 
             // Handle multiple property names
@@ -113,7 +127,11 @@ fn is_nested_item(element: &dom_query::Node, root_item: &dom_query::Node) -> boo
 }
 
 /// Extract value from an element based on its type
-fn extract_value(element: &dom_query::Node) -> JsonValue {
+///
+/// URL-valued attributes are resolved against `base`, if any.
+fn extract_value(element: &dom_query::Node, base: Option<&Url>) -> JsonValue {
+    let url_value = |url: &str| JsonValue::String(url_resolver::resolve(base, url).into_owned());
+
     // Check for special elements
     if let Some(tag_name) = element.node_name() {
         match tag_name.to_lowercase().as_str() {
@@ -126,19 +144,19 @@ fn extract_value(element: &dom_query::Node) -> JsonValue {
             // Link elements use href
             "link" | "a" | "area" => {
                 if let Some(href) = element.attr("href") {
-                    return JsonValue::String(href.to_string());
+                    return url_value(&href);
                 }
             }
             // Image elements use src
             "img" | "audio" | "video" | "source" | "track" | "embed" => {
                 if let Some(src) = element.attr("src") {
-                    return JsonValue::String(src.to_string());
+                    return url_value(&src);
                 }
             }
             // Object elements use data
             "object" => {
                 if let Some(data) = element.attr("data") {
-                    return JsonValue::String(data.to_string());
+                    return url_value(&data);
                 }
             }
             // Time elements use datetime if available
@@ -163,13 +181,31 @@ fn extract_value(element: &dom_query::Node) -> JsonValue {
 
 /// Extract microdata from a document
 pub fn extract_microdata_from_document(doc: &Document) -> Result<Vec<JsonValue>> {
+    let base = url_resolver::document_base_url(&doc.root(), None);
+    extract_top_level_items(doc, base.as_ref())
+}
+
+/// Extract microdata from a document fetched from `document_url`
+///
+/// URL properties are resolved against the document's `<base href>`, itself
+/// resolved against `document_url`, or against `document_url` directly.
+pub fn extract_microdata_from_document_with_url(
+    doc: &Document,
+    document_url: &str,
+) -> Result<Vec<JsonValue>> {
+    let document_url = Url::parse(document_url).ok();
+    let base = url_resolver::document_base_url(&doc.root(), document_url.as_ref());
+    extract_top_level_items(doc, base.as_ref())
+}
+
+fn extract_top_level_items(doc: &Document, base: Option<&Url>) -> Result<Vec<JsonValue>> {
     let mut items = Vec::new();
 
     // Find all top-level microdata items (itemscope without itemprop)
     let top_items = doc.select("[itemscope]:not([itemprop])");
 
     for item in top_items.nodes() {
-        if let Ok(data) = extract_microdata(&item) {
+        if let Ok(data) = extract_microdata_with_base(item, base) {
             items.push(data);
         }
     }
@@ -177,6 +213,148 @@ pub fn extract_microdata_from_document(doc: &Document) -> Result<Vec<JsonValue>>
     Ok(items)
 }
 
+/// Extract the item a URL fragment identifies in a document fetched from `document_url`
+///
+/// This is the element whose `id` is the fragment (or, if that element is not
+/// an item, the first item inside it), otherwise the item whose `itemid` is
+/// the fragment, `#fragment` or the full URL with the fragment.
+pub fn extract_microdata_by_fragment(
+    doc: &Document,
+    document_url: &str,
+    fragment: &str,
+) -> Result<Option<JsonValue>> {
+    let parsed_url = Url::parse(document_url).ok();
+    let base = url_resolver::document_base_url(&doc.root(), parsed_url.as_ref());
+    let full_url = match &parsed_url {
+        Some(url) => {
+            let mut url = url.clone();
+            url.set_fragment(Some(fragment));
+            Some(url)
+        }
+        None => None,
+    };
+
+    let by_id = doc
+        .select("[id]")
+        .nodes()
+        .iter()
+        .find(|element| element.attr("id").is_some_and(|id| &*id == fragment))
+        .and_then(|element| {
+            if element.has_attr("itemscope") {
+                Some(element.clone())
+            } else {
+                Selection::from(element.clone())
+                    .select("[itemscope]")
+                    .nodes()
+                    .first()
+                    .cloned()
+            }
+        });
+
+    let item = by_id.or_else(|| {
+        doc.select("[itemscope][itemid]")
+            .nodes()
+            .iter()
+            .find(|element| {
+                element.attr("itemid").is_some_and(|itemid| {
+                    let itemid = itemid.trim();
+                    itemid == fragment
+                        || itemid.strip_prefix('#') == Some(fragment)
+                        || full_url.as_ref().is_some_and(|full_url| {
+                            base.as_ref()
+                                .and_then(|base| base.join(itemid).ok())
+                                .as_ref()
+                                == Some(full_url)
+                        })
+                })
+            })
+            .cloned()
+    });
+
+    item.map(|element| extract_microdata_with_base(&element, base.as_ref()))
+        .transpose()
+}
+
+/// Extract every item of type `itemtype` from a document fetched from `document_url`
+///
+/// Items nested in other items are included unless they are inside an item
+/// that is already extracted.
+pub fn extract_microdata_by_type(
+    doc: &Document,
+    document_url: Option<&str>,
+    itemtype: &str,
+) -> Result<Vec<JsonValue>> {
+    let document_url = document_url.and_then(|url| Url::parse(url).ok());
+    let base = url_resolver::document_base_url(&doc.root(), document_url.as_ref());
+
+    let matching = doc.select("[itemscope][itemtype]");
+    let matching: Vec<_> = matching
+        .nodes()
+        .iter()
+        .filter(|element| {
+            element
+                .attr("itemtype")
+                .is_some_and(|types| itemtype_matches(&types, itemtype))
+        })
+        .cloned()
+        .collect();
+
+    let mut items = Vec::new();
+    for element in &matching {
+        let mut ancestor = element.parent();
+        let mut within_match = false;
+        while let Some(parent) = ancestor {
+            if matching.iter().any(|other| other.id == parent.id) {
+                within_match = true;
+                break;
+            }
+            ancestor = parent.parent();
+        }
+
+        if !within_match {
+            items.push(extract_microdata_with_base(element, base.as_ref())?);
+        }
+    }
+
+    Ok(items)
+}
+
+/// Check if an `itemtype` attribute includes the type `wanted`
+///
+/// Types are compared ignoring the URL scheme and any trailing slash, so
+/// `schema.org/Product` matches `https://schema.org/Product`.
+pub fn itemtype_matches(itemtype: &str, wanted: &str) -> bool {
+    fn normalize(itemtype: &str) -> &str {
+        let itemtype = itemtype.trim().trim_end_matches('/');
+        itemtype
+            .split_once("://")
+            .map_or(itemtype, |(_, rest)| rest)
+    }
+
+    let wanted = normalize(wanted);
+    itemtype
+        .split_whitespace()
+        .any(|itemtype| normalize(itemtype) == wanted)
+}
+
+/// Collect the URLs of link-valued properties in a document fetched from `document_url`
+///
+/// These are the resolved `href`s of `<a>`, `<link>` and `<area>` elements
+/// with an `itemprop`, i.e. properties that point at another resource rather
+/// than embedding a value.
+pub fn extract_item_links(doc: &Document, document_url: Option<&str>) -> HashSet<String> {
+    let document_url = document_url.and_then(|url| Url::parse(url).ok());
+    let base = url_resolver::document_base_url(&doc.root(), document_url.as_ref());
+
+    doc.select("a[itemprop][href], link[itemprop][href], area[itemprop][href]")
+        .nodes()
+        .iter()
+        .filter(|element| !element.has_attr("itemscope"))
+        .filter_map(|element| element.attr("href"))
+        .map(|href| url_resolver::resolve(base.as_ref(), &href).into_owned())
+        .collect()
+}
+
 /// Extract microdata from HTML string
 pub fn extract_microdata_from_html(html: &str) -> Result<Vec<JsonValue>> {
     let doc = Document::from(html);
@@ -187,6 +365,86 @@ pub fn extract_microdata_from_html(html: &str) -> Result<Vec<JsonValue>> {
 mod tests {
     use super::*;
 
+    #[test]
+    fn test_extract_microdata_by_fragment() {
+        let doc = Document::from(
+            r##"<div itemscope itemtype="https://schema.org/Product">
+                <span itemprop="name">Lamp</span>
+            </div>
+            <section id="product-42">
+                <div itemscope itemtype="https://schema.org/Product">
+                    <span itemprop="name">Desk</span>
+                </div>
+            </section>
+            <div itemscope itemid="https://example.com/shop#chair">
+                <span itemprop="name">Chair</span>
+            </div>
+            <div itemscope itemid="#stool"><span itemprop="name">Stool</span></div>"##,
+        );
+        let url = "https://example.com/shop";
+
+        let item = extract_microdata_by_fragment(&doc, url, "product-42").unwrap();
+        assert_eq!(item.unwrap()["name"], "Desk");
+        let item = extract_microdata_by_fragment(&doc, url, "chair").unwrap();
+        assert_eq!(item.unwrap()["name"], "Chair");
+        let item = extract_microdata_by_fragment(&doc, url, "stool").unwrap();
+        assert_eq!(item.unwrap()["name"], "Stool");
+        assert_eq!(
+            extract_microdata_by_fragment(&doc, url, "missing").unwrap(),
+            None
+        );
+    }
+
+    #[test]
+    fn test_extract_microdata_by_type() {
+        let doc = Document::from(
+            r#"<div itemscope itemtype="https://schema.org/WebPage">
+                <div itemprop="mainEntity" itemscope itemtype="https://schema.org/Product">
+                    <span itemprop="name">Lamp</span>
+                    <div itemprop="isRelatedTo" itemscope itemtype="https://schema.org/Product">
+                        <span itemprop="name">Bulb</span>
+                    </div>
+                </div>
+            </div>
+            <div itemscope itemtype="http://schema.org/Product https://example.com/Sale">
+                <span itemprop="name">Desk</span>
+            </div>
+            <div itemscope itemtype="https://schema.org/Person">
+                <span itemprop="name">Ada</span>
+            </div>"#,
+        );
+
+        let products = extract_microdata_by_type(&doc, None, "schema.org/Product").unwrap();
+        assert_eq!(products.len(), 2);
+        assert_eq!(products[0]["name"], "Lamp");
+        assert_eq!(products[0]["isRelatedTo"]["name"], "Bulb");
+        assert_eq!(products[1]["name"], "Desk");
+
+        assert!(itemtype_matches(
+            "https://schema.org/Product/",
+            "http://schema.org/Product"
+        ));
+        assert!(!itemtype_matches(
+            "https://schema.org/ProductModel",
+            "schema.org/Product"
+        ));
+    }
+
+    #[test]
+    fn test_extract_item_links() {
+        let doc = Document::from(
+            r#"<div itemscope>
+                <a itemprop="author" href="/people/ada">Ada</a>
+                <img itemprop="image" src="/ada.jpg">
+                <a href="/unrelated">Home</a>
+            </div>"#,
+        );
+
+        let links = extract_item_links(&doc, Some("https://example.com/post"));
+        assert_eq!(links.len(), 1);
+        assert!(links.contains("https://example.com/people/ada"));
+    }
+
     #[test]
     fn test_extract_simple_microdata() {
         let html = r#"
@@ -339,6 +597,60 @@ mod tests {
         assert_eq!(author["email"], "jane@example.com");
     }
 
+    #[test]
+    fn test_relative_urls_resolved_against_base_element() {
+        let html = r#"
+            <html>
+                <head><base href="https://example.com/blog/"></head>
+                <body>
+                    <div itemscope>
+                        <a itemprop="url" href="posts/1">Post</a>
+                        <img itemprop="image" src="/img/cover.jpg">
+                        <link itemprop="sameAs" href="https://other.org/post">
+                        <div itemprop="author" itemscope>
+                            <a itemprop="url" href="../authors/jane">Jane</a>
+                        </div>
+                    </div>
+                </body>
+            </html>
+        "#;
+
+        let doc = Document::from(html);
+        let items = extract_microdata_from_document(&doc).unwrap();
+        let item = &items[0];
+
+        assert_eq!(item["url"], "https://example.com/blog/posts/1");
+        assert_eq!(item["image"], "https://example.com/img/cover.jpg");
+        assert_eq!(item["sameAs"], "https://other.org/post");
+        assert_eq!(item["author"]["url"], "https://example.com/authors/jane");
+    }
+
+    #[test]
+    fn test_relative_urls_resolved_against_document_url() {
+        let html = r#"
+            <div itemscope>
+                <a itemprop="url" href="other.html">Other</a>
+            </div>
+        "#;
+        let doc = Document::from(html);
+
+        let items =
+            extract_microdata_from_document_with_url(&doc, "https://example.com/pages/index.html")
+                .unwrap();
+        assert_eq!(items[0]["url"], "https://example.com/pages/other.html");
+
+        // A relative <base> is resolved against the document URL first
+        let html = r#"
+            <html><head><base href="/static/"></head>
+            <body><div itemscope><img itemprop="image" src="a.png"></div></body></html>
+        "#;
+        let doc = Document::from(html);
+        let items =
+            extract_microdata_from_document_with_url(&doc, "https://example.com/pages/index.html")
+                .unwrap();
+        assert_eq!(items[0]["image"], "https://example.com/static/a.png");
+    }
+
     #[test]
     fn test_itemid_extraction() {
         let html = r#"
@@ -1,4 +1,5 @@
-use dom_query::Node;
+use dom_query::{Node, NodeData};
+use html5ever::tendril::StrTendril;
 use std::collections::HashMap;
 
 /// Extension trait for dom_query::Node to provide helper methods
@@ -39,16 +40,12 @@ impl<'a> NodeExt for Node<'a> {
     }
 
     fn set_text_content(&self, text: &str) {
-        // Remove all children and set text
+        // Replace all children with a single text node
         self.remove_children();
-        // Unfortunately dom_query doesn't have a direct way to append text nodes
-        // We'll use set_html as a workaround, escaping HTML entities
-        let escaped = text
-            .replace('&', "&amp;")
-            .replace('<', "&lt;")
-            .replace('>', "&gt;")
-            .replace('"', "&quot;")
-            .replace('\'', "&#39;");
-        self.set_html(escaped);
+        if !text.is_empty() {
+            let contents = StrTendril::from(text);
+            self.tree
+                .append_child_data_of(&self.id, NodeData::Text { contents });
+        }
     }
 }
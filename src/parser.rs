@@ -1,9 +1,14 @@
 use dom_query::{Document, Selection};
 use once_cell::sync::Lazy;
 use regex::Regex;
+use std::collections::HashMap;
+use std::sync::Arc;
 
+use crate::constraints::{ConstraintAttributes, ConstraintEvaluator};
 use crate::error::{Error, Result};
 use crate::node_ext::NodeExt;
+use crate::program::RenderProgram;
+use crate::template_tree::TemplateTree;
 use crate::types::*;
 use crate::utils::split_path_cow;
 
@@ -15,12 +20,25 @@ static ESCAPED_VARIABLE_REGEX: Lazy<Regex> =
 
 pub struct Parser {
     document: Document,
+    constraint_attributes: ConstraintAttributes,
+    constraint_evaluator: Option<Arc<dyn ConstraintEvaluator>>,
 }
 
 impl Parser {
     pub fn new(html: &str) -> Result<Self> {
         let document = Document::from(html);
-        Ok(Self { document })
+        Ok(Self {
+            document,
+            constraint_attributes: ConstraintAttributes::default(),
+            constraint_evaluator: None,
+        })
+    }
+
+    /// Use the constraint attribute names and evaluator from `config`
+    pub fn with_config(mut self, config: &TemplateConfig) -> Self {
+        self.constraint_attributes = config.constraint_attributes.clone();
+        self.constraint_evaluator = config.constraint_evaluator.clone();
+        self
     }
 
     pub fn parse_template(&self, root_selector: Option<&str>) -> Result<CompiledTemplate> {
@@ -79,14 +97,27 @@ impl Parser {
 
         // Parse the template structure
         let elements = self.parse_elements(&content)?;
+        self.validate_else_chains(&content)?;
         let constraints = self.extract_constraints(&content)?;
+        let expressions = constraints
+            .iter()
+            .filter_map(|constraint| match &constraint.constraint_type {
+                ConstraintType::Expression(expr) => {
+                    Some((expr.source().to_string(), Arc::clone(expr.ast())))
+                }
+                ConstraintType::Scope(_) => None,
+            })
+            .collect();
 
         Ok(CompiledTemplate {
             root_selector: root_selector.map(String::from),
             elements,
-            constraints,
+            expressions,
+            constraint_attributes: self.constraint_attributes.clone(),
             base_uri: self.extract_base_uri(),
             template_html: content_html,
+            tree: TemplateTree::from_document(&content_doc),
+            program: RenderProgram::default(),
         })
     }
 
@@ -135,23 +166,19 @@ impl Parser {
         };
 
         // Parse properties from this element
-        let properties = self.parse_properties(element, &clean_name, is_array)?;
+        let properties = Self::parse_properties(element, &clean_name, is_array)?;
 
-        // Check for itemscope
-        let is_scope = element.has_attr("itemscope");
         let itemtype = element.attr("itemtype").map(|s| s.to_string());
 
         // Extract constraints for this element
         let constraint_refs = self.extract_element_constraints(element)?;
 
         // Generate a unique selector for this element
-        let selector = self.generate_selector(element)?;
+        let selector = Self::element_selector(element, &self.constraint_attributes);
 
         elements.push(TemplateElement {
             selector,
             properties,
-            is_array,
-            is_scope,
             itemtype,
             constraints: constraint_refs,
         });
@@ -159,8 +186,7 @@ impl Parser {
         Ok(())
     }
 
-    fn parse_properties(
-        &self,
+    pub(crate) fn parse_properties(
         element: &dom_query::Node,
         prop_name: &str,
         is_array: bool,
@@ -169,13 +195,14 @@ impl Parser {
 
         // Check text content for variables or binding
         let text = element.text_content();
-        let text_variables = self.extract_variables(&text);
+        let text_variables = Self::extract_variables(&text);
         let has_variables = !text_variables.is_empty();
 
         // Create a text content property for elements with itemprop
         // For array elements, only create text content property if there are explicit variables
-        if (!is_array && (has_variables || !text.trim().is_empty() || properties.is_empty())) || 
-           (is_array && has_variables) {
+        if (!is_array && (has_variables || !text.trim().is_empty() || properties.is_empty()))
+            || (is_array && has_variables)
+        {
             properties.push(Property {
                 name: prop_name.to_string(),
                 is_array: false,
@@ -201,7 +228,7 @@ impl Parser {
         // Check attributes for variables
         let attrs = element.attrs();
         for attr in &attrs {
-            let variables = self.extract_variables(&attr.value);
+            let variables = Self::extract_variables(&attr.value);
             if !variables.is_empty() {
                 properties.push(Property {
                     name: prop_name.to_string(),
@@ -259,7 +286,7 @@ impl Parser {
         Ok(properties)
     }
 
-    fn extract_variables(&self, text: &str) -> Vec<Variable> {
+    fn extract_variables(text: &str) -> Vec<Variable> {
         // First, temporarily replace escaped variables with placeholders to avoid false matches
         let mut working_text = text.to_string();
         let escaped_vars: Vec<_> = ESCAPED_VARIABLE_REGEX.captures_iter(text).collect();
@@ -274,7 +301,7 @@ impl Parser {
             .captures_iter(&working_text)
             .map(|cap| {
                 let var_path = &cap[1];
-                let path = self.parse_variable_path(var_path);
+                let path = Self::parse_variable_path(var_path);
                 Variable {
                     path,
                     raw: cap[0].to_string(),
@@ -283,7 +310,7 @@ impl Parser {
             .collect()
     }
 
-    fn parse_variable_path(&self, path: &str) -> Vec<String> {
+    fn parse_variable_path(path: &str) -> Vec<String> {
         // Use zero-copy path splitting for simple cases
         if !path.contains('[') {
             // Simple case without array access
@@ -311,9 +338,7 @@ impl Parser {
         let scope_elements = root.select("[data-scope]");
         for element in scope_elements.nodes() {
             if let Some(scope) = element.attr("data-scope") {
-                let selector = self.generate_selector(element)?;
                 constraints.push(Constraint {
-                    element_selector: selector,
                     constraint_type: ConstraintType::Scope(scope.to_string()),
                     scope: Some(scope.to_string()),
                 });
@@ -321,14 +346,35 @@ impl Parser {
             }
         }
 
-        // Find elements with data-constraint
-        let constraint_elements = root.select("[data-constraint]");
+        // Find elements with data-constraint or data-else-if, parsing each
+        // distinct expression once
+        let attributes = &self.constraint_attributes;
+        let mut parsed: HashMap<String, CompiledExpression> = HashMap::new();
+        let constraint_elements = root.select(&format!(
+            "[{}], [{}]",
+            attributes.constraint, attributes.else_if
+        ));
         for element in constraint_elements.nodes() {
-            if let Some(constraint_expr) = element.attr("data-constraint") {
-                let selector = self.generate_selector(element)?;
+            let constraint_expr = element
+                .attr(&attributes.constraint)
+                .or_else(|| element.attr(&attributes.else_if));
+            if let Some(constraint_expr) = constraint_expr {
+                // A custom evaluator owns its grammar, so its expressions are
+                // only validated and handed over verbatim at render time
+                if let Some(evaluator) = &self.constraint_evaluator {
+                    evaluator.validate(&constraint_expr)?;
+                    continue;
+                }
+                let expr = match parsed.get(constraint_expr.as_ref()) {
+                    Some(expr) => expr.clone(),
+                    None => {
+                        let expr = CompiledExpression::parse(&constraint_expr)?;
+                        parsed.insert(constraint_expr.to_string(), expr.clone());
+                        expr
+                    }
+                };
                 constraints.push(Constraint {
-                    element_selector: selector,
-                    constraint_type: ConstraintType::Expression(constraint_expr.to_string()),
+                    constraint_type: ConstraintType::Expression(expr),
                     scope: None,
                 });
                 _constraint_index += 1;
@@ -338,6 +384,91 @@ impl Parser {
         Ok(constraints)
     }
 
+    /// Check that every `data-else-if` and `data-else` element directly follows
+    /// an element with `data-constraint` or `data-else-if`
+    fn validate_else_chains(&self, root: &Selection) -> Result<()> {
+        let ConstraintAttributes {
+            constraint,
+            else_if,
+            else_branch,
+        } = &self.constraint_attributes;
+
+        // Root elements are always rendered, so they can't be branches
+        for element in root.nodes() {
+            if let Some(attribute) = [else_if, else_branch]
+                .into_iter()
+                .find(|attribute| element.has_attr(attribute))
+            {
+                return Err(Error::parse_owned(format!(
+                    "{} cannot be used on root element <{}>",
+                    attribute,
+                    element.node_name().unwrap_or_default()
+                )));
+            }
+        }
+
+        for element in root
+            .select(&format!("[{}], [{}]", else_if, else_branch))
+            .nodes()
+        {
+            let attribute = if element.has_attr(else_if) {
+                else_if
+            } else {
+                else_branch
+            };
+
+            if element.has_attr(constraint)
+                || (element.has_attr(else_if) && element.has_attr(else_branch))
+            {
+                return Err(Error::parse_owned(format!(
+                    "Element <{}> combines {} with another constraint attribute",
+                    element.node_name().unwrap_or_default(),
+                    attribute
+                )));
+            }
+
+            // Array items test their constraints once per item, so they can
+            // neither head nor continue a chain
+            let is_array_item = |node: &dom_query::Node| {
+                node.attr("itemprop")
+                    .is_some_and(|itemprop| itemprop.ends_with("[]"))
+            };
+            if is_array_item(element) {
+                return Err(Error::parse_owned(format!(
+                    "{} cannot be used on array item <{}>",
+                    attribute,
+                    element.node_name().unwrap_or_default()
+                )));
+            }
+            if let Some(previous) = element
+                .prev_element_sibling()
+                .filter(|previous| is_array_item(previous))
+            {
+                return Err(Error::parse_owned(format!(
+                    "{} on <{}> cannot follow array item <{}>",
+                    attribute,
+                    element.node_name().unwrap_or_default(),
+                    previous.node_name().unwrap_or_default()
+                )));
+            }
+
+            let follows_branch = element.prev_element_sibling().is_some_and(|previous| {
+                previous.has_attr(constraint) || previous.has_attr(else_if)
+            });
+            if !follows_branch {
+                return Err(Error::parse_owned(format!(
+                    "{} on <{}> must immediately follow an element with {} or {}",
+                    attribute,
+                    element.node_name().unwrap_or_default(),
+                    constraint,
+                    else_if
+                )));
+            }
+        }
+
+        Ok(())
+    }
+
     fn extract_element_constraints(
         &self,
         _element: &dom_query::Node,
@@ -350,16 +481,19 @@ impl Parser {
         Ok(refs)
     }
 
-    fn generate_selector(&self, element: &dom_query::Node) -> Result<String> {
-        // Generate a CSS selector for this element
+    /// Generate a CSS selector for an element
+    pub(crate) fn element_selector(
+        element: &dom_query::Node,
+        attributes: &ConstraintAttributes,
+    ) -> String {
         // For elements with itemprop, we only need the itemprop selector
         // This makes the selector more flexible and works across different DOM contexts
-        
+
         if let Some(itemprop) = element.attr("itemprop") {
             // For itemprop elements, just use the attribute selector
-            return Ok(format!("[itemprop=\"{}\"]", itemprop));
+            return format!("[itemprop=\"{}\"]", itemprop);
         }
-        
+
         // For elements without itemprop, build a more specific selector
         let mut selector_parts = Vec::new();
 
@@ -381,9 +515,11 @@ impl Parser {
         }
 
         // Add data-constraint as attribute selector
-        if let Some(constraint) = element.attr("data-constraint") {
+        let constraint_attribute = &attributes.constraint;
+        if let Some(constraint) = element.attr(constraint_attribute) {
             selector_parts.push(format!(
-                "[data-constraint=\"{}\"]",
+                "[{}=\"{}\"]",
+                constraint_attribute,
                 constraint.replace('"', "\\\"")
             ));
         }
@@ -393,7 +529,7 @@ impl Parser {
             selector_parts.push(format!("[data-scope=\"{}\"]", scope));
         }
 
-        Ok(selector_parts.join(""))
+        selector_parts.join("")
     }
 
     fn extract_base_uri(&self) -> Option<String> {
@@ -448,19 +584,16 @@ mod tests {
             </template>
         "#;
 
-        let parser = Parser::new(html).unwrap();
-        let compiled = parser.parse_template(Some("ul")).unwrap();
+        let compiled = crate::compiler::Compiler::compile(html, Some("ul")).unwrap();
 
         assert_eq!(compiled.elements.len(), 1);
-        assert!(compiled.elements[0].is_array);
         assert_eq!(compiled.elements[0].properties[0].name, "items");
+        assert!(compiled.program.to_string().contains("loop items"));
     }
 
     #[test]
     fn test_extract_variables() {
-        let parser = Parser::new("").unwrap();
-
-        let vars = parser.extract_variables("Hello ${name}, your age is ${user.age}!");
+        let vars = Parser::extract_variables("Hello ${name}, your age is ${user.age}!");
         assert_eq!(vars.len(), 2);
         assert_eq!(vars[0].raw, "${name}");
         assert_eq!(vars[0].path, vec!["name"]);
@@ -470,12 +603,10 @@ mod tests {
 
     #[test]
     fn test_parse_variable_path() {
-        let parser = Parser::new("").unwrap();
-
-        let path = parser.parse_variable_path("user.profile.name");
+        let path = Parser::parse_variable_path("user.profile.name");
         assert_eq!(path, vec!["user", "profile", "name"]);
 
-        let path = parser.parse_variable_path("items[0].name");
+        let path = Parser::parse_variable_path("items[0].name");
         assert_eq!(path, vec!["items", "name"]);
     }
 
@@ -489,12 +620,12 @@ mod tests {
             </template>
         "#;
 
-        let parser = Parser::new(html).unwrap();
-        let compiled = parser.parse_template(None).unwrap();
+        let compiled = crate::compiler::Compiler::compile(html, None).unwrap();
+        let program = compiled.program.to_string();
 
         assert_eq!(compiled.elements.len(), 2);
-        assert!(compiled.elements[0].is_scope);
-        assert!(!compiled.elements[1].is_scope);
+        assert!(program.contains("enter-scope author"));
+        assert!(!program.contains("enter-scope name"));
     }
 
     #[test]
@@ -509,21 +640,103 @@ mod tests {
         "#;
 
         let parser = Parser::new(html).unwrap();
-        let compiled = parser.parse_template(Some("div")).unwrap();
+        let content = Document::from(parser.parse_template(None).unwrap().template_html);
+        let constraints = parser
+            .extract_constraints(&content.select("body > div"))
+            .unwrap();
 
-        assert_eq!(compiled.constraints.len(), 2);
+        assert_eq!(constraints.len(), 2);
 
-        match &compiled.constraints[0].constraint_type {
+        match &constraints[0].constraint_type {
             ConstraintType::Scope(scope) => assert_eq!(scope, "user"),
             _ => panic!("Expected scope constraint"),
         }
 
-        match &compiled.constraints[1].constraint_type {
+        match &constraints[1].constraint_type {
             ConstraintType::Expression(expr) => assert_eq!(expr, "age > 18"),
             _ => panic!("Expected expression constraint"),
         }
     }
 
+    #[test]
+    fn test_constraints_parsed_at_compile_time() {
+        let html = r#"
+            <template>
+                <ul>
+                    <li data-constraint="count > 0 && visible">One</li>
+                    <li data-constraint="count > 0 && visible">Two</li>
+                </ul>
+            </template>
+        "#;
+
+        let parser = Parser::new(html).unwrap();
+        let compiled = parser.parse_template(Some("ul")).unwrap();
+        assert_eq!(compiled.expressions.len(), 1);
+
+        // Identical expressions share one parsed AST
+        let content = Document::from(compiled.template_html);
+        let constraints = parser
+            .extract_constraints(&content.select("body > ul"))
+            .unwrap();
+        let asts: Vec<_> = constraints
+            .iter()
+            .map(|constraint| match &constraint.constraint_type {
+                ConstraintType::Expression(expr) => Arc::clone(expr.ast()),
+                _ => panic!("Expected expression constraint"),
+            })
+            .collect();
+        assert!(Arc::ptr_eq(&asts[0], &asts[1]));
+
+        let invalid = r#"<template><div><p data-constraint="a && (b"></p></div></template>"#;
+        let error = Parser::new(invalid)
+            .unwrap()
+            .parse_template(Some("div"))
+            .unwrap_err();
+        assert!(error
+            .to_string()
+            .contains("expected ')', found end of expression at column 8"));
+    }
+
+    #[test]
+    fn test_else_chains() {
+        let html = r#"
+            <template>
+                <div>
+                    <p data-constraint="count > 10">Many</p>
+                    <p data-else-if="count > 0">Some</p>
+                    <p data-else>None</p>
+                </div>
+            </template>
+        "#;
+
+        let compiled = Parser::new(html)
+            .unwrap()
+            .parse_template(Some("div"))
+            .unwrap();
+        assert!(compiled.expressions.contains_key("count > 10"));
+        assert!(compiled.expressions.contains_key("count > 0"));
+
+        let error = |html: &str| {
+            Parser::new(html)
+                .unwrap()
+                .parse_template(Some("div"))
+                .unwrap_err()
+                .to_string()
+        };
+        assert!(
+            error(r#"<template><div><p data-else>x</p></div></template>"#)
+                .contains("data-else on <p> must immediately follow")
+        );
+        assert!(error(
+            r#"<template><div><p data-constraint="a">a</p><p data-else>b</p><p data-else>c</p></div></template>"#
+        )
+        .contains("must immediately follow"));
+        assert!(error(
+            r#"<template><div><p data-constraint="a">a</p><p data-else data-constraint="b">b</p></div></template>"#
+        )
+        .contains("combines data-else with another constraint attribute"));
+    }
+
     #[test]
     fn test_base_uri_extraction() {
         let html = r#"
@@ -663,10 +876,8 @@ mod tests {
             ("${user.emails[0]}", vec!["user", "emails"]), // Array access removed
         ];
 
-        let parser = Parser::new("").unwrap();
-
         for (input, expected_path) in test_cases {
-            let vars = parser.extract_variables(input);
+            let vars = Parser::extract_variables(input);
             if vars.len() > 0 {
                 assert_eq!(vars[0].path, expected_path);
             }
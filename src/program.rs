@@ -0,0 +1,1370 @@
+//! Linear render programs
+//!
+//! The compiler lowers a template into a flat list of ops: static HTML
+//! chunks, escaped property values, attributes, scopes, array loops and
+//! constraint tests. Rendering runs the ops against the data and writes the
+//! output as it goes, without building a document or matching selectors. The
+//! output is either serialized HTML or nodes built in a caller's document.
+//! When the renderer has element handlers, bound elements are built as nodes
+//! first so the handlers can update them.
+
+use std::borrow::Cow;
+use std::fmt;
+use std::io::Write;
+
+use dom_query::{Document, Element, Node, NodeData, NodeId, Selection, Tree};
+use html5ever::tendril::StrTendril;
+use html5ever::{local_name, namespace_url, ns, Attribute, LocalName, QualName};
+
+use crate::constraints::ConstraintAttributes;
+use crate::error::Result;
+use crate::parser::Parser;
+use crate::renderer::{
+    property_value, root_elements, substitute_variables, write_selection, Renderer,
+};
+use crate::template_tree::copy_node;
+use crate::types::{CompiledTemplate, Property, PropertyTarget, Variable};
+use crate::url_resolver;
+use crate::value::RenderValue;
+
+/// A template lowered into a flat sequence of ops
+///
+/// The `Display` output lists the ops with their indices, one per line, to
+/// show how a template will render.
+#[derive(Debug, Clone, Default)]
+pub struct RenderProgram {
+    ops: Vec<Op>,
+    /// For each op, the index in `selectors` of the element it was lowered from
+    sources: Vec<usize>,
+    /// Selectors of the lowered elements, naming where missing data is bound
+    selectors: Vec<String>,
+    /// Number of constraint chains, each tracking whether a branch matched
+    chains: usize,
+}
+
+#[derive(Debug, Clone)]
+enum Op {
+    /// Write HTML that doesn't depend on the data
+    Static(Chunk),
+    /// Write an element's text if any step sets it and jump to `end`,
+    /// otherwise fall through to the ops for its children
+    Text {
+        text: TextBinding,
+        end: usize,
+    },
+    /// Write an attribute bound to the data
+    Attribute(AttributeBinding),
+    /// Render with the named property as the data until the matching `ExitScope`
+    EnterScope {
+        name: String,
+    },
+    ExitScope,
+    /// Run the ops up to `end` once per item of the named array
+    Loop {
+        name: String,
+        end: usize,
+    },
+    /// Continue with the next item of the loop at `start`
+    EndLoop {
+        start: usize,
+    },
+    /// Skip to `otherwise` unless the test holds
+    Test {
+        test: Test,
+        otherwise: usize,
+    },
+    /// Build the element that follows as nodes when the renderer has element
+    /// handlers, until the matching `Handle`
+    Capture,
+    /// Run the element handlers on the element built since the matching
+    /// `Capture` and write it out, passing them the named property if the
+    /// data has it, or else the data itself
+    Handle {
+        property: Option<String>,
+    },
+}
+
+/// Static HTML along with the nodes it stands for, so the output can be
+/// built as a tree as well as written as text
+#[derive(Debug, Clone, Default)]
+pub(crate) struct Chunk {
+    html: String,
+    tokens: Vec<Token>,
+}
+
+#[derive(Debug, Clone)]
+enum Token {
+    /// The start of an element, followed by its attributes
+    StartTag(QualName),
+    Attribute(QualName, String),
+    /// The end of a start tag; void elements have no children or end tag
+    StartTagEnd {
+        void: bool,
+    },
+    EndTag,
+    Text(String),
+    Comment(String),
+    Doctype(String),
+    ProcessingInstruction {
+        target: String,
+        contents: String,
+    },
+}
+
+#[derive(Debug, Clone)]
+struct TextBinding {
+    /// The text of the element in the template
+    content: String,
+    steps: Vec<TextStep>,
+    /// Whether the element's text is written without escaping, as in `<script>`
+    raw: bool,
+}
+
+#[derive(Debug, Clone)]
+enum TextStep {
+    /// Use the value of the current array item
+    Item,
+    Property(Property),
+    /// Substitute the `${}` variables in the text
+    Variables(Vec<Variable>),
+}
+
+#[derive(Debug, Clone)]
+struct AttributeBinding {
+    name: QualName,
+    /// The value in the template, if the element has the attribute
+    original: Option<String>,
+    properties: Vec<Property>,
+    /// Whether the value is a URL to resolve against the base URL
+    url: bool,
+}
+
+#[derive(Debug, Clone)]
+enum Test {
+    Constraint { expression: String, chain: usize },
+    ElseIf { expression: String, chain: usize },
+    Else { chain: usize },
+    Scope(String),
+}
+
+impl RenderProgram {
+    /// Lower the root elements of `template` into a program
+    pub(crate) fn compile(template: &CompiledTemplate) -> Self {
+        let doc = template.tree.to_document();
+        let roots = root_elements(&doc, template.root_selector.as_deref());
+
+        let mut builder = ProgramBuilder {
+            ops: Vec::new(),
+            sources: Vec::new(),
+            selectors: Vec::new(),
+            chains: 0,
+            label: 0,
+            attributes: &template.constraint_attributes,
+        };
+        // The root selection can include text between the root elements
+        for root in roots.nodes() {
+            match root.is_element() {
+                true => {
+                    builder.source(root);
+                    builder.element(root, Context::default());
+                }
+                false => builder.leaf(root, false),
+            }
+        }
+
+        Self {
+            ops: builder.ops,
+            sources: builder.sources,
+            selectors: builder.selectors,
+            chains: builder.chains,
+        }
+    }
+
+    /// Run the program against `data`, sending the rendered nodes to `output`
+    /// and recording the paths that don't resolve in `missing`, if given
+    pub(crate) fn execute(
+        &self,
+        renderer: &Renderer<'_>,
+        data: &dyn RenderValue,
+        output: &mut dyn Output,
+        mut missing: Option<&mut MissingPaths>,
+    ) -> Result<()> {
+        // The data each op renders with, innermost last
+        let mut scopes = vec![data];
+        // Constraints see the current array item, or the root data outside loops
+        let mut items = Vec::new();
+        let mut loops = Vec::new();
+        let mut chains = vec![false; self.chains];
+        // The properties and array items entered, naming the missing paths
+        let mut frames: Vec<Frame<'_>> = Vec::new();
+        // Elements being built for their handlers, innermost last
+        let scratch = renderer.has_handlers().then(Document::default);
+        let mut captures: Vec<TreeOutput<'_>> = Vec::new();
+
+        let mut pc = 0;
+        while let Some(op) = self.ops.get(pc) {
+            let current = *scopes.last().expect("the root data is never popped");
+            let selector = self.selector(pc);
+            pc += 1;
+
+            match op {
+                Op::Static(chunk) => sink(&mut captures, output).write_static(chunk)?,
+                Op::Text { text, end } => {
+                    if let Some(missing) = missing.as_deref_mut() {
+                        for path in text.unresolved(current) {
+                            missing.record(&frames, &path, selector);
+                        }
+                    }
+                    if let Some(value) = text.evaluate(current) {
+                        sink(&mut captures, output).write_text(&value, text.raw)?;
+                        pc = *end;
+                    }
+                }
+                Op::Attribute(attribute) => {
+                    if let Some(missing) = missing.as_deref_mut() {
+                        for path in unresolved_properties(&attribute.properties, current) {
+                            missing.record(&frames, &path, selector);
+                        }
+                    }
+                    attribute.write(current, renderer, sink(&mut captures, output))?
+                }
+                Op::EnterScope { name } => {
+                    let nested = current.get_value(std::slice::from_ref(name));
+                    if let (None, Some(missing)) = (&nested, missing.as_deref_mut()) {
+                        missing.record(&frames, name, selector);
+                    }
+                    scopes.push(nested.unwrap_or(current));
+                    frames.push(Frame { name, index: None });
+                }
+                Op::ExitScope => {
+                    scopes.pop();
+                    frames.pop();
+                }
+                Op::Loop { name, end } => {
+                    let array = match current.get_value(std::slice::from_ref(name)) {
+                        // A single value renders as a one item array
+                        Some(value) => value.as_array().unwrap_or_else(|| vec![value]),
+                        None => {
+                            if let Some(missing) = missing.as_deref_mut() {
+                                missing.record(&frames, name, selector);
+                            }
+                            Vec::new()
+                        }
+                    };
+                    match array.first() {
+                        Some(&item) => {
+                            scopes.push(item);
+                            items.push(item);
+                            loops.push((array, 1));
+                            frames.push(Frame {
+                                name,
+                                index: Some(0),
+                            });
+                        }
+                        None => pc = end + 1,
+                    }
+                }
+                Op::EndLoop { start } => {
+                    scopes.pop();
+                    items.pop();
+                    let frame = frames.pop().expect("the loop was entered");
+                    let (array, next) = loops.last_mut().expect("the loop was entered");
+                    match array.get(*next) {
+                        Some(&item) => {
+                            frames.push(Frame {
+                                index: Some(*next),
+                                ..frame
+                            });
+                            *next += 1;
+                            scopes.push(item);
+                            items.push(item);
+                            pc = start + 1;
+                        }
+                        None => {
+                            loops.pop();
+                        }
+                    }
+                }
+                Op::Test { test, otherwise } => {
+                    let data = items.last().copied().unwrap_or(data);
+                    if let (Some(expression), Some(missing)) =
+                        (test.expression(&chains), missing.as_deref_mut())
+                    {
+                        // Constraints read from the current array item
+                        let item = frames
+                            .iter()
+                            .rposition(|frame| frame.index.is_some())
+                            .map_or(0, |item| item + 1);
+                        for path in renderer.unresolved_constraint_paths(expression, data) {
+                            missing.record(&frames[..item], &path, selector);
+                        }
+                    }
+                    if !test.holds(renderer, data, &mut chains)? {
+                        pc = *otherwise;
+                    }
+                }
+                Op::Capture => {
+                    if let Some(scratch) = &scratch {
+                        captures.push(TreeOutput::within(&scratch.tree));
+                    }
+                }
+                Op::Handle { property } => {
+                    if scratch.is_none() {
+                        continue;
+                    }
+                    let capture = captures.pop().expect("the capture was started");
+                    let value = property
+                        .as_ref()
+                        .and_then(|name| current.get_value(std::slice::from_ref(name)))
+                        .unwrap_or(current);
+                    let nodes = capture.into_nodes();
+                    for node in nodes.iter().filter(|node| node.is_element()) {
+                        renderer.handle_element(node, value)?;
+                    }
+                    let sink = sink(&mut captures, output);
+                    for node in &nodes {
+                        sink.write_node(node)?;
+                    }
+                }
+            }
+        }
+
+        Ok(())
+    }
+
+    /// The selector of the element the op at `index` was lowered from
+    fn selector(&self, index: usize) -> &str {
+        self.sources
+            .get(index)
+            .and_then(|&source| self.selectors.get(source))
+            .map_or("", String::as_str)
+    }
+}
+
+/// Where ops write: the innermost element being built for its handlers, or
+/// else the program's output
+fn sink<'s>(captures: &'s mut [TreeOutput<'_>], output: &'s mut dyn Output) -> &'s mut dyn Output {
+    match captures.last_mut() {
+        Some(capture) => capture,
+        None => output,
+    }
+}
+
+/// A property or array item entered while running a program
+#[derive(Debug, Clone, Copy)]
+struct Frame<'p> {
+    name: &'p str,
+    /// The index of the current item, for array loops
+    index: Option<usize>,
+}
+
+impl TextBinding {
+    /// Compute the element's text, or `None` to keep its children
+    fn evaluate<'d>(&'d self, data: &'d dyn RenderValue) -> Option<Cow<'d, str>> {
+        let mut text = Cow::Borrowed(self.content.as_str());
+        let mut set = false;
+
+        for step in &self.steps {
+            let value = match step {
+                TextStep::Item => data.get_property(&[]).map(Cow::into_owned),
+                TextStep::Property(property) => {
+                    property_value(property, &text, data).map(Cow::into_owned)
+                }
+                TextStep::Variables(variables) => {
+                    // Values set by earlier steps may bring variables of their own
+                    let found;
+                    let variables = if set {
+                        found = text_variables(&text);
+                        &found
+                    } else {
+                        variables
+                    };
+                    if variables.is_empty() || !(text.contains("${") || text.contains("$$")) {
+                        continue;
+                    }
+                    Some(substitute_variables(&text, variables, data).into_owned())
+                }
+            };
+            if let Some(value) = value {
+                text = Cow::Owned(value);
+                set = true;
+            }
+        }
+
+        set.then_some(text)
+    }
+
+    /// The paths the text reads that don't resolve in `data`
+    fn unresolved(&self, data: &dyn RenderValue) -> Vec<String> {
+        let mut paths = Vec::new();
+        for step in &self.steps {
+            match step {
+                TextStep::Item => {}
+                TextStep::Property(property) => {
+                    paths.extend(unresolved_properties(std::slice::from_ref(property), data))
+                }
+                TextStep::Variables(variables) => {
+                    paths.extend(unresolved_variables(variables, data))
+                }
+            }
+        }
+        paths
+    }
+}
+
+/// The paths `properties` read that don't resolve in `data`
+fn unresolved_properties(properties: &[Property], data: &dyn RenderValue) -> Vec<String> {
+    let mut paths = Vec::new();
+    for property in properties {
+        match property.variables.is_empty() {
+            true => {
+                if data
+                    .get_value(std::slice::from_ref(&property.name))
+                    .is_none()
+                {
+                    paths.push(property.name.clone());
+                }
+            }
+            false => paths.extend(unresolved_variables(&property.variables, data)),
+        }
+    }
+    paths
+}
+
+fn unresolved_variables(variables: &[Variable], data: &dyn RenderValue) -> Vec<String> {
+    variables
+        .iter()
+        .filter(|variable| data.get_value(&variable.path).is_none())
+        .map(|variable| variable.path.join("."))
+        .collect()
+}
+
+impl AttributeBinding {
+    fn write(
+        &self,
+        data: &dyn RenderValue,
+        renderer: &Renderer<'_>,
+        output: &mut dyn Output,
+    ) -> Result<()> {
+        let mut value = self.original.as_deref().map(Cow::Borrowed);
+        for property in &self.properties {
+            let current = value.as_deref().unwrap_or_default();
+            if let Some(new) = property_value(property, current, data).map(Cow::into_owned) {
+                value = Some(Cow::Owned(new));
+            }
+        }
+
+        // Properties that didn't resolve leave a new attribute out
+        let Some(mut value) = value else {
+            return Ok(());
+        };
+        if self.url {
+            if let Cow::Owned(resolved) = url_resolver::resolve(renderer.base_url(), &value) {
+                value = Cow::Owned(resolved);
+            }
+        }
+
+        output.write_attribute(&self.name, &value)
+    }
+}
+
+impl Test {
+    /// The constraint expression `holds` would evaluate, if any
+    fn expression(&self, chains: &[bool]) -> Option<&str> {
+        match self {
+            Test::Constraint { expression, .. } => Some(expression),
+            Test::ElseIf { expression, chain } if !chains[*chain] => Some(expression),
+            _ => None,
+        }
+    }
+
+    fn holds(
+        &self,
+        renderer: &Renderer<'_>,
+        data: &dyn RenderValue,
+        chains: &mut [bool],
+    ) -> Result<bool> {
+        match self {
+            Test::Constraint { expression, chain } => {
+                chains[*chain] = renderer.test_constraint(expression, data)?;
+                Ok(chains[*chain])
+            }
+            Test::ElseIf { expression, chain } => {
+                if chains[*chain] {
+                    return Ok(false);
+                }
+                chains[*chain] = renderer.test_constraint(expression, data)?;
+                Ok(chains[*chain])
+            }
+            Test::Else { chain } => Ok(!chains[*chain]),
+            Test::Scope(scope) => Ok(renderer.in_scope(scope, data)),
+        }
+    }
+}
+
+/// Where a running program sends the rendered nodes
+pub(crate) trait Output {
+    fn write_static(&mut self, chunk: &Chunk) -> Result<()>;
+
+    /// Write the text of an element, which the serializer escapes unless `raw`
+    fn write_text(&mut self, text: &str, raw: bool) -> Result<()>;
+
+    /// Add an attribute to the element whose start tag is being written
+    fn write_attribute(&mut self, name: &QualName, value: &str) -> Result<()>;
+
+    /// Write a node built elsewhere, along with its descendants
+    fn write_node(&mut self, node: &Node) -> Result<()>;
+}
+
+/// Serializes the rendered nodes as HTML into a writer
+pub(crate) struct HtmlOutput<'w, W: Write + ?Sized>(pub(crate) &'w mut W);
+
+impl<W: Write + ?Sized> Output for HtmlOutput<'_, W> {
+    fn write_static(&mut self, chunk: &Chunk) -> Result<()> {
+        self.0.write_all(chunk.html.as_bytes())?;
+        Ok(())
+    }
+
+    fn write_text(&mut self, text: &str, raw: bool) -> Result<()> {
+        self.0.write_all(escape(text, raw, false).as_bytes())?;
+        Ok(())
+    }
+
+    fn write_attribute(&mut self, name: &QualName, value: &str) -> Result<()> {
+        write!(
+            self.0,
+            " {}=\"{}\"",
+            attribute_name(name),
+            escape(value, false, true)
+        )?;
+        Ok(())
+    }
+
+    fn write_node(&mut self, node: &Node) -> Result<()> {
+        write_selection(&Selection::from(node.clone()), self.0)
+    }
+}
+
+/// Creates the rendered nodes in a tree, detached from its document
+pub(crate) struct TreeOutput<'t> {
+    tree: &'t Tree<NodeData>,
+    /// Elements whose children are being written, innermost last
+    open: Vec<NodeId>,
+    /// The top-level nodes, in order
+    roots: Vec<NodeId>,
+}
+
+impl<'t> TreeOutput<'t> {
+    pub(crate) fn new(tree: &'t Tree<NodeData>) -> Self {
+        Self {
+            tree,
+            open: Vec::new(),
+            roots: Vec::new(),
+        }
+    }
+
+    /// Create the rendered nodes as children of a new fragment, so
+    /// handlers can replace them
+    fn within(tree: &'t Tree<NodeData>) -> Self {
+        let fragment = tree.create_node(NodeData::Fragment);
+        Self {
+            tree,
+            open: vec![fragment],
+            roots: vec![fragment],
+        }
+    }
+
+    /// The top-level nodes rendered, ready to be attached
+    pub(crate) fn into_roots(self) -> Vec<NodeId> {
+        self.roots
+    }
+
+    /// The nodes rendered by an output created `within` a fragment
+    fn into_nodes(self) -> Vec<Node<'t>> {
+        self.roots
+            .first()
+            .map(|fragment| Node::new(*fragment, self.tree).children())
+            .unwrap_or_default()
+    }
+
+    fn insert(&mut self, data: NodeData) -> NodeId {
+        let id = self.tree.create_node(data);
+        self.attach(id);
+        id
+    }
+
+    fn attach(&mut self, id: NodeId) {
+        match self.open.last() {
+            Some(parent) => self.tree.append_child_of(parent, &id),
+            None => self.roots.push(id),
+        }
+    }
+
+    /// Add text, merging it into a preceding text node as the parser would
+    fn insert_text(&mut self, text: &str) {
+        let last = match self.open.last() {
+            Some(parent) => self.tree.last_child_of(parent).map(|node| node.id),
+            None => self.roots.last().copied(),
+        };
+        let merged = last.and_then(|last| {
+            self.tree.update_node(&last, |node| match &mut node.data {
+                NodeData::Text { contents } => {
+                    contents.push_slice(text);
+                    true
+                }
+                _ => false,
+            })
+        });
+        if merged != Some(true) {
+            self.insert(NodeData::Text {
+                contents: StrTendril::from(text),
+            });
+        }
+    }
+}
+
+impl Output for TreeOutput<'_> {
+    fn write_static(&mut self, chunk: &Chunk) -> Result<()> {
+        for token in &chunk.tokens {
+            match token {
+                Token::StartTag(name) => {
+                    let element = Element::new(name.clone(), Vec::new(), None, false);
+                    let id = self.insert(NodeData::Element(element));
+                    self.open.push(id);
+                }
+                Token::Attribute(name, value) => self.write_attribute(name, value)?,
+                Token::StartTagEnd { void } => {
+                    if *void {
+                        self.open.pop();
+                    }
+                }
+                Token::EndTag => {
+                    self.open.pop();
+                }
+                Token::Text(text) => self.insert_text(text),
+                Token::Comment(contents) => {
+                    self.insert(NodeData::Comment {
+                        contents: StrTendril::from(contents.as_str()),
+                    });
+                }
+                Token::Doctype(name) => {
+                    self.insert(NodeData::Doctype {
+                        name: StrTendril::from(name.as_str()),
+                        public_id: StrTendril::new(),
+                        system_id: StrTendril::new(),
+                    });
+                }
+                Token::ProcessingInstruction { target, contents } => {
+                    self.insert(NodeData::ProcessingInstruction {
+                        target: StrTendril::from(target.as_str()),
+                        contents: StrTendril::from(contents.as_str()),
+                    });
+                }
+            }
+        }
+        Ok(())
+    }
+
+    fn write_text(&mut self, text: &str, _raw: bool) -> Result<()> {
+        self.insert_text(text);
+        Ok(())
+    }
+
+    fn write_attribute(&mut self, name: &QualName, value: &str) -> Result<()> {
+        if let Some(element) = self.open.last() {
+            self.tree.update_node(element, |node| {
+                if let NodeData::Element(element) = &mut node.data {
+                    element.attrs.push(Attribute {
+                        name: name.clone(),
+                        value: StrTendril::from(value),
+                    });
+                }
+            });
+        }
+        Ok(())
+    }
+
+    fn write_node(&mut self, node: &Node) -> Result<()> {
+        let id = copy_node(node, self.tree);
+        self.attach(id);
+        Ok(())
+    }
+}
+
+/// A path the template binds that the data lacks
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct MissingPath {
+    /// The path from the root of the data, such as `items[1].name`
+    pub path: String,
+    /// The selector of the element that binds it
+    pub selector: String,
+}
+
+impl fmt::Display for MissingPath {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{} (at {})", self.path, self.selector)
+    }
+}
+
+/// The paths that don't resolve, collected while a program runs
+#[derive(Debug, Default)]
+pub(crate) struct MissingPaths(Vec<MissingPath>);
+
+impl MissingPaths {
+    /// The missing paths in the order they were first found
+    pub(crate) fn into_paths(self) -> Vec<MissingPath> {
+        self.0
+    }
+
+    /// Record `path`, relative to the properties and items in `frames`
+    fn record(&mut self, frames: &[Frame<'_>], path: &str, selector: &str) {
+        let mut full = String::new();
+        for frame in frames {
+            if !full.is_empty() {
+                full.push('.');
+            }
+            full.push_str(frame.name);
+            if let Some(index) = frame.index {
+                full.push_str(&format!("[{}]", index));
+            }
+        }
+        if !full.is_empty() {
+            full.push('.');
+        }
+        full.push_str(path);
+
+        let missing = MissingPath {
+            path: full,
+            selector: selector.to_string(),
+        };
+        if !self.0.contains(&missing) {
+            self.0.push(missing);
+        }
+    }
+}
+
+/// Where in the template an element is lowered
+#[derive(Debug, Clone, Copy, Default)]
+struct Context {
+    /// Inside an array item, where the text of leaf elements has its
+    /// variables substituted
+    in_loop: bool,
+    /// Below the root elements, which are never removed by constraints or scopes
+    nested: bool,
+}
+
+struct ProgramBuilder<'a> {
+    ops: Vec<Op>,
+    sources: Vec<usize>,
+    selectors: Vec<String>,
+    chains: usize,
+    /// The latest jump target, which static HTML must not be merged across
+    label: usize,
+    attributes: &'a ConstraintAttributes,
+}
+
+impl ProgramBuilder<'_> {
+    /// Lower an element, repeating it for each item if it's an array element
+    fn element(&mut self, node: &Node, cx: Context) {
+        let itemprop = node.attr("itemprop");
+        let Some(name) = itemprop.as_deref().and_then(|name| name.strip_suffix("[]")) else {
+            self.element_body(node, cx, false);
+            return;
+        };
+
+        let start = self.push(Op::Loop {
+            name: name.to_string(),
+            end: 0,
+        });
+        let cx = Context {
+            in_loop: true,
+            nested: true,
+        };
+        // The item's own constraint and scope are tested for every item
+        let head = node
+            .attr(&self.attributes.constraint)
+            .map(|expression| self.chain_head(expression.to_string()));
+        let tests = self.tests(node, cx, head);
+        self.element_body(node, cx, true);
+        self.patch(&tests);
+
+        let end = self.push(Op::EndLoop { start });
+        if let Op::Loop { end: loop_end, .. } = &mut self.ops[start] {
+            *loop_end = end;
+        }
+    }
+
+    /// Lower an element's tags, bindings and children
+    fn element_body(&mut self, node: &Node, cx: Context, item: bool) {
+        let Some((name, attrs)) = node
+            .query(|inner| {
+                inner
+                    .as_element()
+                    .map(|element| (element.name.clone(), element.attrs.clone()))
+            })
+            .flatten()
+        else {
+            return;
+        };
+        let tag: &str = &name.local;
+
+        let mut properties = match node.attr("itemprop") {
+            // Array items are bound to the item itself
+            Some(itemprop) if !item => {
+                Parser::parse_properties(node, &itemprop, false).unwrap_or_default()
+            }
+            _ => Vec::new(),
+        };
+        let is_scope = !properties.is_empty() && node.has_attr("itemscope");
+        let scope_name = properties.first().map(|property| property.name.clone());
+        // Bound elements other than array items go through the element handlers
+        let handled = !item && node.has_attr("itemprop");
+        let handler_property = scope_name.clone();
+
+        // Select elements are left to their handler
+        let is_text = |property: &Property| matches!(property.target, PropertyTarget::TextContent);
+        if tag.eq_ignore_ascii_case("select") && properties.iter().any(is_text) {
+            properties.clear();
+        }
+        // Scopes don't render their own value as text
+        let text_property = match is_scope {
+            true => None,
+            false => properties
+                .iter()
+                .find(|property| is_text(property))
+                .cloned(),
+        };
+        properties.retain(|property| !is_text(property));
+
+        if let Some(name) = scope_name.filter(|_| is_scope) {
+            self.push(Op::EnterScope { name });
+        }
+        if handled {
+            self.push(Op::Capture);
+        }
+
+        // Start tag, with bound attributes in their original position and
+        // new ones after the rest
+        self.push_static(&format!("<{}", tag), Token::StartTag(name.clone()));
+        for attr in &attrs {
+            let local: &str = &attr.name.local;
+            let bound = take_properties(&mut properties, local);
+            let value = match local {
+                "itemprop" if item => attr.value.trim_end_matches("[]"),
+                _ => &attr.value,
+            };
+            let url = url_resolver::is_url_attribute(tag, local);
+
+            if bound.is_empty() && !url {
+                self.push_static(
+                    &format!(
+                        " {}=\"{}\"",
+                        attribute_name(&attr.name),
+                        escape(value, false, true)
+                    ),
+                    Token::Attribute(attr.name.clone(), value.to_string()),
+                );
+            } else {
+                self.push(Op::Attribute(AttributeBinding {
+                    name: attr.name.clone(),
+                    original: Some(value.to_string()),
+                    properties: bound,
+                    url,
+                }));
+            }
+        }
+        while let Some(property) = properties.first() {
+            let local = target_attribute(property).to_string();
+            let bound = take_properties(&mut properties, &local);
+            self.push(Op::Attribute(AttributeBinding {
+                url: url_resolver::is_url_attribute(tag, &local),
+                name: QualName::new(None, ns!(), LocalName::from(local)),
+                original: None,
+                properties: bound,
+            }));
+        }
+        let void = is_void(&name);
+        self.push_static(">", Token::StartTagEnd { void });
+
+        if !void {
+            let raw = is_raw_text(&name);
+            let mut steps = Vec::new();
+            if item
+                && Selection::from(node.clone())
+                    .select("[itemprop]")
+                    .is_empty()
+            {
+                steps.push(TextStep::Item);
+            }
+            if let Some(property) = text_property {
+                steps.push(TextStep::Property(property));
+            }
+            let content = node.text().to_string();
+            if cx.in_loop && node.element_children().is_empty() {
+                let variables = text_variables(&content);
+                if !variables.is_empty() && (content.contains("${") || content.contains("$$")) {
+                    steps.push(TextStep::Variables(variables));
+                }
+            }
+
+            let text = match steps.is_empty() {
+                true => None,
+                false => Some(self.push(Op::Text {
+                    text: TextBinding {
+                        content,
+                        steps,
+                        raw,
+                    },
+                    end: 0,
+                })),
+            };
+
+            self.children(node, Context { nested: true, ..cx }, raw);
+
+            if let Some(text) = text {
+                let end = self.label();
+                if let Op::Text { end: text_end, .. } = &mut self.ops[text] {
+                    *text_end = end;
+                }
+            }
+            self.push_static(&format!("</{}>", tag), Token::EndTag);
+        }
+
+        if handled {
+            self.push(Op::Handle {
+                property: handler_property,
+            });
+        }
+        if is_scope {
+            self.push(Op::ExitScope);
+        }
+    }
+
+    /// Lower the children of an element, linking constraint chains among them
+    fn children(&mut self, parent: &Node, cx: Context, raw: bool) {
+        let attributes = self.attributes;
+        let mut chain = None;
+
+        for child in parent.children() {
+            if !child.is_element() {
+                self.leaf(&child, raw);
+                continue;
+            }
+            self.source(&child);
+
+            let is_array = child
+                .attr("itemprop")
+                .is_some_and(|itemprop| itemprop.ends_with("[]"));
+            let test = if is_array {
+                // Array items test their constraints for each item
+                chain = None;
+                None
+            } else if let Some(expression) = child.attr(&attributes.constraint) {
+                let test = self.chain_head(expression.to_string());
+                if let Test::Constraint { chain: head, .. } = &test {
+                    chain = Some(*head);
+                }
+                Some(test)
+            } else if let Some(head) = chain {
+                if let Some(expression) = child.attr(&attributes.else_if) {
+                    Some(Test::ElseIf {
+                        expression: expression.to_string(),
+                        chain: head,
+                    })
+                } else {
+                    chain = None;
+                    child
+                        .has_attr(&attributes.else_branch)
+                        .then_some(Test::Else { chain: head })
+                }
+            } else {
+                None
+            };
+
+            let tests = match is_array {
+                true => Vec::new(),
+                false => self.tests(&child, cx, test),
+            };
+            self.element(&child, cx);
+            self.patch(&tests);
+        }
+    }
+
+    /// Lower a node other than an element
+    fn leaf(&mut self, node: &Node, raw: bool) {
+        let lowered = node.query(|inner| match &inner.data {
+            NodeData::Text { contents } => Some((
+                escape(contents, raw, false).into_owned(),
+                Token::Text(contents.to_string()),
+            )),
+            NodeData::Comment { contents } => Some((
+                format!("<!--{}-->", contents),
+                Token::Comment(contents.to_string()),
+            )),
+            NodeData::Doctype { name, .. } => Some((
+                format!("<!DOCTYPE {}>", name),
+                Token::Doctype(name.to_string()),
+            )),
+            NodeData::ProcessingInstruction { target, contents } => Some((
+                format!("<?{} {}>", target, contents),
+                Token::ProcessingInstruction {
+                    target: target.to_string(),
+                    contents: contents.to_string(),
+                },
+            )),
+            _ => None,
+        });
+        if let Some((html, token)) = lowered.flatten() {
+            self.push_static(&html, token);
+        }
+    }
+
+    /// Start a constraint chain
+    fn chain_head(&mut self, expression: String) -> Test {
+        let chain = self.chains;
+        self.chains += 1;
+        Test::Constraint { expression, chain }
+    }
+
+    /// Push the tests guarding an element, returning their indices so they
+    /// can skip past it once it's lowered
+    fn tests(&mut self, node: &Node, cx: Context, chain: Option<Test>) -> Vec<usize> {
+        if !cx.nested {
+            return Vec::new();
+        }
+        let scope = node
+            .attr("data-scope")
+            .map(|scope| Test::Scope(scope.trim().to_string()));
+        chain
+            .into_iter()
+            .chain(scope)
+            .map(|test| self.push(Op::Test { test, otherwise: 0 }))
+            .collect()
+    }
+
+    /// Point the tests at `indices` past the ops lowered since
+    fn patch(&mut self, indices: &[usize]) {
+        if indices.is_empty() {
+            return;
+        }
+        let end = self.label();
+        for &index in indices {
+            if let Op::Test { otherwise, .. } = &mut self.ops[index] {
+                *otherwise = end;
+            }
+        }
+    }
+
+    /// Mark the next op as a jump target
+    fn label(&mut self) -> usize {
+        self.label = self.ops.len();
+        self.label
+    }
+
+    /// Attribute the ops pushed from now on to the element `node`
+    fn source(&mut self, node: &Node) {
+        self.selectors
+            .push(Parser::element_selector(node, self.attributes));
+    }
+
+    fn push(&mut self, op: Op) -> usize {
+        self.ops.push(op);
+        self.sources.push(self.selectors.len().saturating_sub(1));
+        self.ops.len() - 1
+    }
+
+    fn push_static(&mut self, html: &str, token: Token) {
+        if self.ops.len() <= self.label || !matches!(self.ops.last(), Some(Op::Static(_))) {
+            self.push(Op::Static(Chunk::default()));
+        }
+        if let Some(Op::Static(chunk)) = self.ops.last_mut() {
+            chunk.html.push_str(html);
+            chunk.tokens.push(token);
+        }
+    }
+}
+
+/// Remove the properties targeting the attribute `local` from `properties`
+fn take_properties(properties: &mut Vec<Property>, local: &str) -> Vec<Property> {
+    let (taken, rest) = std::mem::take(properties)
+        .into_iter()
+        .partition(|property| target_attribute(property) == local);
+    *properties = rest;
+    taken
+}
+
+fn target_attribute(property: &Property) -> &str {
+    match &property.target {
+        PropertyTarget::Attribute(name) => name,
+        PropertyTarget::Value | PropertyTarget::TextContent => "value",
+    }
+}
+
+/// Find the `${}` variables in text
+fn text_variables(text: &str) -> Vec<Variable> {
+    crate::parser::VARIABLE_REGEX
+        .captures_iter(text)
+        .map(|cap| Variable {
+            path: crate::utils::split_path_cow(&cap[1]).into_owned(),
+            raw: cap[0].to_string(),
+        })
+        .collect()
+}
+
+/// Serialize an attribute name as the HTML serializer does
+fn attribute_name(name: &QualName) -> String {
+    let prefix = match name.ns {
+        ns!(xml) => "xml:",
+        ns!(xmlns) if name.local != local_name!("xmlns") => "xmlns:",
+        ns!(xlink) => "xlink:",
+        _ => "",
+    };
+    format!("{}{}", prefix, name.local)
+}
+
+/// Escape text or an attribute value as the HTML serializer does
+fn escape(text: &str, raw: bool, attribute: bool) -> Cow<'_, str> {
+    let special = |c: char| match c {
+        '&' | '\u{00A0}' => true,
+        '"' => attribute,
+        '<' | '>' => !attribute,
+        _ => false,
+    };
+    if raw || !text.contains(special) {
+        return Cow::Borrowed(text);
+    }
+
+    let mut escaped = String::with_capacity(text.len() + 8);
+    for c in text.chars() {
+        match c {
+            '&' => escaped.push_str("&amp;"),
+            '\u{00A0}' => escaped.push_str("&nbsp;"),
+            '"' if attribute => escaped.push_str("&quot;"),
+            '<' if !attribute => escaped.push_str("&lt;"),
+            '>' if !attribute => escaped.push_str("&gt;"),
+            c => escaped.push(c),
+        }
+    }
+    Cow::Owned(escaped)
+}
+
+/// Elements whose text the serializer writes without escaping
+fn is_raw_text(name: &QualName) -> bool {
+    name.ns == ns!(html)
+        && matches!(
+            name.local,
+            local_name!("style")
+                | local_name!("script")
+                | local_name!("xmp")
+                | local_name!("iframe")
+                | local_name!("noembed")
+                | local_name!("noframes")
+                | local_name!("plaintext")
+        )
+}
+
+/// Elements with neither children nor an end tag
+fn is_void(name: &QualName) -> bool {
+    name.ns == ns!(html)
+        && matches!(
+            name.local,
+            local_name!("area")
+                | local_name!("base")
+                | local_name!("basefont")
+                | local_name!("bgsound")
+                | local_name!("br")
+                | local_name!("col")
+                | local_name!("embed")
+                | local_name!("frame")
+                | local_name!("hr")
+                | local_name!("img")
+                | local_name!("input")
+                | local_name!("keygen")
+                | local_name!("link")
+                | local_name!("meta")
+                | local_name!("param")
+                | local_name!("source")
+                | local_name!("track")
+                | local_name!("wbr")
+        )
+}
+
+impl fmt::Display for RenderProgram {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        for (index, op) in self.ops.iter().enumerate() {
+            writeln!(f, "{:04} {}", index, op)?;
+        }
+        Ok(())
+    }
+}
+
+impl fmt::Display for Op {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Op::Static(chunk) => write!(f, "static {:?}", chunk.html),
+            Op::Text { text, end } => {
+                write!(f, "text")?;
+                for step in &text.steps {
+                    write!(f, " {}", step)?;
+                }
+                if text.raw {
+                    write!(f, " raw")?;
+                }
+                write!(f, " -> {:04}", end)
+            }
+            Op::Attribute(attribute) => {
+                write!(f, "attribute {}", attribute_name(&attribute.name))?;
+                if let Some(original) = &attribute.original {
+                    write!(f, " = {:?}", original)?;
+                }
+                for property in &attribute.properties {
+                    write!(f, " {}", DisplayProperty(property))?;
+                }
+                if attribute.url {
+                    write!(f, " url")?;
+                }
+                Ok(())
+            }
+            Op::EnterScope { name } => write!(f, "enter-scope {}", name),
+            Op::ExitScope => write!(f, "exit-scope"),
+            Op::Loop { name, end } => write!(f, "loop {} until {:04}", name, end),
+            Op::EndLoop { start } => write!(f, "end-loop {:04}", start),
+            Op::Test { test, otherwise } => write!(f, "test {} else {:04}", test, otherwise),
+            Op::Capture => write!(f, "capture"),
+            Op::Handle { property } => {
+                write!(f, "handle")?;
+                if let Some(property) = property {
+                    write!(f, " {}", property)?;
+                }
+                Ok(())
+            }
+        }
+    }
+}
+
+impl fmt::Display for TextStep {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            TextStep::Item => write!(f, "item"),
+            TextStep::Property(property) => write!(f, "{}", DisplayProperty(property)),
+            TextStep::Variables(variables) => {
+                let raw: Vec<&str> = variables.iter().map(|v| v.raw.as_str()).collect();
+                write!(f, "variables [{}]", raw.join(", "))
+            }
+        }
+    }
+}
+
+impl fmt::Display for Test {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Test::Constraint { expression, chain } => {
+                write!(f, "constraint {:?} chain {}", expression, chain)
+            }
+            Test::ElseIf { expression, chain } => {
+                write!(f, "else-if {:?} chain {}", expression, chain)
+            }
+            Test::Else { chain } => write!(f, "else chain {}", chain),
+            Test::Scope(scope) => write!(f, "scope {:?}", scope),
+        }
+    }
+}
+
+struct DisplayProperty<'p>(&'p Property);
+
+impl fmt::Display for DisplayProperty<'_> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let raw: Vec<&str> = self.0.variables.iter().map(|v| v.raw.as_str()).collect();
+        write!(f, "property {} [{}]", self.0.name, raw.join(", "))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::renderer::root_elements;
+    use crate::HtmlTemplate;
+    use serde_json::json;
+
+    #[test]
+    fn test_program_dump() {
+        let html = r#"
+            <template>
+                <ul class="list">
+                    <li itemprop="items[]" data-constraint="visible">${name}</li>
+                </ul>
+            </template>
+        "#;
+        let template = HtmlTemplate::from_str(html, Some("ul")).unwrap();
+
+        assert_eq!(
+            template.program().to_string(),
+            "0000 static \"<ul class=\\\"list\\\">\\n                    \"\n\
+             0001 loop items until 0007\n\
+             0002 test constraint \"visible\" chain 0 else 0007\n\
+             0003 static \"<li itemprop=\\\"items\\\" data-constraint=\\\"visible\\\">\"\n\
+             0004 text item variables [${name}] -> 0006\n\
+             0005 static \"${name}\"\n\
+             0006 static \"</li>\"\n\
+             0007 end-loop 0001\n\
+             0008 static \"\\n                </ul>\"\n"
+        );
+    }
+
+    #[test]
+    fn test_static_html_matches_serializer() {
+        let html = r##"
+            <template>
+                <div title="a &quot;b&quot; &amp; c&nbsp;d">
+                    <!-- note -->
+                    <p>1 &lt; 2 &amp;&amp; 3 &gt; 2&nbsp;</p>
+                    <script>if (a < b && c) {}</script>
+                    <br><img src="#top" alt="x">
+                    <svg><use xlink:href="#icon"></use></svg>
+                </div>
+            </template>
+        "##;
+        let template = HtmlTemplate::from_str(html, None).unwrap();
+        let compiled = &template.compiled;
+        let doc = compiled.tree.to_document();
+        let expected: String = root_elements(&doc, None)
+            .nodes()
+            .iter()
+            .map(|node| node.html().to_string())
+            .collect();
+
+        assert_eq!(template.render(&json!({})).unwrap(), expected);
+    }
+
+    #[test]
+    fn test_render_nested_loops_and_chains() {
+        let html = r#"
+            <template>
+                <div>
+                    <section itemprop="groups[]">
+                        <h2 itemprop="title"></h2>
+                        <p itemprop="entries[]">${label}</p>
+                        <em data-constraint="open">open</em>
+                        <em data-else="">closed</em>
+                    </section>
+                </div>
+            </template>
+        "#;
+        let template = HtmlTemplate::from_str(html, Some("div")).unwrap();
+        let data = json!({
+            "groups": [
+                {"title": "A", "open": true, "entries": [{"label": "a1"}, {"label": "a2"}]},
+                {"title": "B", "open": false, "entries": []}
+            ]
+        });
+
+        let result = template.render(&data).unwrap();
+        let compact: String = result.split_whitespace().collect::<Vec<_>>().join(" ");
+        assert_eq!(
+            compact,
+            "<div> <section itemprop=\"groups\"> <h2 itemprop=\"title\">A</h2> \
+             <p itemprop=\"entries\">a1</p><p itemprop=\"entries\">a2</p> \
+             <em data-constraint=\"open\">open</em> </section><section itemprop=\"groups\"> \
+             <h2 itemprop=\"title\">B</h2> <em data-else=\"\">closed</em> </section> </div>"
+        );
+    }
+}
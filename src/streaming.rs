@@ -3,31 +3,28 @@
 //! This module provides a streaming interface for rendering templates with
 //! large amounts of data without loading everything into memory at once.
 
-use std::collections::HashMap;
-use std::sync::Arc;
-
-use crate::error::{Error, Result};
-use crate::handlers::ElementHandler;
+#[cfg(feature = "async")]
+use crate::error::Error;
+use crate::error::Result;
 use crate::renderer::Renderer;
 use crate::types::*;
 use crate::value::RenderValue;
 
 /// Streaming renderer that processes data iteratively
+///
+/// Items render with the template's handlers, base URL, constraint
+/// functions and constraint evaluator, exactly as [`HtmlTemplate::render`]
+/// renders them.
 pub struct StreamingRenderer<'a> {
-    template: &'a CompiledTemplate,
-    handlers: &'a HashMap<String, Box<dyn ElementHandler>>,
+    template: &'a HtmlTemplate,
     buffer_size: usize,
 }
 
 impl<'a> StreamingRenderer<'a> {
     /// Create a new streaming renderer
-    pub fn new(
-        template: &'a CompiledTemplate,
-        handlers: &'a HashMap<String, Box<dyn ElementHandler>>,
-    ) -> Self {
+    pub fn new(template: &'a HtmlTemplate) -> Self {
         Self {
             template,
-            handlers,
             buffer_size: 1024, // Default buffer size
         }
     }
@@ -44,14 +41,13 @@ impl<'a> StreamingRenderer<'a> {
         I: IntoIterator<Item = Box<dyn RenderValue>>,
         I::IntoIter: 'static,
     {
-        OwnedStreamingResult::new(self.template, self.handlers, data_stream.into_iter())
+        OwnedStreamingResult::new(self.template, data_stream.into_iter())
             .with_buffer_size(self.buffer_size)
     }
 
     /// Render a single chunk of data
     pub fn render_chunk(&self, data: &dyn RenderValue) -> Result<String> {
-        let renderer = Renderer::new(self.template, self.handlers);
-        renderer.render(data)
+        self.template.render(data)
     }
 
     /// Render multiple items and collect results
@@ -82,8 +78,7 @@ pub struct StreamingResult<'a> {
 
 /// Owned streaming result that doesn't require lifetime management
 pub struct OwnedStreamingResult {
-    template: Arc<CompiledTemplate>,
-    handlers: Arc<HashMap<String, Box<dyn ElementHandler>>>,
+    template: HtmlTemplate,
     data_iter: Box<dyn Iterator<Item = Box<dyn RenderValue>>>,
     buffer: Vec<String>,
     buffer_size: usize,
@@ -154,29 +149,12 @@ impl<'a> StreamingResult<'a> {
     }
 
     /// Write results to a writer with optional separator
-    pub fn write_to<W: std::io::Write>(
-        mut self,
-        mut writer: W,
-        separator: Option<&str>,
-    ) -> Result<()> {
-        let sep = separator.unwrap_or("");
-        let mut first = true;
-
-        while let Some(chunk) = self.next_chunk()? {
-            for item in chunk {
-                if !first && !sep.is_empty() {
-                    writer
-                        .write_all(sep.as_bytes())
-                        .map_err(|e| Error::IoError(e))?;
-                }
-                writer
-                    .write_all(item.as_bytes())
-                    .map_err(|e| Error::IoError(e))?;
-                first = false;
-            }
-        }
-
-        Ok(())
+    ///
+    /// Each item is rendered straight into `writer`, as
+    /// [`HtmlTemplate::render_to`] does, without building a `String`.
+    pub fn write_to<W: std::io::Write>(self, writer: W, separator: Option<&str>) -> Result<()> {
+        let renderer = self.renderer.template.renderer(None);
+        write_items(&renderer, self.data_iter, writer, separator)
     }
 }
 
@@ -189,12 +167,9 @@ pub struct AsyncStreamingRenderer<'a> {
 #[cfg(feature = "async")]
 impl<'a> AsyncStreamingRenderer<'a> {
     /// Create a new async streaming renderer
-    pub fn new(
-        template: &'a CompiledTemplate,
-        handlers: &'a HashMap<String, Box<dyn ElementHandler>>,
-    ) -> Self {
+    pub fn new(template: &'a HtmlTemplate) -> Self {
         Self {
-            renderer: StreamingRenderer::new(template, handlers),
+            renderer: StreamingRenderer::new(template),
         }
     }
 
@@ -268,7 +243,7 @@ impl<'a> AsyncStreamingRenderer<'a> {
 impl HtmlTemplate {
     /// Create a streaming renderer for this template
     pub fn streaming_renderer(&self) -> StreamingRenderer {
-        StreamingRenderer::new(&self.compiled, &self.handlers)
+        StreamingRenderer::new(self)
     }
 
     /// Render a stream of data items
@@ -289,7 +264,7 @@ impl HtmlTemplate {
     /// Create an async streaming renderer
     #[cfg(feature = "async")]
     pub fn async_streaming_renderer(&self) -> AsyncStreamingRenderer {
-        AsyncStreamingRenderer::new(&self.compiled, &self.handlers)
+        AsyncStreamingRenderer::new(self)
     }
 }
 
@@ -423,25 +398,96 @@ mod tests {
         assert!(output.contains("Writer Test"));
         assert!(output.contains("Test description"));
     }
+
+    #[test]
+    fn test_write_to_matches_render() {
+        let template = create_test_template().unwrap();
+        let data = [json!({"name": "First"}), json!({"name": "Second"})];
+        let items: Vec<Box<dyn RenderValue>> = data
+            .iter()
+            .cloned()
+            .map(|item| Box::new(item) as _)
+            .collect();
+
+        let mut buffer = Vec::new();
+        template
+            .render_stream(items)
+            .write_to(&mut buffer, Some("\n"))
+            .unwrap();
+
+        let expected = [
+            template.render(&data[0]).unwrap(),
+            template.render(&data[1]).unwrap(),
+        ]
+        .join("\n");
+        assert_eq!(String::from_utf8(buffer).unwrap(), expected);
+    }
+
+    fn even_template() -> HtmlTemplate {
+        crate::builder::HtmlTemplateBuilder::new()
+            .from_str(
+                r#"<template><div><p data-constraint="isEven(count)">Even</p></div></template>"#,
+            )
+            .register_constraint_function("isEven", |args| {
+                Ok(json!(args[0].as_i64().is_some_and(|n| n % 2 == 0)))
+            })
+            .no_caching()
+            .build()
+            .unwrap()
+    }
+
+    #[test]
+    fn test_streams_use_constraint_functions() {
+        let template = even_template();
+        let items: Vec<Box<dyn RenderValue>> =
+            vec![Box::new(json!({"count": 2})), Box::new(json!({"count": 3}))];
+
+        let results = template.render_stream(items).collect_all().unwrap();
+        assert!(results[0].contains("Even"));
+        assert!(!results[1].contains("Even"));
+
+        let data = json!({"count": 4});
+        let renderer = template.streaming_renderer();
+        assert!(renderer.render_chunk(&data).unwrap().contains("Even"));
+    }
+
+    #[test]
+    fn test_write_to_uses_constraint_functions() {
+        let template = even_template();
+        let data = [json!({"count": 2}), json!({"count": 3})];
+        let items: Vec<Box<dyn RenderValue>> = data
+            .iter()
+            .cloned()
+            .map(|item| Box::new(item) as _)
+            .collect();
+
+        let mut buffer = Vec::new();
+        template
+            .render_stream(items)
+            .write_to(&mut buffer, Some("|"))
+            .unwrap();
+
+        let expected = [
+            template.render(&data[0]).unwrap(),
+            template.render(&data[1]).unwrap(),
+        ]
+        .join("|");
+        assert_eq!(String::from_utf8(buffer).unwrap(), expected);
+        assert_eq!(expected.matches(">Even<").count(), 1);
+    }
 }
 
 impl OwnedStreamingResult {
     /// Create a new owned streaming result
-    pub fn new<I>(
-        template: &CompiledTemplate,
-        _handlers: &HashMap<String, Box<dyn ElementHandler>>,
-        data_iter: I,
-    ) -> Self
+    ///
+    /// The result keeps a clone of `template`, which shares its compiled
+    /// template, handlers and configuration.
+    pub fn new<I>(template: &HtmlTemplate, data_iter: I) -> Self
     where
         I: Iterator<Item = Box<dyn RenderValue>> + 'static,
     {
-        // Create a new empty handlers map for now, since ElementHandler doesn't implement Clone
-        // In practice, this would need a different approach if handlers are required
-        let empty_handlers = HashMap::new();
-
         Self {
-            template: Arc::new(template.clone()),
-            handlers: Arc::new(empty_handlers),
+            template: template.clone(),
             data_iter: Box::new(data_iter),
             buffer: Vec::new(),
             buffer_size: 1024,
@@ -457,8 +503,7 @@ impl OwnedStreamingResult {
 
     /// Render a single chunk of data
     pub fn render_chunk(&self, data: &dyn RenderValue) -> Result<String> {
-        let renderer = Renderer::new(&self.template, &self.handlers);
-        renderer.render(data)
+        self.template.render(data)
     }
 
     /// Collect all results into a vector
@@ -511,28 +556,27 @@ impl OwnedStreamingResult {
     }
 
     /// Write results to a writer with optional separator
-    pub fn write_to<W: std::io::Write>(
-        mut self,
-        mut writer: W,
-        separator: Option<&str>,
-    ) -> Result<()> {
-        let sep = separator.unwrap_or("");
-        let mut first = true;
+    ///
+    /// Each item is rendered straight into `writer`, as
+    /// [`HtmlTemplate::render_to`] does, without building a `String`.
+    pub fn write_to<W: std::io::Write>(self, writer: W, separator: Option<&str>) -> Result<()> {
+        let renderer = self.template.renderer(None);
+        write_items(&renderer, self.data_iter, writer, separator)
+    }
+}
 
-        while let Some(chunk) = self.next_chunk()? {
-            for item in chunk {
-                if !first && !sep.is_empty() {
-                    writer
-                        .write_all(sep.as_bytes())
-                        .map_err(|e| Error::IoError(e))?;
-                }
-                writer
-                    .write_all(item.as_bytes())
-                    .map_err(|e| Error::IoError(e))?;
-                first = false;
-            }
+/// Render each item into `writer`, with `separator` between items
+fn write_items<W: std::io::Write>(
+    renderer: &Renderer<'_>,
+    items: impl Iterator<Item = Box<dyn RenderValue>>,
+    mut writer: W,
+    separator: Option<&str>,
+) -> Result<()> {
+    for (index, data) in items.enumerate() {
+        if let Some(separator) = separator.filter(|_| index > 0) {
+            writer.write_all(separator.as_bytes())?;
         }
-
-        Ok(())
+        renderer.render_to(data.as_ref(), &mut writer)?;
     }
+    Ok(())
 }
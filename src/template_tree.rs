@@ -0,0 +1,259 @@
+//! Pre-parsed template nodes
+//!
+//! `dom_query` documents are neither `Send` nor `Sync`, so a compiled
+//! template can't hold one. [`TemplateTree`] keeps an owned copy of the
+//! parsed nodes instead, which is turned back into a document for each render
+//! without running the HTML parser.
+
+use std::fmt;
+
+use dom_query::{Document, Element, Node, NodeData, NodeId, Tree};
+use html5ever::tendril::StrTendril;
+use html5ever::{Attribute, QualName};
+
+/// An immutable copy of a parsed node tree
+#[derive(Clone)]
+pub(crate) struct TemplateTree {
+    /// Nodes in document order, starting with the document root
+    nodes: Vec<TreeNode>,
+}
+
+#[derive(Clone)]
+struct TreeNode {
+    data: TreeNodeData,
+    /// The contents of a `<template>` element
+    template_contents: Option<usize>,
+    children: Vec<usize>,
+}
+
+#[derive(Clone)]
+enum TreeNodeData {
+    Document,
+    Fragment,
+    Doctype {
+        name: String,
+        public_id: String,
+        system_id: String,
+    },
+    Text(String),
+    Comment(String),
+    Element {
+        name: QualName,
+        attrs: Vec<(QualName, String)>,
+    },
+    ProcessingInstruction {
+        target: String,
+        contents: String,
+    },
+}
+
+impl TemplateTree {
+    /// Copy every node of `document`
+    pub(crate) fn from_document(document: &Document) -> Self {
+        let mut tree = Self { nodes: Vec::new() };
+        tree.push(&document.root());
+        tree
+    }
+
+    /// Build a new document holding a copy of the nodes
+    pub(crate) fn to_document(&self) -> Document {
+        let document = Document::default();
+        let tree = &document.tree;
+
+        // The first node is the root, which the new document already has
+        let ids: Vec<NodeId> = std::iter::once(tree.root_id())
+            .chain(
+                self.nodes[1..]
+                    .iter()
+                    .map(|node| tree.create_node(node.data.to_node_data())),
+            )
+            .collect();
+
+        for (node, id) in self.nodes.iter().zip(&ids) {
+            for &child in &node.children {
+                tree.append_child_of(id, &ids[child]);
+            }
+            if let Some(contents) = node.template_contents {
+                tree.update_node(id, |inner| {
+                    if let NodeData::Element(element) = &mut inner.data {
+                        element.template_contents = Some(ids[contents]);
+                    }
+                });
+            }
+        }
+
+        document
+    }
+
+    fn push(&mut self, node: &Node) -> usize {
+        let index = self.nodes.len();
+        let (data, contents) = node
+            .query(|inner| TreeNodeData::from_node_data(&inner.data))
+            .unwrap_or((TreeNodeData::Fragment, None));
+        self.nodes.push(TreeNode {
+            data,
+            template_contents: None,
+            children: Vec::new(),
+        });
+
+        if let Some(contents) = contents {
+            let contents = self.push(&Node::new(contents, node.tree));
+            self.nodes[index].template_contents = Some(contents);
+        }
+        for child in node.children() {
+            let child = self.push(&child);
+            self.nodes[index].children.push(child);
+        }
+        index
+    }
+}
+
+impl fmt::Debug for TemplateTree {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("TemplateTree")
+            .field("nodes", &self.nodes.len())
+            .finish()
+    }
+}
+
+impl TreeNodeData {
+    /// Copy `data`, along with the id of its template contents if it has any
+    fn from_node_data(data: &NodeData) -> (Self, Option<NodeId>) {
+        match data {
+            NodeData::Document => (Self::Document, None),
+            NodeData::Fragment => (Self::Fragment, None),
+            NodeData::Doctype {
+                name,
+                public_id,
+                system_id,
+            } => (
+                Self::Doctype {
+                    name: name.to_string(),
+                    public_id: public_id.to_string(),
+                    system_id: system_id.to_string(),
+                },
+                None,
+            ),
+            NodeData::Text { contents } => (Self::Text(contents.to_string()), None),
+            NodeData::Comment { contents } => (Self::Comment(contents.to_string()), None),
+            NodeData::Element(element) => (
+                Self::Element {
+                    name: element.name.clone(),
+                    attrs: element
+                        .attrs
+                        .iter()
+                        .map(|attr| (attr.name.clone(), attr.value.to_string()))
+                        .collect(),
+                },
+                element.template_contents,
+            ),
+            NodeData::ProcessingInstruction { target, contents } => (
+                Self::ProcessingInstruction {
+                    target: target.to_string(),
+                    contents: contents.to_string(),
+                },
+                None,
+            ),
+        }
+    }
+
+    fn to_node_data(&self) -> NodeData {
+        match self {
+            Self::Document => NodeData::Document,
+            Self::Fragment => NodeData::Fragment,
+            Self::Doctype {
+                name,
+                public_id,
+                system_id,
+            } => NodeData::Doctype {
+                name: StrTendril::from(name.as_str()),
+                public_id: StrTendril::from(public_id.as_str()),
+                system_id: StrTendril::from(system_id.as_str()),
+            },
+            Self::Text(contents) => NodeData::Text {
+                contents: StrTendril::from(contents.as_str()),
+            },
+            Self::Comment(contents) => NodeData::Comment {
+                contents: StrTendril::from(contents.as_str()),
+            },
+            Self::Element { name, attrs } => NodeData::Element(Element::new(
+                name.clone(),
+                attrs
+                    .iter()
+                    .map(|(name, value)| Attribute {
+                        name: name.clone(),
+                        value: StrTendril::from(value.as_str()),
+                    })
+                    .collect(),
+                None,
+                false,
+            )),
+            Self::ProcessingInstruction { target, contents } => NodeData::ProcessingInstruction {
+                target: StrTendril::from(target.as_str()),
+                contents: StrTendril::from(contents.as_str()),
+            },
+        }
+    }
+}
+
+/// Copy `node` and its descendants into `tree`, which may be the tree `node`
+/// belongs to, and return the id of the detached copy
+pub(crate) fn copy_node(node: &Node, tree: &Tree<NodeData>) -> NodeId {
+    let mut data = node
+        .query(|inner| inner.data.clone())
+        .unwrap_or(NodeData::Fragment);
+    if let NodeData::Element(element) = &mut data {
+        if let Some(contents) = element.template_contents {
+            element.template_contents = Some(copy_node(&Node::new(contents, node.tree), tree));
+        }
+    }
+
+    let id = tree.create_node(data);
+    for child in node.children() {
+        let child = copy_node(&child, tree);
+        tree.append_child_of(&id, &child);
+    }
+    id
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_template_tree_round_trip() {
+        let html = r#"<!DOCTYPE html><html><head></head><body><ul><li itemprop="items[]" class="a">${name} &amp; <b>x</b></li></ul><!-- note --><template><p>inner</p></template></body></html>"#;
+        let parsed = Document::from(html);
+        let tree = TemplateTree::from_document(&parsed);
+
+        let rebuilt = tree.to_document();
+        assert_eq!(rebuilt.html(), parsed.html());
+
+        let template = rebuilt.select("template").nodes()[0].clone();
+        let contents = template
+            .query(|inner| {
+                inner
+                    .as_element()
+                    .and_then(|element| element.template_contents)
+            })
+            .flatten()
+            .expect("template contents are copied");
+        assert_eq!(
+            Node::new(contents, template.tree).inner_html().to_string(),
+            "<p>inner</p>"
+        );
+    }
+
+    #[test]
+    fn test_copy_node() {
+        let doc = Document::from("<div><p id=\"a\">one <b>two</b></p></div>");
+        let p = doc.select("#a").nodes()[0].clone();
+
+        let copy = copy_node(&p, &doc.tree);
+        p.append_prev_sibling(&copy);
+        assert_eq!(
+            doc.select("div").html().to_string(),
+            "<div><p id=\"a\">one <b>two</b></p><p id=\"a\">one <b>two</b></p></div>"
+        );
+    }
+}
@@ -1,10 +1,17 @@
 //! Test utilities for HTML template testing
 //!
 //! This module provides helpful utilities for testing HTML templates,
-//! including HTML normalization and comparison functions.
+//! including HTML normalization and comparison functions, and, with the
+//! `test-utils` feature, a loopback HTTP server for exercising
+//! cross-document fetching.
 
 use dom_query::Document;
 
+#[cfg(any(test, feature = "test-utils"))]
+mod http;
+#[cfg(any(test, feature = "test-utils"))]
+pub use http::{MockHttpServer, MockResponse, RecordedRequest};
+
 /// Normalize HTML for comparison by parsing and re-serializing
 ///
 /// This function:
@@ -8,6 +8,7 @@
 //! - [`HtmlTemplate`] - The main template type for rendering HTML
 //! - [`TemplateConfig`] - Configuration options for template behavior
 //! - [`CacheMode`] - Cache strategies for improved performance
+//! - [`RenderPosition`] - Where rendered nodes go in an existing document
 //! - [`CompiledTemplate`] - Internal compiled template representation
 //!
 //! # Usage
@@ -27,12 +28,18 @@
 //! ```
 
 use dom_query::Document;
-use std::collections::HashMap;
+use std::collections::{BTreeSet, HashMap};
 use std::sync::Arc;
 
 use crate::cache::{get_global_cache, TemplateCache, TemplateCacheKey};
+use crate::constraints::{
+    ConstraintAttributes, ConstraintChecker, ConstraintEvaluator, ConstraintFunctionRegistry,
+    ConstraintWarning, Expression,
+};
 use crate::error::{Error, Result};
 use crate::handlers::{ElementHandler, HandlerRegistry};
+use crate::program::RenderProgram;
+use crate::template_tree::TemplateTree;
 use crate::value::RenderValue;
 
 /// Main template type for HTML rendering with microdata support
@@ -99,8 +106,11 @@ use crate::value::RenderValue;
 pub struct HtmlTemplate {
     pub(crate) compiled: Arc<CompiledTemplate>,
     pub(crate) config: TemplateConfig,
-    pub(crate) handlers: HashMap<String, Box<dyn ElementHandler>>,
-    pub(crate) handler_registry: Option<HandlerRegistry>,
+    pub(crate) handlers: Arc<HashMap<String, Box<dyn ElementHandler>>>,
+    pub(crate) handler_registry: Option<Arc<HandlerRegistry>>,
+    pub(crate) constraint_functions: Option<ConstraintFunctionRegistry>,
+    /// The configured base URL combined with the template's `<base href>`
+    pub(crate) base_url: Option<url::Url>,
 }
 
 impl Clone for HtmlTemplate {
@@ -108,10 +118,11 @@ impl Clone for HtmlTemplate {
         Self {
             compiled: self.compiled.clone(),
             config: self.config.clone(),
-            // Note: handlers are not cloned as ElementHandler doesn't implement Clone
-            // This means cached templates will have empty handlers
-            handlers: HashMap::new(),
-            handler_registry: None,
+            // Handlers aren't Clone, so clones share them
+            handlers: self.handlers.clone(),
+            handler_registry: self.handler_registry.clone(),
+            constraint_functions: self.constraint_functions.clone(),
+            base_url: self.base_url.clone(),
         }
     }
 }
@@ -128,16 +139,22 @@ impl std::fmt::Debug for HtmlTemplate {
 
 impl HtmlTemplate {
     /// Create a new HtmlTemplate from compiled template
+    ///
+    /// An invalid configured base URL is ignored here; the `from_*`
+    /// constructors report it as an error.
     pub fn new(
         compiled: Arc<CompiledTemplate>,
         config: TemplateConfig,
         handlers: std::collections::HashMap<String, Box<dyn ElementHandler>>,
     ) -> Self {
+        let base_url = base_url(&config, &compiled).unwrap_or_default();
         Self {
             compiled,
             config,
-            handlers,
+            handlers: Arc::new(handlers),
             handler_registry: None,
+            constraint_functions: None,
+            base_url,
         }
     }
 
@@ -147,11 +164,14 @@ impl HtmlTemplate {
         config: TemplateConfig,
         handler_registry: HandlerRegistry,
     ) -> Self {
+        let base_url = base_url(&config, &compiled).unwrap_or_default();
         Self {
             compiled,
             config,
-            handlers: HashMap::new(),
-            handler_registry: Some(handler_registry),
+            handlers: Arc::default(),
+            handler_registry: Some(Arc::new(handler_registry)),
+            constraint_functions: None,
+            base_url,
         }
     }
 
@@ -168,22 +188,22 @@ impl HtmlTemplate {
     ) -> Result<Self> {
         let compiled = if config.cache_mode != CacheMode::None {
             // Use caching
-            let cache_key = TemplateCacheKey::new(html, selector);
+            let cache_key = TemplateCacheKey::for_config(html, selector, &config);
             let cache = get_global_cache();
 
             cache.get_or_compile_template(&cache_key, || {
-                crate::compiler::Compiler::compile(html, selector)
+                crate::compiler::Compiler::compile_with_config(html, selector, &config)
             })?
         } else {
             // Direct compilation without caching
-            crate::compiler::Compiler::compile(html, selector)?
+            crate::compiler::Compiler::compile_with_config(html, selector, &config)?
         };
 
-        Ok(Self::new(
-            compiled,
-            config,
-            std::collections::HashMap::new(),
-        ))
+        let base_url = base_url(&config, &compiled)?;
+        Ok(Self {
+            base_url,
+            ..Self::new(compiled, config, std::collections::HashMap::new())
+        })
     }
 
     /// Create a template from HTML string using a custom cache
@@ -194,19 +214,19 @@ impl HtmlTemplate {
         cache: &TemplateCache,
     ) -> Result<Self> {
         let compiled = if config.cache_mode != CacheMode::None {
-            let cache_key = TemplateCacheKey::new(html, selector);
+            let cache_key = TemplateCacheKey::for_config(html, selector, &config);
             cache.get_or_compile_template(&cache_key, || {
-                crate::compiler::Compiler::compile(html, selector)
+                crate::compiler::Compiler::compile_with_config(html, selector, &config)
             })?
         } else {
-            crate::compiler::Compiler::compile(html, selector)?
+            crate::compiler::Compiler::compile_with_config(html, selector, &config)?
         };
 
-        Ok(Self::new(
-            compiled,
-            config,
-            std::collections::HashMap::new(),
-        ))
+        let base_url = base_url(&config, &compiled)?;
+        Ok(Self {
+            base_url,
+            ..Self::new(compiled, config, std::collections::HashMap::new())
+        })
     }
 
     /// Create a template from a DOM element
@@ -272,12 +292,217 @@ impl HtmlTemplate {
 
     /// Render the template with the given data
     pub fn render(&self, data: &dyn RenderValue) -> Result<String> {
+        self.render_in_scopes(data, None)
+    }
+
+    /// Render the template, keeping only the `data-scope` sections whose
+    /// scope is one of `scopes`
+    ///
+    /// Scopes are typically the caller's roles or feature flags. Elements
+    /// without `data-scope` are unaffected.
+    ///
+    /// # Examples
+    ///
+    /// ```rust,ignore
+    /// let html = template.render_with_scopes(&data, &["admin", "beta"])?;
+    /// ```
+    pub fn render_with_scopes<S: AsRef<str>>(
+        &self,
+        data: &dyn RenderValue,
+        scopes: &[S],
+    ) -> Result<String> {
+        let scopes: Vec<String> = scopes.iter().map(|s| s.as_ref().to_string()).collect();
+        self.render_in_scopes(data, Some(&scopes))
+    }
+
+    /// Render the template straight into `writer`, such as a file, socket or
+    /// pooled response buffer, without building the output as a `String`
+    ///
+    /// Errors from the writer are returned as [`Error::IoError`].
+    ///
+    /// # Examples
+    ///
+    /// ```rust,ignore
+    /// let mut body = Vec::with_capacity(8 * 1024);
+    /// template.render_to(&data, &mut body)?;
+    /// ```
+    pub fn render_to<W: std::io::Write + ?Sized>(
+        &self,
+        data: &dyn RenderValue,
+        writer: &mut W,
+    ) -> Result<()> {
+        self.renderer(None).render_to(data, writer)
+    }
+
+    /// Render the template into a [`std::fmt::Write`] sink, such as a
+    /// `String` being built or a `fmt::Formatter`
+    ///
+    /// A formatting error is returned as [`Error::IoError`].
+    pub fn render_to_fmt<W: std::fmt::Write + ?Sized>(
+        &self,
+        data: &dyn RenderValue,
+        writer: &mut W,
+    ) -> Result<()> {
+        self.render_to(data, &mut crate::renderer::FmtWriter(writer))
+    }
+
+    /// Render the template into a new document holding just the rendered nodes
+    ///
+    /// The rendered elements are direct children of the document root,
+    /// without `<html>` or `<body>` wrappers, so `document.html()` is the
+    /// same HTML [`render`](Self::render) returns.
+    pub fn render_document(&self, data: &dyn RenderValue) -> Result<Document> {
+        let document = Document::default();
+        let root = document.root();
+        for id in self.renderer(None).render_nodes(data, &document.tree)? {
+            root.append_child(&id);
+        }
+        Ok(document)
+    }
+
+    /// Render the template straight into the document `target` belongs to,
+    /// placing the rendered nodes relative to `target` as `position` says
+    ///
+    /// Nodes are created in the caller's document without serializing and
+    /// re-parsing HTML. If rendering fails, the document is left unchanged.
+    ///
+    /// # Examples
+    ///
+    /// ```rust,ignore
+    /// let page = Document::from(layout_html);
+    /// let main = page.select("main").nodes()[0].clone();
+    /// template.render_into(&data, &main, RenderPosition::Append)?;
+    /// ```
+    pub fn render_into(
+        &self,
+        data: &dyn RenderValue,
+        target: &dom_query::Node,
+        position: RenderPosition,
+    ) -> Result<()> {
+        if position == RenderPosition::Replace && target.parent().is_none() {
+            return Err(Error::render_static(
+                "Cannot replace a node that has no parent",
+            ));
+        }
+
+        let nodes = self.renderer(None).render_nodes(data, target.tree)?;
+        match position {
+            RenderPosition::Append => {
+                for id in &nodes {
+                    target.append_child(id);
+                }
+            }
+            RenderPosition::Prepend => match target.first_child() {
+                Some(first) => {
+                    for id in &nodes {
+                        first.append_prev_sibling(id);
+                    }
+                }
+                None => {
+                    for id in &nodes {
+                        target.append_child(id);
+                    }
+                }
+            },
+            RenderPosition::Replace => {
+                for id in &nodes {
+                    target.append_prev_sibling(id);
+                }
+                target.remove_from_parent();
+            }
+        }
+        Ok(())
+    }
+
+    /// Render with optional active scopes; `None` keeps every section
+    pub(crate) fn render_in_scopes(
+        &self,
+        data: &dyn RenderValue,
+        scopes: Option<&[String]>,
+    ) -> Result<String> {
+        self.renderer(scopes).render(data)
+    }
+
+    /// Create a renderer with this template's handlers, configuration and
+    /// constraint functions
+    pub(crate) fn renderer<'a>(
+        &'a self,
+        scopes: Option<&'a [String]>,
+    ) -> crate::renderer::Renderer<'a> {
         let renderer = if let Some(ref registry) = self.handler_registry {
             crate::renderer::Renderer::new_with_registry(&self.compiled, registry, &self.handlers)
         } else {
             crate::renderer::Renderer::new(&self.compiled, &self.handlers)
         };
-        renderer.render(data)
+        renderer
+            .with_base_url(self.base_url.as_ref())
+            .with_constraint_functions(self.constraint_functions.as_ref())
+            .with_constraint_evaluator(self.config.constraint_evaluator.as_deref())
+            .with_scopes(scopes)
+    }
+
+    /// Set the functions that `data-constraint` expressions can call
+    pub fn with_constraint_functions(mut self, functions: ConstraintFunctionRegistry) -> Self {
+        self.constraint_functions = Some(functions);
+        self
+    }
+
+    /// Check the template's constraint expressions for likely mistakes
+    ///
+    /// Reports paths that no `itemprop` or `${}` binding uses, such as a typo
+    /// in `data-constraint="hasDescripton"`, calls to unregistered functions,
+    /// comparisons between incompatible values and conditions that are always
+    /// true or always false. Expressions left to a custom
+    /// [`ConstraintEvaluator`] are not checked.
+    ///
+    /// # Examples
+    ///
+    /// ```rust,ignore
+    /// for warning in template.check_constraints() {
+    ///     eprintln!("{}", warning);
+    /// }
+    /// ```
+    pub fn check_constraints(&self) -> Vec<ConstraintWarning> {
+        self.check_constraints_with(None)
+    }
+
+    /// Check constraint expressions like [`check_constraints`](Self::check_constraints),
+    /// but look up paths and `@id` references in `sample` data instead of the
+    /// template's bindings
+    ///
+    /// A path counts as present if it resolves at the top of `sample` or in
+    /// any object or array nested in it.
+    pub fn check_constraints_against(&self, sample: &serde_json::Value) -> Vec<ConstraintWarning> {
+        self.check_constraints_with(Some(sample))
+    }
+
+    /// Get the program the template was compiled into
+    ///
+    /// Printing it lists the ops a render runs, which shows how each
+    /// binding, loop and constraint was understood.
+    ///
+    /// # Examples
+    ///
+    /// ```rust,ignore
+    /// println!("{}", template.program());
+    /// ```
+    pub fn program(&self) -> &RenderProgram {
+        &self.compiled.program
+    }
+
+    fn check_constraints_with(&self, sample: Option<&serde_json::Value>) -> Vec<ConstraintWarning> {
+        let bindings = self.compiled.bound_names();
+        let mut checker = ConstraintChecker::new(&bindings, self.constraint_functions.as_ref());
+        if let Some(sample) = sample {
+            checker = checker.with_sample(sample);
+        }
+
+        let mut expressions: Vec<_> = self.compiled.expressions.iter().collect();
+        expressions.sort_by_key(|(source, _)| *source);
+        expressions
+            .into_iter()
+            .flat_map(|(source, expr)| checker.check(source, expr))
+            .collect()
     }
 
     /// Render template using microdata extracted from a DOM element
@@ -315,18 +540,50 @@ impl HtmlTemplate {
 pub struct CompiledTemplate {
     pub(crate) root_selector: Option<String>,
     pub(crate) elements: Vec<TemplateElement>,
-    pub(crate) constraints: Vec<Constraint>,
-    #[allow(dead_code)]
+    /// Parsed `data-constraint` expressions, keyed by their source
+    pub(crate) expressions: HashMap<String, Arc<Expression>>,
+    /// Attribute names the constraints were read from
+    pub(crate) constraint_attributes: ConstraintAttributes,
     pub(crate) base_uri: Option<String>,
     pub(crate) template_html: String,
+    /// Parsed template content, copied into a new document for each render
+    pub(crate) tree: TemplateTree,
+    /// The template lowered into ops, built by the compiler
+    pub(crate) program: RenderProgram,
+}
+
+impl CompiledTemplate {
+    /// Names bound by `itemprop` attributes and the segments of `${}` paths
+    pub(crate) fn bound_names(&self) -> BTreeSet<String> {
+        let mut names = BTreeSet::new();
+        for property in self.elements.iter().flat_map(|element| &element.properties) {
+            names.insert(property.name.clone());
+        }
+
+        // `${}` paths are found in the whole template, including elements
+        // without itemprop
+        for captures in crate::parser::VARIABLE_REGEX.captures_iter(&self.template_html) {
+            for segment in captures[1].split('.') {
+                let name = segment.split('[').next().unwrap_or_default().trim();
+                if !name.is_empty() {
+                    names.insert(name.to_string());
+                }
+            }
+        }
+        names
+    }
+}
+
+/// Get the base URL that rendered URL attributes of `compiled` are resolved
+/// against under `config`
+fn base_url(config: &TemplateConfig, compiled: &CompiledTemplate) -> Result<Option<url::Url>> {
+    crate::url_resolver::template_base_url(config.base_url.as_deref(), compiled.base_uri.as_deref())
 }
 
 #[derive(Debug, Clone)]
 pub struct TemplateElement {
     pub(crate) selector: String,
     pub(crate) properties: Vec<Property>,
-    pub(crate) is_array: bool,
-    pub(crate) is_scope: bool,
     #[allow(dead_code)]
     pub(crate) itemtype: Option<String>,
     #[allow(dead_code)]
@@ -355,12 +612,29 @@ pub struct Variable {
     pub(crate) raw: String,
 }
 
-#[derive(Debug, Clone)]
+#[derive(Clone)]
 pub struct TemplateConfig {
     pub(crate) cache_mode: CacheMode,
     pub(crate) zero_copy: bool,
     pub(crate) cache_compiled_templates: bool,
     pub(crate) cache_external_documents: bool,
+    pub(crate) base_url: Option<String>,
+    pub(crate) constraint_attributes: ConstraintAttributes,
+    pub(crate) constraint_evaluator: Option<Arc<dyn ConstraintEvaluator>>,
+}
+
+impl std::fmt::Debug for TemplateConfig {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("TemplateConfig")
+            .field("cache_mode", &self.cache_mode)
+            .field("zero_copy", &self.zero_copy)
+            .field("cache_compiled_templates", &self.cache_compiled_templates)
+            .field("cache_external_documents", &self.cache_external_documents)
+            .field("base_url", &self.base_url)
+            .field("constraint_attributes", &self.constraint_attributes)
+            .field("constraint_evaluator", &self.constraint_evaluator.is_some())
+            .finish()
+    }
 }
 
 impl TemplateConfig {
@@ -393,6 +667,34 @@ impl TemplateConfig {
         self
     }
 
+    /// Resolve relative URL attributes in rendered output against `base_url`
+    ///
+    /// A `<base href>` in the template document is resolved against this URL.
+    /// Without a configured base, URL attributes are rendered verbatim, even
+    /// if the template has a `<base href>`. An invalid URL makes building the
+    /// template fail.
+    pub fn with_base_url<S: Into<String>>(mut self, base_url: S) -> Self {
+        self.base_url = Some(base_url.into());
+        self
+    }
+
+    /// Read constraints from these attributes instead of `data-constraint`,
+    /// `data-else-if` and `data-else`
+    pub fn with_constraint_attributes(mut self, attributes: ConstraintAttributes) -> Self {
+        self.constraint_attributes = attributes;
+        self
+    }
+
+    /// Evaluate constraint expressions with `evaluator` instead of the
+    /// built-in grammar
+    pub fn with_constraint_evaluator<E>(mut self, evaluator: E) -> Self
+    where
+        E: ConstraintEvaluator + 'static,
+    {
+        self.constraint_evaluator = Some(Arc::new(evaluator));
+        self
+    }
+
     /// Create configuration for aggressive caching
     pub fn aggressive_caching() -> Self {
         Self {
@@ -400,6 +702,9 @@ impl TemplateConfig {
             zero_copy: true,
             cache_compiled_templates: true,
             cache_external_documents: true,
+            base_url: None,
+            constraint_attributes: ConstraintAttributes::default(),
+            constraint_evaluator: None,
         }
     }
 
@@ -410,6 +715,9 @@ impl TemplateConfig {
             zero_copy: true,
             cache_compiled_templates: false,
             cache_external_documents: false,
+            base_url: None,
+            constraint_attributes: ConstraintAttributes::default(),
+            constraint_evaluator: None,
         }
     }
 
@@ -426,6 +734,15 @@ impl TemplateConfig {
     pub fn cache_external_documents(&self) -> bool {
         self.cache_external_documents
     }
+    pub fn base_url(&self) -> Option<&str> {
+        self.base_url.as_deref()
+    }
+    pub fn constraint_attributes(&self) -> &ConstraintAttributes {
+        &self.constraint_attributes
+    }
+    pub fn constraint_evaluator(&self) -> Option<&dyn ConstraintEvaluator> {
+        self.constraint_evaluator.as_deref()
+    }
 }
 
 impl Default for TemplateConfig {
@@ -435,6 +752,9 @@ impl Default for TemplateConfig {
             zero_copy: true,
             cache_compiled_templates: true,
             cache_external_documents: true,
+            base_url: None,
+            constraint_attributes: ConstraintAttributes::default(),
+            constraint_evaluator: None,
         }
     }
 }
@@ -446,9 +766,19 @@ pub enum CacheMode {
     Aggressive,
 }
 
+/// Where [`HtmlTemplate::render_into`] places the rendered nodes
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum RenderPosition {
+    /// After the target's last child
+    Append,
+    /// Before the target's first child
+    Prepend,
+    /// In place of the target itself
+    Replace,
+}
+
 #[derive(Debug, Clone)]
 pub struct Constraint {
-    pub(crate) element_selector: String,
     pub(crate) constraint_type: ConstraintType,
     #[allow(dead_code)]
     pub(crate) scope: Option<String>,
@@ -457,7 +787,40 @@ pub struct Constraint {
 #[derive(Debug, Clone)]
 pub enum ConstraintType {
     Scope(String),
-    Expression(String),
+    Expression(CompiledExpression),
+}
+
+/// A `data-constraint` expression parsed when the template is compiled
+#[derive(Debug, Clone)]
+pub struct CompiledExpression {
+    source: String,
+    ast: Arc<Expression>,
+}
+
+impl CompiledExpression {
+    /// Parse a constraint expression
+    pub fn parse(source: &str) -> Result<Self> {
+        Ok(Self {
+            source: source.to_string(),
+            ast: Arc::new(Expression::parse(source)?),
+        })
+    }
+
+    /// Get the expression as written in the template
+    pub fn source(&self) -> &str {
+        &self.source
+    }
+
+    /// Get the parsed expression
+    pub fn ast(&self) -> &Arc<Expression> {
+        &self.ast
+    }
+}
+
+impl PartialEq<str> for CompiledExpression {
+    fn eq(&self, other: &str) -> bool {
+        self.source == other
+    }
 }
 
 #[derive(Debug, Clone)]
@@ -692,4 +1055,210 @@ mod tests {
             .to_string()
             .contains("Failed to read template file"));
     }
+
+    #[test]
+    fn test_render_with_base_url() {
+        let html = r##"
+            <template>
+                <div>
+                    <a itemprop="link" href="${path}">Read</a>
+                    <img src="logo.png">
+                    <a href="#top">Top</a>
+                    <a href="https://other.org/">Other</a>
+                </div>
+            </template>
+        "##;
+        let data = serde_json::json!({"link": "x", "path": "posts/1"});
+
+        // Verbatim without a base
+        let template = HtmlTemplate::from_str(html, Some("div")).unwrap();
+        let output = template.render(&data).unwrap();
+        assert!(output.contains(r#"href="posts/1""#));
+        assert!(output.contains(r#"src="logo.png""#));
+
+        let config = TemplateConfig::default().with_base_url("https://example.com/blog/");
+        let template = HtmlTemplate::from_str_with_config(html, Some("div"), config).unwrap();
+        let output = template.render(&data).unwrap();
+        assert!(output.contains(r#"href="https://example.com/blog/posts/1""#));
+        assert!(output.contains(r#"src="https://example.com/blog/logo.png""#));
+        assert!(output.contains(r##"href="#top""##));
+        assert!(output.contains(r#"href="https://other.org/""#));
+    }
+
+    #[test]
+    fn test_render_with_template_base_element() {
+        let html = r#"
+            <base href="/static/">
+            <template>
+                <img itemprop="image" src="${file}">
+            </template>
+        "#;
+        let data = serde_json::json!({"image": "", "file": "a.png"});
+
+        let config = TemplateConfig::default().with_base_url("https://cdn.example.com/v1/");
+        let template = HtmlTemplate::from_str_with_config(html, Some("img"), config).unwrap();
+        let output = template.render(&data).unwrap();
+        assert!(output.contains(r#"src="https://cdn.example.com/static/a.png""#));
+
+        // The template base alone leaves URLs as they are
+        let template = HtmlTemplate::from_str(html, Some("img")).unwrap();
+        let output = template.render(&data).unwrap();
+        assert!(output.contains(r#"src="a.png""#));
+
+        let config = TemplateConfig::default().with_base_url("not a url");
+        let error = HtmlTemplate::from_str_with_config(html, Some("img"), config).unwrap_err();
+        assert!(error.to_string().contains("Invalid base URL"));
+    }
+
+    #[test]
+    fn test_render_to_writers() {
+        let html = r#"
+            <template>
+                <ul>
+                    <li itemprop="items[]">${name} &amp; co</li>
+                </ul>
+                <p itemprop="note"></p>
+            </template>
+        "#;
+        let data = serde_json::json!({
+            "items": [{"name": "Ada"}, {"name": "Grace <3"}],
+            "note": "Café"
+        });
+        let template = HtmlTemplate::from_str(html, None).unwrap();
+        let expected = template.render(&data).unwrap();
+
+        let mut bytes = Vec::new();
+        template.render_to(&data, &mut bytes).unwrap();
+        assert_eq!(String::from_utf8(bytes).unwrap(), expected);
+
+        let mut text = String::from("<!-- page -->");
+        template.render_to_fmt(&data, &mut text).unwrap();
+        assert_eq!(text, format!("<!-- page -->{}", expected));
+
+        struct FailingWriter;
+        impl std::io::Write for FailingWriter {
+            fn write(&mut self, _buf: &[u8]) -> std::io::Result<usize> {
+                Err(std::io::Error::new(
+                    std::io::ErrorKind::BrokenPipe,
+                    "closed",
+                ))
+            }
+            fn flush(&mut self) -> std::io::Result<()> {
+                Ok(())
+            }
+        }
+        let error = template.render_to(&data, &mut FailingWriter).unwrap_err();
+        assert!(
+            matches!(error, Error::IoError(ref e) if e.kind() == std::io::ErrorKind::BrokenPipe)
+        );
+    }
+
+    #[test]
+    fn test_render_document() {
+        let html = r#"
+            <template>
+                <h1 itemprop="title"></h1>
+                <ul>
+                    <li itemprop="items[]">${name}</li>
+                </ul>
+            </template>
+        "#;
+        let data = serde_json::json!({
+            "title": "Team",
+            "items": [{"name": "Ada"}, {"name": "Grace"}]
+        });
+        let template = HtmlTemplate::from_str(html, None).unwrap();
+
+        let document = template.render_document(&data).unwrap();
+        assert_eq!(document.html().to_string(), template.render(&data).unwrap());
+        assert_eq!(document.select("li").length(), 2);
+    }
+
+    #[test]
+    fn test_render_into_positions() {
+        let html = r#"<template><p itemprop="text"></p></template>"#;
+        let template = HtmlTemplate::from_str(html, None).unwrap();
+        let data = serde_json::json!({"text": "new"});
+        let page = || Document::from("<main><p>old</p></main>");
+        let main_html = |page: &Document| page.select("main").html().to_string();
+
+        let appended = page();
+        let main = appended.select("main").nodes()[0].clone();
+        template
+            .render_into(&data, &main, RenderPosition::Append)
+            .unwrap();
+        assert_eq!(
+            main_html(&appended),
+            r#"<main><p>old</p><p itemprop="text">new</p></main>"#
+        );
+
+        let prepended = page();
+        let main = prepended.select("main").nodes()[0].clone();
+        template
+            .render_into(&data, &main, RenderPosition::Prepend)
+            .unwrap();
+        assert_eq!(
+            main_html(&prepended),
+            r#"<main><p itemprop="text">new</p><p>old</p></main>"#
+        );
+
+        let replaced = page();
+        let old = replaced.select("main p").nodes()[0].clone();
+        template
+            .render_into(&data, &old, RenderPosition::Replace)
+            .unwrap();
+        assert_eq!(
+            main_html(&replaced),
+            r#"<main><p itemprop="text">new</p></main>"#
+        );
+
+        let detached = Document::default();
+        let error = template
+            .render_into(&data, &detached.root(), RenderPosition::Replace)
+            .unwrap_err();
+        assert!(error.to_string().contains("no parent"));
+    }
+
+    #[test]
+    fn test_render_into_table_body() {
+        // Rows would be dropped if rendered HTML were re-parsed outside a table
+        let html = r#"
+            <template>
+                <table><tbody>
+                    <tr itemprop="rows[]"><td itemprop="name"></td></tr>
+                </tbody></table>
+            </template>
+        "#;
+        let template = HtmlTemplate::from_str(html, Some("tr")).unwrap();
+        let data = serde_json::json!({"rows": [{"name": "Ada"}, {"name": "Grace"}]});
+
+        let page = Document::from("<table><tbody></tbody></table>");
+        let body = page.select("tbody").nodes()[0].clone();
+        template
+            .render_into(&data, &body, RenderPosition::Append)
+            .unwrap();
+        assert_eq!(page.select("tbody > tr > td").length(), 2);
+        assert_eq!(page.select("td").text().to_string(), "AdaGrace");
+    }
+
+    #[test]
+    fn test_render_into_with_handlers() {
+        let html = r#"<template><input itemprop="name"></template>"#;
+        let template = crate::HtmlTemplateBuilder::new()
+            .from_str(html)
+            .with_default_handlers()
+            .build()
+            .unwrap();
+        let data = serde_json::json!({"name": "Ada"});
+
+        let page = Document::from("<form></form>");
+        let form = page.select("form").nodes()[0].clone();
+        template
+            .render_into(&data, &form, RenderPosition::Append)
+            .unwrap();
+        assert_eq!(
+            page.select("form input").attr("value").unwrap().as_ref(),
+            "Ada"
+        );
+    }
 }
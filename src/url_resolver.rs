@@ -0,0 +1,164 @@
+//! Relative URL resolution for microdata values and rendered attributes
+//!
+//! Microdata URL properties are resolved against the source document's base
+//! URL, as the WHATWG microdata specification requires. Rendered templates can
+//! optionally resolve their URL attributes against a configured base.
+
+use std::borrow::Cow;
+
+use dom_query::Selection;
+use url::Url;
+
+use crate::error::{Error, Result};
+
+/// Attributes that hold a URL on any element
+const URL_ATTRIBUTES: &[&str] = &["href", "src", "action", "formaction", "poster", "cite"];
+
+/// Check if an attribute of the given element holds a URL
+pub fn is_url_attribute(tag_name: &str, attr_name: &str) -> bool {
+    URL_ATTRIBUTES.contains(&attr_name)
+        || (attr_name == "data" && tag_name.eq_ignore_ascii_case("object"))
+}
+
+/// Resolve a possibly relative URL reference against a base
+///
+/// Absolute URLs, fragment-only references and values still containing
+/// `${...}` variables are returned unchanged, as is any reference when there
+/// is no base.
+pub fn resolve<'a>(base: Option<&Url>, reference: &'a str) -> Cow<'a, str> {
+    let base = match base {
+        Some(base) => base,
+        None => return Cow::Borrowed(reference),
+    };
+
+    let trimmed = reference.trim();
+    if trimmed.is_empty()
+        || trimmed.starts_with('#')
+        || trimmed.contains("${")
+        || Url::parse(trimmed).is_ok()
+    {
+        return Cow::Borrowed(reference);
+    }
+
+    match base.join(trimmed) {
+        Ok(resolved) => Cow::Owned(resolved.into()),
+        Err(_) => Cow::Borrowed(reference),
+    }
+}
+
+/// Determine the base URL of the document containing `node`
+///
+/// The first `<base href>` is resolved against `document_url` when given;
+/// without a `<base>` element the document URL itself is the base.
+pub fn document_base_url(node: &dom_query::Node, document_url: Option<&Url>) -> Option<Url> {
+    let root = Selection::from(node.tree.root());
+    let base_href = root
+        .select("base[href]")
+        .nodes()
+        .first()
+        .and_then(|base| base.attr("href"))
+        .map(|href| href.trim().to_string());
+
+    match (base_href, document_url) {
+        (Some(href), Some(document_url)) => document_url
+            .join(&href)
+            .ok()
+            .or_else(|| Some(document_url.clone())),
+        (Some(href), None) => Url::parse(&href).ok(),
+        (None, document_url) => document_url.cloned(),
+    }
+}
+
+/// Combine a configured base URL with a template's own `<base href>`
+///
+/// The template base is resolved against the configured one. Without a
+/// configured base URL, URLs are left as they are and the template base is
+/// ignored.
+pub fn template_base_url(
+    configured: Option<&str>,
+    template_base: Option<&str>,
+) -> Result<Option<Url>> {
+    let configured = configured
+        .map(|url| {
+            Url::parse(url)
+                .map_err(|e| Error::render_owned(format!("Invalid base URL '{}': {}", url, e)))
+        })
+        .transpose()?;
+
+    Ok(match (configured, template_base) {
+        (Some(configured), Some(template_base)) => {
+            Some(configured.join(template_base).unwrap_or(configured))
+        }
+        (Some(configured), None) => Some(configured),
+        (None, _) => None,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use dom_query::Document;
+
+    #[test]
+    fn test_resolve() {
+        let base = Url::parse("https://example.com/blog/post/").unwrap();
+
+        assert_eq!(
+            resolve(Some(&base), "image.jpg"),
+            "https://example.com/blog/post/image.jpg"
+        );
+        assert_eq!(
+            resolve(Some(&base), "../about"),
+            "https://example.com/blog/about"
+        );
+        assert_eq!(resolve(Some(&base), "/root"), "https://example.com/root");
+        assert_eq!(
+            resolve(Some(&base), "https://other.org"),
+            "https://other.org"
+        );
+        assert_eq!(resolve(Some(&base), "#top"), "#top");
+        assert_eq!(resolve(Some(&base), "${url}"), "${url}");
+        assert_eq!(resolve(None, "image.jpg"), "image.jpg");
+    }
+
+    #[test]
+    fn test_document_base_url() {
+        let doc = Document::from(
+            r#"<html><head><base href="/assets/"></head><body><p>x</p></body></html>"#,
+        );
+        let node = doc.select("p").nodes()[0].clone();
+        let page = Url::parse("https://example.com/page.html").unwrap();
+
+        assert_eq!(
+            document_base_url(&node, Some(&page)).unwrap().as_str(),
+            "https://example.com/assets/"
+        );
+        // A relative <base> cannot be used without the document URL
+        assert_eq!(document_base_url(&node, None), None);
+
+        let doc = Document::from("<p>x</p>");
+        let node = doc.select("p").nodes()[0].clone();
+        assert_eq!(document_base_url(&node, Some(&page)), Some(page));
+    }
+
+    #[test]
+    fn test_template_base_url() {
+        let base = template_base_url(Some("https://cdn.example.com/"), Some("v2/")).unwrap();
+        assert_eq!(base.unwrap().as_str(), "https://cdn.example.com/v2/");
+
+        // The template base alone doesn't opt in to resolving URLs
+        let base = template_base_url(None, Some("https://example.com/app/")).unwrap();
+        assert_eq!(base, None);
+        assert_eq!(template_base_url(None, Some("relative/")).unwrap(), None);
+        assert!(template_base_url(Some("not a url"), None).is_err());
+    }
+
+    #[test]
+    fn test_is_url_attribute() {
+        assert!(is_url_attribute("a", "href"));
+        assert!(is_url_attribute("img", "src"));
+        assert!(is_url_attribute("object", "data"));
+        assert!(!is_url_attribute("div", "data"));
+        assert!(!is_url_attribute("a", "title"));
+    }
+}
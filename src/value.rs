@@ -206,6 +206,17 @@ pub trait RenderValue {
         // Implementations can override to provide nested value access
         None
     }
+
+    /// Get this value as JSON, if it is backed by JSON
+    ///
+    /// Constraint expressions use this to compare numbers, booleans, null,
+    /// arrays and objects by type. The default returns `None`, in which case
+    /// constraints see the string values from [`get_property`].
+    ///
+    /// [`get_property`]: RenderValue::get_property
+    fn as_json(&self) -> Option<&JsonValue> {
+        None
+    }
 }
 
 impl RenderValue for JsonValue {
@@ -296,6 +307,10 @@ impl RenderValue for JsonValue {
 
         Some(current)
     }
+
+    fn as_json(&self) -> Option<&JsonValue> {
+        Some(self)
+    }
 }
 
 impl RenderValue for String {
@@ -431,7 +446,7 @@ impl<'a, T: Serialize> SerializeWrapper<'a, T> {
         }
     }
 
-    fn as_json(&self) -> &JsonValue {
+    fn json(&self) -> &JsonValue {
         self.cached_json
             .get_or_init(|| serde_json::to_value(self.value).unwrap_or(JsonValue::Null))
     }
@@ -439,11 +454,11 @@ impl<'a, T: Serialize> SerializeWrapper<'a, T> {
 
 impl<'a, T: Serialize> RenderValue for SerializeWrapper<'a, T> {
     fn get_property(&self, path: &[String]) -> Option<Cow<str>> {
-        self.as_json().get_property(path)
+        self.json().get_property(path)
     }
 
     fn is_array(&self) -> bool {
-        self.as_json().is_array()
+        self.json().is_array()
     }
 
     fn as_array(&self) -> Option<Vec<&dyn RenderValue>> {
@@ -452,11 +467,15 @@ impl<'a, T: Serialize> RenderValue for SerializeWrapper<'a, T> {
     }
 
     fn get_type(&self) -> Option<&str> {
-        self.as_json().get_type()
+        self.json().get_type()
     }
 
     fn get_id(&self) -> Option<&str> {
-        self.as_json().get_id()
+        self.json().get_id()
+    }
+
+    fn as_json(&self) -> Option<&JsonValue> {
+        Some(self.json())
     }
 }
 
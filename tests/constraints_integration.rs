@@ -2,7 +2,10 @@
 //!
 //! These tests verify data-constraint and data-scope attribute handling.
 
-use html_template::HtmlTemplate;
+use html_template::{
+    ConstraintAttributes, ConstraintContext, ConstraintEvaluator, ConstraintWarningKind, Error,
+    HtmlTemplate, HtmlTemplateBuilder, Result,
+};
 use serde_json::json;
 
 #[test]
@@ -263,15 +266,57 @@ fn test_data_scope_attributes() {
 
     let template = HtmlTemplate::from_str(html, None).unwrap();
 
-    // This test might depend on how scope is implemented
-    // For now, we'll test basic rendering
     let data = json!({
         "userRole": "admin"
     });
 
+    // Without scopes every section renders
     let result = template.render(&data).unwrap();
     assert!(result.contains("Dashboard"));
-    // Scope handling might filter these based on implementation
+    assert!(result.contains("Admin controls"));
+    assert!(result.contains("User dashboard"));
+
+    let result = template.render_with_scopes(&data, &["admin"]).unwrap();
+    assert!(result.contains("Dashboard"));
+    assert!(result.contains("Admin controls"));
+    assert!(!result.contains("User dashboard"));
+
+    let result = template.render_with_scopes::<&str>(&data, &[]).unwrap();
+    assert!(result.contains("Dashboard"));
+    assert!(!result.contains("Admin controls"));
+    assert!(!result.contains("User dashboard"));
+}
+
+#[test]
+fn test_data_scope_in_array_items() {
+    use html_template::RenderBuilder;
+
+    let html = r#"
+        <template>
+            <div>
+                <div itemprop="articles[]" class="article">
+                    <h3 itemprop="title"></h3>
+                    <button data-scope="editor">Edit</button>
+                    <span data-scope="beta">New layout</span>
+                </div>
+            </div>
+        </template>
+    "#;
+
+    let template = HtmlTemplate::from_str(html, None).unwrap();
+
+    let data = json!({
+        "articles": [{"title": "One"}, {"title": "Two"}]
+    });
+
+    let result = RenderBuilder::new(&template)
+        .with_scopes(["admin", "beta"])
+        .render(&data)
+        .unwrap();
+    assert!(result.contains("One"));
+    assert!(result.contains("Two"));
+    assert!(!result.contains("Edit"));
+    assert_eq!(result.matches("New layout").count(), 2);
 }
 
 #[test]
@@ -352,3 +397,459 @@ fn test_constraints_with_null_values() {
     assert!(!result.contains("Null is truthy"));
     assert!(result.contains("Null is falsy"));
 }
+
+#[test]
+fn test_grouped_constraint_expressions() {
+    let html = r#"
+        <template>
+            <div>
+                <div data-constraint="member && (admin || editor)">
+                    <p>Can edit</p>
+                </div>
+                <div data-constraint="!admin && member">
+                    <p>Regular member</p>
+                </div>
+                <div data-constraint="title == 'Q&amp;A && more > less'">
+                    <p>Literal operators</p>
+                </div>
+                <div data-constraint="tags[1] == 'rust'">
+                    <p>Indexed tag</p>
+                </div>
+            </div>
+        </template>
+    "#;
+
+    let template = HtmlTemplate::from_str(html, None).unwrap();
+
+    let data = json!({
+        "member": true,
+        "admin": false,
+        "editor": true,
+        "title": "Q&A && more > less",
+        "tags": ["html", "rust"]
+    });
+
+    let result = template.render(&data).unwrap();
+    assert!(result.contains("Can edit"));
+    assert!(result.contains("Regular member"));
+    assert!(result.contains("Literal operators"));
+    assert!(result.contains("Indexed tag"));
+}
+
+#[test]
+fn test_constraint_syntax_errors_fail_compilation() {
+    let html = r#"
+        <template>
+            <div>
+                <div data-constraint="premium && (credits > 0">
+                    <p>Premium</p>
+                </div>
+            </div>
+        </template>
+    "#;
+
+    let error = HtmlTemplate::from_str(html, None).unwrap_err();
+    assert!(matches!(error, html_template::Error::ConstraintError(_)));
+    assert!(error.to_string().contains("column"));
+}
+
+#[test]
+fn test_typed_constraint_comparisons() {
+    let html = r#"
+        <template>
+            <div>
+                <div data-constraint="items.length > 0">
+                    <p>Has items</p>
+                </div>
+                <div data-constraint="status in ['draft', 'review']">
+                    <p>Not yet published</p>
+                </div>
+                <div data-constraint="price > 10">
+                    <p>Expensive</p>
+                </div>
+                <div data-constraint="published >= '2024-01-01'">
+                    <p>Recent</p>
+                </div>
+                <div data-constraint="discount == null">
+                    <p>Missing is not null</p>
+                </div>
+            </div>
+        </template>
+    "#;
+
+    let template = HtmlTemplate::from_str(html, None).unwrap();
+
+    let data = json!({
+        "items": ["a"],
+        "status": "review",
+        "price": 9.5,
+        "published": "2024-03-15T10:00:00Z"
+    });
+
+    let result = template.render(&data).unwrap();
+    assert!(result.contains("Has items"));
+    assert!(result.contains("Not yet published"));
+    assert!(!result.contains("Expensive"));
+    assert!(result.contains("Recent"));
+    assert!(!result.contains("Missing is not null"));
+}
+
+#[test]
+fn test_constraint_functions() {
+    use html_template::{ConstraintFunctionRegistry, HtmlTemplateBuilder};
+
+    let html = r#"
+        <template>
+            <div>
+                <div data-constraint="contains(tags, 'sale')">
+                    <p>On sale</p>
+                </div>
+                <div data-constraint="startsWith(url, 'https') && !empty(url)">
+                    <p>Secure link</p>
+                </div>
+                <div data-constraint="discounted(price, 0.5) < 20">
+                    <p>Bargain</p>
+                </div>
+            </div>
+        </template>
+    "#;
+
+    let mut functions = ConstraintFunctionRegistry::with_defaults();
+    functions.register("discounted", |args| {
+        let price = args[0].as_f64().unwrap_or(0.0);
+        let rate = args[1].as_f64().unwrap_or(0.0);
+        Ok(json!(price * (1.0 - rate)))
+    });
+
+    let template = HtmlTemplateBuilder::new()
+        .from_str(html)
+        .with_constraint_functions(functions)
+        .build()
+        .unwrap();
+
+    let data = json!({
+        "tags": ["new", "sale"],
+        "url": "https://example.com",
+        "price": 30
+    });
+
+    let result = template.render(&data).unwrap();
+    assert!(result.contains("On sale"));
+    assert!(result.contains("Secure link"));
+    assert!(result.contains("Bargain"));
+
+    // Without the registry the unknown function fails the render
+    let plain = HtmlTemplate::from_str(html, None).unwrap();
+    let error = plain.render(&data).unwrap_err();
+    assert!(matches!(error, html_template::Error::ConstraintError(_)));
+    assert!(error
+        .to_string()
+        .contains("Unknown constraint function 'discounted'"));
+}
+
+#[test]
+fn test_else_if_chains() {
+    let html = r#"
+        <template>
+            <div>
+                <div itemprop="orders[]" class="order">
+                    <h3 itemprop="id"></h3>
+                    <p data-constraint="status == 'shipped'">Shipped</p>
+                    <p data-else-if="status == 'packed'">Packed</p>
+                    <p data-else-if="status in ['new', 'packed']">Received</p>
+                    <p data-else>Unknown status</p>
+                </div>
+                <p data-constraint="orders.length > 2">Busy</p>
+                <p data-else>Quiet</p>
+            </div>
+        </template>
+    "#;
+
+    let template = HtmlTemplate::from_str(html, None).unwrap();
+
+    let data = json!({
+        "orders": [
+            {"id": "A1", "status": "shipped"},
+            {"id": "B2", "status": "packed"},
+            {"id": "C3", "status": "new"},
+            {"id": "D4", "status": "lost"}
+        ]
+    });
+
+    let result = template.render(&data).unwrap();
+    let branches: Vec<&str> = ["Shipped", "Packed", "Received", "Unknown status"]
+        .into_iter()
+        .filter(|branch| result.contains(&format!(">{}<", branch)))
+        .collect();
+
+    // Exactly one branch renders per order, the first that holds
+    assert_eq!(
+        branches,
+        ["Shipped", "Packed", "Received", "Unknown status"]
+    );
+    assert_eq!(result.matches("Packed").count(), 1);
+    assert_eq!(result.matches("Received").count(), 1);
+    assert!(result.contains("Busy"));
+    assert!(!result.contains("Quiet"));
+}
+
+#[test]
+fn test_orphan_else_fails_compilation() {
+    let orphan = r#"
+        <template>
+            <div>
+                <p>Always</p>
+                <p data-else>Never</p>
+            </div>
+        </template>
+    "#;
+
+    let error = HtmlTemplate::from_str(orphan, None).unwrap_err();
+    assert!(matches!(error, html_template::Error::ParseError(_)));
+    assert!(error.to_string().contains("data-else"));
+
+    let separated = r#"
+        <template>
+            <div>
+                <p data-constraint="a">A</p>
+                <hr>
+                <p data-else-if="b">B</p>
+            </div>
+        </template>
+    "#;
+    assert!(HtmlTemplate::from_str(separated, None).is_err());
+}
+
+#[test]
+fn test_else_after_array_item_fails_compilation() {
+    let after_item = r#"
+        <template>
+            <ul>
+                <li itemprop="items[]" data-constraint="visible"><span itemprop="name"></span></li>
+                <li data-else>No visible items</li>
+            </ul>
+        </template>
+    "#;
+
+    let error = HtmlTemplate::from_str(after_item, None).unwrap_err();
+    assert!(matches!(error, html_template::Error::ParseError(_)));
+    assert!(error.to_string().contains("cannot follow array item <li>"));
+
+    let on_item = r#"
+        <template>
+            <ul>
+                <li data-constraint="ready">Ready</li>
+                <li itemprop="items[]" data-else-if="pending"><span itemprop="name"></span></li>
+            </ul>
+        </template>
+    "#;
+
+    let error = HtmlTemplate::from_str(on_item, None).unwrap_err();
+    assert!(error
+        .to_string()
+        .contains("cannot be used on array item <li>"));
+}
+
+#[test]
+fn test_else_on_root_element_fails_compilation() {
+    // Root elements are always rendered, so a chain among them can't pick a branch
+    let html = r#"<template><p data-constraint="x">A</p><p data-else>B</p></template>"#;
+    let error = HtmlTemplate::from_str(html, None).unwrap_err();
+    assert!(matches!(error, html_template::Error::ParseError(_)));
+    assert!(error
+        .to_string()
+        .contains("data-else cannot be used on root element <p>"));
+
+    let html = r#"<template><p data-constraint="x">A</p><p data-else-if="y">B</p></template>"#;
+    assert!(HtmlTemplate::from_str(html, Some("p")).is_err());
+}
+
+#[test]
+fn test_constraints_reference_items_by_id() {
+    let html = r#"
+        <template>
+            <div>
+                <div itemprop="posts[]" class="post">
+                    <h3 itemprop="title"></h3>
+                    <div itemprop="meta" itemscope>
+                        <span data-constraint="@user-42.role == 'admin'">Moderate</span>
+                    </div>
+                    <span data-constraint="@user-42.name == author">Yours</span>
+                </div>
+            </div>
+        </template>
+    "#;
+
+    let template = HtmlTemplate::from_str(html, None).unwrap();
+
+    let data = json!({
+        "viewer": {"@id": "user-42", "name": "Ada", "role": "admin"},
+        "posts": [
+            {"title": "First", "author": "Ada", "meta": {}},
+            {"title": "Second", "author": "Grace", "meta": {}}
+        ]
+    });
+
+    let result = template.render(&data).unwrap();
+    assert_eq!(result.matches("Moderate").count(), 2);
+    assert_eq!(result.matches("Yours").count(), 1);
+}
+
+#[test]
+fn test_unknown_item_id_fails_render() {
+    let html = r#"
+        <template>
+            <div>
+                <div itemprop="posts[]">
+                    <span data-constraint="@user-99.role == 'admin'">Moderate</span>
+                </div>
+            </div>
+        </template>
+    "#;
+
+    let template = HtmlTemplate::from_str(html, None).unwrap();
+    let data = json!({
+        "viewer": {"@id": "user-42", "role": "admin"},
+        "posts": [{"title": "First"}]
+    });
+
+    let error = template.render(&data).unwrap_err();
+    assert!(matches!(error, html_template::Error::ConstraintError(_)));
+    assert!(error.to_string().contains("Unknown item id '@user-99'"));
+
+    // Without items to test, the constraint is never evaluated
+    let empty = json!({"viewer": {"@id": "user-42"}, "posts": []});
+    assert!(template.render(&empty).is_ok());
+}
+
+/// Shows an element when `key=value` matches the data, for every `;`-separated pair
+struct PairEvaluator;
+
+impl ConstraintEvaluator for PairEvaluator {
+    fn evaluate(&self, expression: &str, context: &ConstraintContext<'_>) -> Result<bool> {
+        Ok(expression.split(';').all(|pair| {
+            let (key, value) = pair.split_once('=').unwrap_or((pair, "true"));
+            context
+                .data()
+                .get_property(&[key.trim().to_string()])
+                .as_deref()
+                == Some(value.trim())
+        }))
+    }
+
+    fn validate(&self, expression: &str) -> Result<()> {
+        if expression.trim().is_empty() {
+            return Err(Error::constraint_static("Empty constraint"));
+        }
+        Ok(())
+    }
+}
+
+#[test]
+fn test_custom_constraint_evaluator() {
+    let html = r#"
+        <template>
+            <div>
+                <div itemprop="users[]" class="user">
+                    <span itemprop="name"></span>
+                    <em data-if="role=admin; active">Admin</em>
+                    <em data-elif="role=admin">Inactive admin</em>
+                    <em data-otherwise>Member</em>
+                </div>
+                <p data-constraint="users.length > 1">Plain attribute</p>
+            </div>
+        </template>
+    "#;
+
+    let template = HtmlTemplateBuilder::new()
+        .from_str(html)
+        .with_constraint_attributes(ConstraintAttributes::new(
+            "data-if",
+            "data-elif",
+            "data-otherwise",
+        ))
+        .with_constraint_evaluator(PairEvaluator)
+        .build()
+        .unwrap();
+
+    let data = json!({
+        "users": [
+            {"name": "Ada", "role": "admin", "active": true},
+            {"name": "Grace", "role": "admin", "active": false},
+            {"name": "Linus", "role": "user", "active": true}
+        ]
+    });
+
+    let result = template.render(&data).unwrap();
+    assert_eq!(result.matches(">Admin<").count(), 1);
+    assert_eq!(result.matches(">Inactive admin<").count(), 1);
+    assert_eq!(result.matches(">Member<").count(), 1);
+    // data-constraint is no longer a constraint attribute
+    assert!(result.contains("Plain attribute"));
+
+    // The same markup compiled with the default attributes is not served
+    // from the cache entry above
+    let default = HtmlTemplate::from_str(html, None).unwrap();
+    let result = default.render(&data).unwrap();
+    assert_eq!(result.matches(">Admin<").count(), 3);
+
+    // Expressions are validated by the evaluator when the template is compiled
+    let invalid = r#"<template><div><p data-if=" ">Never</p></div></template>"#;
+    let error = HtmlTemplateBuilder::new()
+        .from_str(invalid)
+        .with_constraint_attributes(ConstraintAttributes::new(
+            "data-if",
+            "data-elif",
+            "data-otherwise",
+        ))
+        .with_constraint_evaluator(PairEvaluator)
+        .build()
+        .unwrap_err();
+    assert!(error.to_string().contains("Empty constraint"));
+}
+
+#[test]
+fn test_check_constraints() {
+    let html = r#"
+        <template>
+            <div>
+                <h1 itemprop="title"></h1>
+                <p itemprop="description" data-constraint="descripton">${description}</p>
+                <p data-constraint="description && 1 < 2">Both</p>
+                <span data-constraint="rating >= true">Rated</span>
+                <a href="${link.url}" data-constraint="link.url">More</a>
+            </div>
+        </template>
+    "#;
+
+    let template = HtmlTemplate::from_str(html, None).unwrap();
+    let warnings: Vec<String> = template
+        .check_constraints()
+        .iter()
+        .map(ToString::to_string)
+        .collect();
+
+    assert_eq!(
+        warnings,
+        [
+            "constraint 'description && 1 < 2': `1 < 2` is always true",
+            "constraint 'descripton': `descripton` is not bound by any itemprop or ${} path; did you mean `description`?",
+            "constraint 'rating >= true': `rating >= true` compares incompatible values and is always false",
+        ]
+    );
+
+    // Against sample data, paths are looked up in the data instead
+    let sample = json!({
+        "title": "Post",
+        "link": {"url": "/more"}
+    });
+    let warnings = template.check_constraints_against(&sample);
+    let missing: Vec<_> = warnings
+        .iter()
+        .filter_map(|warning| match &warning.kind {
+            ConstraintWarningKind::MissingPath { path } => Some(path.as_str()),
+            _ => None,
+        })
+        .collect();
+    assert_eq!(missing, ["description", "descripton"]);
+}
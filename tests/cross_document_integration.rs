@@ -3,7 +3,10 @@
 //! These tests verify that templates can render content from external HTML documents
 //! using microdata extraction and cross-document rendering methods.
 
-use html_template::{HtmlTemplate, HtmlTemplateBuilder};
+use html_template::{
+    ConflictPolicy, CrossDocumentRenderer, CrossDocumentTemplate, DataSource, DocumentFetcher,
+    HtmlTemplate, HtmlTemplateBuilder, MemorySource,
+};
 use serde_json::json;
 
 #[test]
@@ -144,7 +147,7 @@ fn test_cross_document_with_special_elements() {
                 <p>Published: <span itemprop="datePublished"></span></p>
                 <p>Modified: <span itemprop="dateModified"></span></p>
                 <p>URL: <a href="${url}" itemprop="url">Read more</a></p>
-                <img itemprop="image" alt="Article image">
+                <img itemprop="image" src="${image}" alt="Article image">
             </article>
         </template>
     "#;
@@ -334,3 +337,183 @@ fn test_cross_document_with_complex_nesting() {
     assert!(result.contains("Senior Developer"));
     // Similarly, nested team name shows "Tech Corp" instead of "Development Team"
 }
+
+#[test]
+fn test_cross_document_from_memory_source() {
+    let fixtures = MemorySource::new()
+        .with_html(
+            "cms://team/alice",
+            r#"<div itemscope itemtype="https://schema.org/Person">
+                <span itemprop="name">Alice Johnson</span>
+                <span itemprop="jobTitle">Designer</span>
+            </div>"#,
+        )
+        .with_html(
+            "cms://team/bob",
+            r#"<div itemscope itemtype="https://schema.org/Person">
+                <span itemprop="name">Bob Wilson</span>
+                <span itemprop="jobTitle">Developer</span>
+            </div>"#,
+        );
+
+    let template_html = r#"
+        <template>
+            <div class="person">
+                <h3 itemprop="name"></h3>
+                <p itemprop="jobTitle"></p>
+            </div>
+        </template>
+    "#;
+    let template = HtmlTemplate::from_str(template_html, Some("div.person")).unwrap();
+
+    // Route the custom scheme through the default fetcher
+    let mut fetcher = DocumentFetcher::new();
+    fetcher.register_source("cms", fixtures);
+    let renderer = CrossDocumentRenderer::with_fetcher(fetcher);
+
+    let results = renderer
+        .render_from_multiple_urls(&template, &["cms://team/alice", "cms://team/bob"])
+        .unwrap();
+    assert_eq!(results.len(), 2);
+    assert!(results[0].contains("Alice Johnson"));
+    assert!(results[1].contains("Developer"));
+
+    // The same source can back a cross-document template
+    let mut cross_doc_template = CrossDocumentTemplate::with_renderer(template, renderer);
+    cross_doc_template.add_data_source(DataSource::Url {
+        url: "cms://team/bob".to_string(),
+        selector: None,
+    });
+    cross_doc_template.add_data_source(DataSource::Static {
+        data: json!({"name": "Carol Brown", "jobTitle": "Manager"}),
+    });
+
+    let rendered = cross_doc_template.render().unwrap();
+    assert_eq!(rendered.len(), 2);
+    assert!(rendered[0].contains("Bob Wilson"));
+    assert!(rendered[1].contains("Carol Brown"));
+}
+
+#[test]
+fn test_cross_document_resolves_relative_urls() {
+    let source = MemorySource::new().with_html(
+        "https://example.com/team/index.html",
+        r#"<div itemscope itemtype="https://schema.org/Person">
+            <span itemprop="name">Alice Johnson</span>
+            <a itemprop="url" href="alice.html">Profile</a>
+            <img itemprop="image" src="/photos/alice.jpg">
+        </div>"#,
+    );
+
+    let template_html = r#"
+        <template>
+            <div class="person">
+                <a itemprop="url" href="${url}"><span itemprop="name"></span></a>
+                <img itemprop="image" src="${image}">
+            </div>
+        </template>
+    "#;
+    let template = HtmlTemplate::from_str(template_html, Some("div.person")).unwrap();
+    let renderer = CrossDocumentRenderer::with_source(source);
+
+    let results = renderer
+        .render_from_url(&template, "https://example.com/team/index.html")
+        .unwrap();
+    assert_eq!(results.len(), 1);
+    assert!(results[0].contains(r#"href="https://example.com/team/alice.html""#));
+    assert!(results[0].contains(r#"src="https://example.com/photos/alice.jpg""#));
+}
+
+#[test]
+fn test_cross_document_template_merges_sources() {
+    let source = MemorySource::new()
+        .with_html(
+            "https://shop.example/product/1",
+            r#"<div itemscope itemtype="https://schema.org/Product">
+                <h1 itemprop="name">Desk Lamp</h1>
+                <span itemprop="price">49.00</span>
+            </div>"#,
+        )
+        .with_html(
+            "https://reviews.example/product/1",
+            r#"<div itemscope itemtype="https://schema.org/Review">
+                <span itemprop="reviewBody">Bright and sturdy</span>
+            </div>
+            <div itemscope itemtype="https://schema.org/Review">
+                <span itemprop="reviewBody">Great value</span>
+            </div>"#,
+        );
+
+    let template_html = r#"
+        <template>
+            <main>
+                <header><span itemprop="siteName"></span></header>
+                <section itemprop="product[]" itemscope>
+                    <h1 itemprop="name"></h1>
+                    <span itemprop="price"></span>
+                </section>
+                <ul>
+                    <li itemprop="reviews[]"><span itemprop="reviewBody"></span></li>
+                </ul>
+            </main>
+        </template>
+    "#;
+    let template = HtmlTemplate::from_str(template_html, Some("main")).unwrap();
+
+    let mut page = CrossDocumentTemplate::with_source(template, source)
+        .with_conflict_policy(ConflictPolicy::Error);
+    page.add_data_source(DataSource::url("https://shop.example/product/1").mount_at("product"));
+    page.add_data_source(DataSource::url("https://reviews.example/product/1").mount_at("reviews"));
+    page.add_data_source(DataSource::Static {
+        data: json!({"siteName": "Lamp Shop"}),
+    });
+
+    let html = page.render_merged().unwrap();
+    assert!(html.contains("Lamp Shop"));
+    assert!(html.contains("Desk Lamp"));
+    assert!(html.contains("49.00"));
+    assert!(html.contains("Bright and sturdy"));
+    assert!(html.contains("Great value"));
+    assert_eq!(html.matches("<main").count(), 1);
+}
+
+#[test]
+fn test_cross_document_selects_items_on_mixed_pages() {
+    let source = MemorySource::new().with_html(
+        "https://shop.example/catalog",
+        r#"<nav itemscope itemtype="https://schema.org/SiteNavigationElement">
+            <span itemprop="name">Main menu</span>
+        </nav>
+        <div id="product-42" itemscope itemtype="https://schema.org/Product">
+            <span itemprop="name">Desk Lamp</span>
+        </div>
+        <div id="product-43" itemscope itemtype="https://schema.org/Product">
+            <span itemprop="name">Floor Lamp</span>
+        </div>"#,
+    );
+
+    let template_html = r#"
+        <template>
+            <div class="product"><span itemprop="name"></span></div>
+        </template>
+    "#;
+    let template = HtmlTemplate::from_str(template_html, Some("div.product")).unwrap();
+    let renderer = CrossDocumentRenderer::with_source(source);
+
+    let products = renderer
+        .render_from_url_with_type(
+            &template,
+            "https://shop.example/catalog",
+            "schema.org/Product",
+        )
+        .unwrap();
+    assert_eq!(products.len(), 2);
+    assert!(products[0].contains("Desk Lamp"));
+    assert!(products[1].contains("Floor Lamp"));
+
+    let single = renderer
+        .render_from_url(&template, "https://shop.example/catalog#product-43")
+        .unwrap();
+    assert_eq!(single.len(), 1);
+    assert!(single[0].contains("Floor Lamp"));
+}
@@ -358,3 +358,52 @@ fn test_nested_form_arrays() {
     assert!(result.contains(r#"type="password""#));
     assert!(result.contains(r#"name="password""#));
 }
+
+#[test]
+fn test_handlers_keep_rendered_output() {
+    // Handlers run on the rendered element, so attaching them only changes
+    // what the handlers themselves change
+    let html = r#"<template><form><input itemprop="q" value="old"></form></template>"#;
+    let data = json!({"q": "new"});
+
+    let plain = HtmlTemplate::from_str(html, Some("form")).unwrap();
+    let handled = HtmlTemplateBuilder::new()
+        .from_str(html)
+        .with_selector("form")
+        .with_default_handlers()
+        .build()
+        .unwrap();
+
+    let expected = r#"<form><input itemprop="q" value="new"></form>"#;
+    assert_eq!(plain.render(&data).unwrap(), expected);
+    assert_eq!(handled.render(&data).unwrap(), expected);
+}
+
+#[test]
+fn test_handlers_in_array_items_and_scopes() {
+    let html = r#"
+        <template>
+            <div>
+                <p itemprop="tags[]"><span itemprop="label"></span></p>
+                <section itemprop="author" itemscope><b itemprop="name"></b></section>
+            </div>
+        </template>
+    "#;
+    let template = HtmlTemplateBuilder::new()
+        .from_str(html)
+        .with_selector("div")
+        .register_handler("span", Box::new(html_template::ClassHandler::new()), 10)
+        .register_handler("b", Box::new(html_template::ClassHandler::new()), 10)
+        .build()
+        .unwrap();
+    let data = json!({
+        "tags": [{"label": "a"}, {"label": ""}],
+        "author": {"name": "Ada"}
+    });
+
+    let result = template.render(&data).unwrap();
+    assert!(result.contains(r#"<span itemprop="label" class="has-content">a</span>"#));
+    assert!(result.contains(r#"<span itemprop="label" class="empty"></span>"#));
+    assert!(result.contains(r#"<b itemprop="name" class="has-content">Ada</b>"#));
+    assert!(template.program().to_string().contains("handle label"));
+}